@@ -0,0 +1,66 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use html2md_confluence::{Converter, ParseOptions};
+use std::hint::black_box;
+use std::io;
+
+/// Builds a synthetic page with the given number of sections, each containing the usual mix of
+/// paragraphs, lists, tables and macros.
+fn synthetic_page(sections: usize) -> String {
+    let mut page = String::new();
+    for section in 0..sections {
+        page.push_str(&format!(
+            r#"<h2>Section {section}</h2>
+<p>Some <strong>bold</strong> and <em>emphasized</em> text&nbsp;with an <a href="https://example.com/{section}">external link</a> and <code>inline code</code>.</p>
+<ul><li>First item</li><li>Second item<ul><li>Nested &amp; escaped</li></ul></li></ul>
+<ol><li><p>Step one</p></li><li><p>Step two</p></li></ol>
+<table><tbody><tr><th>Key</th><th>Value</th></tr><tr><td>Row {section}</td><td><p>Cell with <em>markup</em></p></td></tr></tbody></table>
+<ac:structured-macro ac:name="info"><ac:parameter ac:name="title">Note {section}</ac:parameter><ac:rich-text-body><p>Admonition with a list:</p><ul><li>a</li><li>b</li></ul></ac:rich-text-body></ac:structured-macro>
+<ac:structured-macro ac:name="code"><ac:parameter ac:name="language">rust</ac:parameter><ac:plain-text-body><![CDATA[fn section_{section}() -> usize {{
+    {section}
+}}]]></ac:plain-text-body></ac:structured-macro>
+<ac:structured-macro ac:name="expand"><ac:rich-text-body><p>Hidden details</p></ac:rich-text-body></ac:structured-macro>
+<ac:task-list><ac:task><ac:task-status>incomplete</ac:task-status><ac:task-body>Follow up</ac:task-body></ac:task></ac:task-list>
+"#
+        ));
+    }
+    page
+}
+
+fn bench_convert(c: &mut Criterion) {
+    let converter = Converter::new(ParseOptions::default());
+    let mut group = c.benchmark_group("convert");
+    group.sample_size(10);
+    for sections in [100, 1_000, 5_000] {
+        let page = synthetic_page(sections);
+        group.throughput(Throughput::Bytes(page.len() as u64));
+        group.bench_with_input(BenchmarkId::new("str", sections), &page, |b, page| {
+            b.iter(|| converter.convert(black_box(page)))
+        });
+        group.bench_with_input(BenchmarkId::new("stream", sections), &page, |b, page| {
+            b.iter(|| {
+                converter
+                    .convert_stream(black_box(page.as_bytes()), io::sink())
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_convert);
+criterion_main!(benches);
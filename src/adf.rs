@@ -0,0 +1,272 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::document::{AdmonitionKind, Block, Inline};
+use crate::macros::info::admonition_block;
+use crate::macros::status::{StatusColour, render_status};
+use crate::macros::{DocumentContext, MacroContext};
+use crate::util::{find_child, get_tag_name, get_text_content};
+use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory, common::get_tag_attr};
+use std::str::FromStr;
+
+/// Returns the value of the `ac:adf-attribute` with the given key.
+pub fn get_adf_attribute(node: &Handle, key: &str) -> Option<String> {
+    node.children
+        .borrow()
+        .iter()
+        .filter(|child| get_tag_name(child).is_some_and(|name| name == "ac:adf-attribute"))
+        .find(|attribute| get_tag_attr(attribute, "key").is_some_and(|k| k == key))
+        .map(get_text_content)
+        .filter(|value| !value.is_empty())
+}
+
+fn panel_kind(panel_type: &str) -> AdmonitionKind {
+    match panel_type {
+        "info" => AdmonitionKind::Info,
+        "success" => AdmonitionKind::Tip,
+        "warning" | "error" => AdmonitionKind::Warning,
+        _ => AdmonitionKind::Note,
+    }
+}
+
+/// Handler for Confluence Cloud's `ac:adf-extension` elements.
+///
+/// Known node types are rendered natively. For all other types, only the `ac:adf-fallback`
+/// content is rendered, because the `ac:adf-node` itself is skipped.
+pub struct AdfExtensionHandler {
+    context: MacroContext,
+    handled: bool,
+}
+
+impl AdfExtensionHandler {
+    pub fn with_context(context: MacroContext) -> Self {
+        Self {
+            context,
+            handled: false,
+        }
+    }
+
+    fn handle_status(&self, node: &Handle, printer: &mut StructuredPrinter) {
+        let Some(title) = get_adf_attribute(node, "text") else {
+            return;
+        };
+        let colour = get_adf_attribute(node, "color")
+            .and_then(|colour| StatusColour::from_str(&colour).ok())
+            .unwrap_or_default();
+        let subtle = get_adf_attribute(node, "style").is_some_and(|style| style == "subtle");
+        printer.append_str(&render_status(
+            &title,
+            colour,
+            subtle,
+            self.context.status_style,
+        ));
+    }
+
+    fn render_content(&self, node: &Handle, printer: &StructuredPrinter) -> String {
+        find_child(node, "ac:adf-content")
+            .map(|content| self.context.renderer.render_children(&content, printer))
+            .unwrap_or_default()
+    }
+
+    fn decision_list(&self, node: &Handle, printer: &StructuredPrinter) -> Block {
+        let items = node
+            .children
+            .borrow()
+            .iter()
+            .filter(|child| {
+                get_tag_name(child).is_some_and(|name| name == "ac:adf-node")
+                    && get_tag_attr(child, "type").is_some_and(|t| t == "decision-item")
+            })
+            .map(|item| {
+                let decided = get_adf_attribute(item, "state").is_some_and(|s| s == "DECIDED");
+                let content = self.render_content(item, printer);
+                let paragraph = Block::Paragraph(vec![
+                    Inline::Strong(vec![Inline::Text("Decision:".to_string())]),
+                    Inline::Markdown(format!(" {content}")),
+                ]);
+                (decided, vec![paragraph])
+            })
+            .collect();
+        Block::TaskList { items }
+    }
+
+    fn panel(&self, node: &Handle, printer: &StructuredPrinter) -> Block {
+        let kind = get_adf_attribute(node, "panel-type")
+            .as_deref()
+            .map(panel_kind)
+            .unwrap_or(AdmonitionKind::Info);
+        let content = self.render_content(node, printer);
+        admonition_block(kind, None, content, printer)
+    }
+
+    fn expand(&self, node: &Handle, printer: &StructuredPrinter) -> Block {
+        let summary = get_adf_attribute(node, "title")
+            .unwrap_or_else(|| "Click here to expand...".to_string());
+        let content = self.render_content(node, printer);
+        Block::Details {
+            summary,
+            content: vec![Block::Markdown(content)],
+        }
+    }
+}
+
+impl TagHandler for AdfExtensionHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let Some(node) = find_child(tag, "ac:adf-node") else {
+            return;
+        };
+
+        let block = match get_tag_attr(&node, "type").as_deref() {
+            Some("status") => {
+                self.handle_status(&node, printer);
+                self.handled = true;
+                return;
+            }
+            Some("decision-list") => self.decision_list(&node, printer),
+            Some("panel") => self.panel(&node, printer),
+            Some("expand" | "nested-expand") => self.expand(&node, printer),
+            _ => return,
+        };
+        printer.insert_newline();
+        printer.insert_newline();
+        printer.append_str(&block.to_markdown(self.context.flavor));
+        printer.insert_newline();
+        printer.insert_newline();
+        self.handled = true;
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        self.handled
+    }
+}
+
+pub struct AdfExtensionHandlerFactory {
+    document: DocumentContext,
+}
+
+impl AdfExtensionHandlerFactory {
+    pub(crate) fn new(document: DocumentContext) -> Self {
+        Self { document }
+    }
+}
+
+impl TagHandlerFactory for AdfExtensionHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(AdfExtensionHandler::with_context(self.document.get()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{MarkdownFlavor, ParseOptions, markdown_assert_eq, parse_confluence};
+
+    #[test]
+    fn test_decision_list() {
+        markdown_assert_eq!(
+            r#"
+<ac:adf-extension>
+  <ac:adf-node type="decision-list">
+    <ac:adf-attribute key="local-id">1</ac:adf-attribute>
+    <ac:adf-node type="decision-item">
+      <ac:adf-attribute key="state">DECIDED</ac:adf-attribute>
+      <ac:adf-content>Use <strong>Rust</strong></ac:adf-content>
+    </ac:adf-node>
+    <ac:adf-node type="decision-item">
+      <ac:adf-attribute key="state">UNDECIDED</ac:adf-attribute>
+      <ac:adf-content>Pick a name</ac:adf-content>
+    </ac:adf-node>
+  </ac:adf-node>
+  <ac:adf-fallback><ul><li>Use Rust</li><li>Pick a name</li></ul></ac:adf-fallback>
+</ac:adf-extension>
+"#,
+            "\
+- [x] **Decision:** Use **Rust**
+- [ ] **Decision:** Pick a name"
+        );
+    }
+
+    #[test]
+    fn test_panel_with_nested_expand() {
+        markdown_assert_eq!(
+            r#"
+<ac:adf-extension>
+  <ac:adf-node type="panel">
+    <ac:adf-attribute key="panel-type">warning</ac:adf-attribute>
+    <ac:adf-content>
+      <p>Careful!</p>
+      <ac:adf-extension>
+        <ac:adf-node type="nested-expand">
+          <ac:adf-attribute key="title">Details</ac:adf-attribute>
+          <ac:adf-content><p>Hidden text.</p></ac:adf-content>
+        </ac:adf-node>
+        <ac:adf-fallback><p>Hidden text.</p></ac:adf-fallback>
+      </ac:adf-extension>
+    </ac:adf-content>
+  </ac:adf-node>
+  <ac:adf-fallback><p>Careful!</p><p>Hidden text.</p></ac:adf-fallback>
+</ac:adf-extension>
+"#,
+            "\
+> [!WARNING]
+>
+> Careful!
+>
+> <details><summary>Details</summary>
+>
+> Hidden text.
+>
+> </details>"
+        );
+    }
+
+    #[test]
+    fn test_expand_title() {
+        let html = r#"
+<ac:adf-extension>
+  <ac:adf-node type="expand">
+    <ac:adf-attribute key="title">&lt;b&gt;Details&lt;/b&gt;</ac:adf-attribute>
+    <ac:adf-content><p>Hidden text.</p></ac:adf-content>
+  </ac:adf-node>
+  <ac:adf-fallback><p>Hidden text.</p></ac:adf-fallback>
+</ac:adf-extension>
+"#;
+        assert_eq!(
+            parse_confluence(html, &ParseOptions::default()),
+            "<details><summary>&lt;b&gt;Details&lt;/b&gt;</summary>\n\nHidden text.\n\n</details>"
+        );
+        let options = ParseOptions::default().with_flavor(MarkdownFlavor::Plain);
+        assert_eq!(
+            parse_confluence(html, &options),
+            "**\\<b>Details\\</b>**\n\nHidden text."
+        );
+    }
+
+    #[test]
+    fn test_unknown_node_renders_fallback() {
+        markdown_assert_eq!(
+            r#"
+<ac:adf-extension>
+  <ac:adf-node type="layout-section">
+    <ac:adf-content><p>Column text</p></ac:adf-content>
+  </ac:adf-node>
+  <ac:adf-fallback><p>Column text</p></ac:adf-fallback>
+</ac:adf-extension>
+"#,
+            "Column text"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::index::PageIndex;
+use crate::manifest::debug_hash;
+use crate::util::{ConfluencePageId, ConfluenceServer, find_child};
+use html2md::{Handle, common::get_tag_attr};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+/// A file attached to a Confluence page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    filename: String,
+    media_type: Option<String>,
+    local_path: Option<String>,
+    size: Option<u64>,
+    author: Option<String>,
+    created: Option<String>,
+    comment: Option<String>,
+}
+
+impl Attachment {
+    pub fn new<S: Into<String>>(filename: S) -> Self {
+        Self {
+            filename: filename.into(),
+            media_type: None,
+            local_path: None,
+            size: None,
+            author: None,
+            created: None,
+            comment: None,
+        }
+    }
+
+    pub fn with_media_type<S: Into<String>>(mut self, media_type: S) -> Self {
+        self.media_type = Some(media_type.into());
+        self
+    }
+
+    /// Sets the path of the exported file, which is used instead of the Confluence download URL.
+    pub fn with_local_path<S: Into<String>>(mut self, local_path: S) -> Self {
+        self.local_path = Some(local_path.into());
+        self
+    }
+
+    /// Sets the file size in bytes.
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_author<S: Into<String>>(mut self, author: S) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    /// Sets the creation date, preferably in ISO 8601 format so that it sorts correctly.
+    pub fn with_created<S: Into<String>>(mut self, created: S) -> Self {
+        self.created = Some(created.into());
+        self
+    }
+
+    pub fn with_comment<S: Into<String>>(mut self, comment: S) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn media_type(&self) -> Option<&str> {
+        self.media_type.as_deref()
+    }
+
+    pub fn local_path(&self) -> Option<&str> {
+        self.local_path.as_deref()
+    }
+
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn created(&self) -> Option<&str> {
+        self.created.as_deref()
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Returns the file size in a human-readable format, e.g. `1.5 MB`.
+    pub fn human_readable_size(&self) -> Option<String> {
+        const UNITS: [&str; 4] = ["kB", "MB", "GB", "TB"];
+
+        let size = self.size?;
+        if size < 1024 {
+            return Some(format!("{size} B"));
+        }
+        let mut value = size as f64 / 1024.0;
+        let mut unit = UNITS[0];
+        for next_unit in &UNITS[1..] {
+            if value < 1024.0 {
+                break;
+            }
+            value /= 1024.0;
+            unit = next_unit;
+        }
+        Some(format!("{value:.1} {unit}"))
+    }
+
+    /// Returns the lowercase file extension.
+    pub fn extension(&self) -> Option<String> {
+        self.filename
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_ascii_lowercase())
+    }
+
+    pub fn is_image(&self) -> bool {
+        match self.media_type() {
+            Some(media_type) => media_type.starts_with("image/"),
+            None => self.extension().is_some_and(|extension| {
+                matches!(
+                    extension.as_str(),
+                    "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "bmp"
+                )
+            }),
+        }
+    }
+}
+
+/// Source of the attachments of a page, e.g. from a space export.
+pub trait AttachmentProvider: fmt::Debug + Send + Sync {
+    /// Returns all attachments of the page with the given id.
+    fn attachments(&self, page_id: &ConfluencePageId) -> Vec<Attachment>;
+
+    /// Returns a value that changes whenever the provided attachments change. See
+    /// [`JiraIssueProvider::fingerprint`](crate::JiraIssueProvider::fingerprint).
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
+}
+
+/// [`AttachmentProvider`] that holds the attachments in memory.
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryAttachmentProvider {
+    attachments: HashMap<ConfluencePageId, Vec<Attachment>>,
+}
+
+impl InMemoryAttachmentProvider {
+    pub fn insert(&mut self, page_id: ConfluencePageId, attachment: Attachment) {
+        self.attachments
+            .entry(page_id)
+            .or_default()
+            .push(attachment);
+    }
+
+    pub fn with_attachment(mut self, page_id: ConfluencePageId, attachment: Attachment) -> Self {
+        self.insert(page_id, attachment);
+        self
+    }
+}
+
+impl AttachmentProvider for InMemoryAttachmentProvider {
+    fn attachments(&self, page_id: &ConfluencePageId) -> Vec<Attachment> {
+        self.attachments.get(page_id).cloned().unwrap_or_default()
+    }
+
+    fn fingerprint(&self) -> Option<String> {
+        let attachments: BTreeMap<_, _> = self
+            .attachments
+            .iter()
+            .map(|(page_id, attachments)| (page_id.to_string(), attachments))
+            .collect();
+        Some(debug_hash(&attachments))
+    }
+}
+
+/// Resolves attachments to URLs, preferring exported local files over the Confluence download
+/// URL. The attachments of the current page are fetched once per document.
+#[derive(Debug, Default, Clone)]
+pub struct AttachmentResolver {
+    server: Option<ConfluenceServer>,
+    page_id: Option<ConfluencePageId>,
+    provider: Option<Arc<dyn AttachmentProvider>>,
+    attachments: Vec<Attachment>,
+    positions: HashMap<String, usize>,
+    page_index: Option<Arc<PageIndex>>,
+    default_space_key: Option<String>,
+}
+
+impl AttachmentResolver {
+    pub fn new(
+        server: Option<ConfluenceServer>,
+        page_id: Option<ConfluencePageId>,
+        provider: Option<Arc<dyn AttachmentProvider>>,
+    ) -> Self {
+        let attachments = provider
+            .as_deref()
+            .zip(page_id.as_ref())
+            .map(|(provider, page_id)| provider.attachments(page_id))
+            .unwrap_or_default();
+        let positions = attachments
+            .iter()
+            .enumerate()
+            .map(|(position, attachment)| (attachment.filename.clone(), position))
+            .collect();
+        Self {
+            server,
+            page_id,
+            provider,
+            attachments,
+            positions,
+            page_index: None,
+            default_space_key: None,
+        }
+    }
+
+    /// Use the page index to resolve attachments of other pages, which are referenced by title.
+    pub fn with_page_index(
+        mut self,
+        page_index: Option<Arc<PageIndex>>,
+        default_space_key: Option<String>,
+    ) -> Self {
+        self.page_index = page_index;
+        self.default_space_key = default_space_key;
+        self
+    }
+
+    /// Returns all known attachments of the current page.
+    pub fn attachments(&self) -> &[Attachment] {
+        &self.attachments
+    }
+
+    /// Returns the attachment of the current page with the given filename, if known.
+    pub fn attachment<S: AsRef<str>>(&self, filename: S) -> Option<&Attachment> {
+        self.positions
+            .get(filename.as_ref())
+            .map(|&position| &self.attachments[position])
+    }
+
+    /// Returns the URL of the attachment with the given filename on the current page.
+    pub fn url<S: AsRef<str>>(&self, filename: S) -> Option<String> {
+        let filename = filename.as_ref();
+        self.attachment(filename)
+            .and_then(|attachment| attachment.local_path.clone())
+            .or_else(|| self.download_url(self.page_id.as_ref()?, filename))
+    }
+
+    /// Returns the URL of the attachment referenced by the `ri:attachment` element, which refers
+    /// to an attachment of another page if it contains an `ri:page`.
+    pub fn reference_url(&self, reference: &Handle) -> Option<String> {
+        let filename = get_tag_attr(reference, "ri:filename")?;
+        let Some(page) = find_child(reference, "ri:page") else {
+            return self.url(filename);
+        };
+
+        let title = get_tag_attr(&page, "ri:content-title")?;
+        let space_key =
+            get_tag_attr(&page, "ri:space-key").or_else(|| self.default_space_key.clone());
+        let page_id = self
+            .page_index
+            .as_deref()?
+            .pages()
+            .iter()
+            .find(|page| {
+                page.title() == title
+                    && space_key
+                        .as_deref()
+                        .is_none_or(|space_key| page.space_key() == space_key)
+            })?
+            .id();
+        if self.page_id.as_ref() == Some(page_id) {
+            return self.url(filename);
+        }
+        self.provider
+            .as_deref()
+            .map(|provider| provider.attachments(page_id))
+            .unwrap_or_default()
+            .into_iter()
+            .find(|attachment| attachment.filename == filename)
+            .and_then(|attachment| attachment.local_path)
+            .or_else(|| self.download_url(page_id, &filename))
+    }
+
+    fn download_url(&self, page_id: &ConfluencePageId, filename: &str) -> Option<String> {
+        self.server
+            .as_ref()
+            .map(|server| server.attachment_url(page_id, filename))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{IndexedPage, ParseOptions, parse_confluence};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts how often the attachments are fetched.
+    #[derive(Debug, Default)]
+    struct CountingProvider {
+        inner: InMemoryAttachmentProvider,
+        calls: AtomicUsize,
+    }
+
+    impl AttachmentProvider for CountingProvider {
+        fn attachments(&self, page_id: &ConfluencePageId) -> Vec<Attachment> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.inner.attachments(page_id)
+        }
+    }
+
+    fn options(provider: Arc<CountingProvider>) -> ParseOptions {
+        let index = PageIndex::from_iter([
+            IndexedPage::new(ConfluencePageId::from(1), "DOCS", "Current"),
+            IndexedPage::new(ConfluencePageId::from(2), "DOCS", "Other"),
+        ]);
+        ParseOptions::default()
+            .with_default_space_key("DOCS".to_string())
+            .with_default_page_id(ConfluencePageId::from(1))
+            .with_page_index(index)
+            .with_attachment_provider(provider)
+    }
+
+    #[test]
+    fn test_attachments_are_fetched_once() {
+        let provider = Arc::new(CountingProvider {
+            inner: InMemoryAttachmentProvider::default()
+                .with_attachment(
+                    ConfluencePageId::from(1),
+                    Attachment::new("a.png").with_local_path("assets/a.png"),
+                )
+                .with_attachment(
+                    ConfluencePageId::from(1),
+                    Attachment::new("b.png").with_local_path("assets/b.png"),
+                ),
+            ..Default::default()
+        });
+        let md = parse_confluence(
+            r#"<ac:image><ri:attachment ri:filename="a.png" /></ac:image><ac:image><ri:attachment ri:filename="b.png" /></ac:image>"#,
+            &options(Arc::clone(&provider)),
+        );
+        assert_eq!(md, "![](assets/a.png)![](assets/b.png)");
+        assert_eq!(provider.calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_attachment_of_other_page() {
+        let provider = Arc::new(CountingProvider {
+            inner: InMemoryAttachmentProvider::default()
+                .with_attachment(
+                    ConfluencePageId::from(1),
+                    Attachment::new("a.png").with_local_path("current/a.png"),
+                )
+                .with_attachment(
+                    ConfluencePageId::from(2),
+                    Attachment::new("a.png").with_local_path("other/a.png"),
+                ),
+            ..Default::default()
+        });
+        let md = parse_confluence(
+            r#"<ac:image><ri:attachment ri:filename="a.png"><ri:page ri:content-title="Other" /></ri:attachment></ac:image>"#,
+            &options(provider),
+        );
+        assert_eq!(md, "![](other/a.png)");
+    }
+}
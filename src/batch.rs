@@ -0,0 +1,520 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::converter::Converter;
+use crate::manifest::{self, Manifest, ManifestChanges, ManifestEntry};
+use crate::metadata::PageMetadata;
+use crate::util::ConfluencePageId;
+use std::any::Any;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A document to convert as part of a batch.
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    input: PathBuf,
+    output: PathBuf,
+    page_id: Option<ConfluencePageId>,
+}
+
+impl BatchJob {
+    pub fn new<P: Into<PathBuf>, Q: Into<PathBuf>>(input: P, output: Q) -> Self {
+        Self {
+            input: input.into(),
+            output: output.into(),
+            page_id: None,
+        }
+    }
+
+    /// Sets the id of the page, which is used to resolve its attachments and comments.
+    pub fn with_page_id(mut self, page_id: ConfluencePageId) -> Self {
+        self.page_id = Some(page_id);
+        self
+    }
+
+    pub fn input(&self) -> &Path {
+        &self.input
+    }
+
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    /// Key of the job in the [`Manifest`]: the page id, or the input path for jobs without id.
+    pub(crate) fn key(&self) -> String {
+        match &self.page_id {
+            Some(page_id) => page_id.to_string(),
+            None => self.input.to_string_lossy().into_owned(),
+        }
+    }
+
+    fn run(&self, converter: &Converter) -> Result<PageMetadata, String> {
+        let input = File::open(&self.input).map_err(|error| error.to_string())?;
+        let mut output = Vec::new();
+        let metadata = panic::catch_unwind(AssertUnwindSafe(|| match &self.page_id {
+            Some(page_id) => converter.convert_stream_with_id(input, &mut output, page_id),
+            None => converter.convert_stream(input, &mut output),
+        }))
+        .map_err(|payload| panic_message(payload.as_ref()))?
+        .map_err(|error| error.to_string())?;
+
+        // Only write the output once the conversion succeeded, so that failed documents do not
+        // leave partial files behind.
+        if let Some(parent) = self.output.parent() {
+            fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+        }
+        let mut writer =
+            BufWriter::new(File::create(&self.output).map_err(|error| error.to_string())?);
+        writer
+            .write_all(&output)
+            .and_then(|()| writer.flush())
+            .map_err(|error| error.to_string())?;
+        Ok(metadata)
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown error");
+    format!("conversion panicked: {message}")
+}
+
+/// The outcome of converting a single document of a batch.
+#[derive(Debug, Clone)]
+pub struct DocumentReport {
+    input: PathBuf,
+    output: PathBuf,
+    elapsed: Duration,
+    result: Result<PageMetadata, String>,
+}
+
+impl DocumentReport {
+    pub fn input(&self) -> &Path {
+        &self.input
+    }
+
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    /// Time it took to convert the document.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// The metadata of the page, if the conversion succeeded.
+    pub fn metadata(&self) -> Option<&PageMetadata> {
+        self.result.as_ref().ok()
+    }
+
+    /// The reason why the conversion failed, if it did.
+    pub fn error(&self) -> Option<&str> {
+        self.result.as_ref().err().map(String::as_str)
+    }
+}
+
+/// Progress of a running batch, passed to the progress callback after each document.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchProgress<'a> {
+    pub completed: usize,
+    pub total: usize,
+    pub document: &'a DocumentReport,
+}
+
+/// The outcome of a batch conversion.
+#[derive(Debug, Clone, Default)]
+pub struct BatchReport {
+    documents: Vec<DocumentReport>,
+    elapsed: Duration,
+}
+
+impl BatchReport {
+    /// Reports of all documents, in the order of the jobs.
+    pub fn documents(&self) -> &[DocumentReport] {
+        &self.documents
+    }
+
+    pub fn successes(&self) -> impl Iterator<Item = &DocumentReport> {
+        self.documents
+            .iter()
+            .filter(|document| document.is_success())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &DocumentReport> {
+        self.documents
+            .iter()
+            .filter(|document| !document.is_success())
+    }
+
+    /// Total wall-clock time of the batch.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns a human-readable summary of the batch, listing the slowest and all failed
+    /// documents as well as the diagnostics of lenient parsing.
+    pub fn summary(&self) -> String {
+        let failed = self.failures().count();
+        let mut summary = format!(
+            "Converted {converted} of {total} documents in {elapsed:.2?} ({failed} failed)\n",
+            converted = self.documents.len() - failed,
+            total = self.documents.len(),
+            elapsed = self.elapsed,
+        );
+
+        let mut slowest: Vec<_> = self.successes().collect();
+        slowest.sort_by_key(|document| std::cmp::Reverse(document.elapsed));
+        if !slowest.is_empty() {
+            summary.push_str("\nSlowest documents:\n");
+            for document in slowest.into_iter().take(5) {
+                summary.push_str(&format!(
+                    "  {input} ({elapsed:.2?})\n",
+                    input = document.input.display(),
+                    elapsed = document.elapsed,
+                ));
+            }
+        }
+
+        if failed > 0 {
+            summary.push_str("\nFailures:\n");
+            for document in self.failures() {
+                summary.push_str(&format!(
+                    "  {input}: {error}\n",
+                    input = document.input.display(),
+                    error = document.error().unwrap_or_default(),
+                ));
+            }
+        }
+
+        let diagnostics: Vec<_> = self
+            .documents
+            .iter()
+            .filter_map(|document| Some((document, document.metadata()?.diagnostics())))
+            .filter(|(_, diagnostics)| !diagnostics.is_empty())
+            .collect();
+        if !diagnostics.is_empty() {
+            summary.push_str("\nDiagnostics:\n");
+            for (document, diagnostics) in diagnostics {
+                for diagnostic in diagnostics {
+                    summary.push_str(&format!(
+                        "  {input}:{diagnostic}\n",
+                        input = document.input.display(),
+                    ));
+                }
+            }
+        }
+        summary
+    }
+}
+
+/// Converts many documents in parallel with a bounded number of worker threads.
+///
+/// Failures are isolated per document: a page that is malformed or makes the conversion panic is
+/// reported as failed, while the rest of the batch continues.
+#[derive(Debug, Clone)]
+pub struct BatchConverter {
+    converter: Converter,
+    workers: NonZeroUsize,
+}
+
+impl BatchConverter {
+    pub fn new(converter: Converter) -> Self {
+        Self {
+            converter,
+            workers: thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+        }
+    }
+
+    /// Use at most the given number of worker threads (defaults to the available parallelism).
+    pub fn with_workers(mut self, workers: NonZeroUsize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Convert all jobs, calling `progress` on the current thread after each document.
+    pub fn convert<F: FnMut(BatchProgress<'_>)>(
+        &self,
+        jobs: &[BatchJob],
+        mut progress: F,
+    ) -> BatchReport {
+        let start = Instant::now();
+        let next_job = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        let mut documents: Vec<Option<DocumentReport>> = vec![None; jobs.len()];
+
+        thread::scope(|scope| {
+            for _ in 0..self.workers.get().min(jobs.len()) {
+                let sender = sender.clone();
+                let next_job = &next_job;
+                scope.spawn(move || {
+                    loop {
+                        let index = next_job.fetch_add(1, Ordering::Relaxed);
+                        let Some(job) = jobs.get(index) else {
+                            break;
+                        };
+                        let start = Instant::now();
+                        let result = job.run(&self.converter);
+                        let report = DocumentReport {
+                            input: job.input.clone(),
+                            output: job.output.clone(),
+                            elapsed: start.elapsed(),
+                            result,
+                        };
+                        if sender.send((index, report)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            for (completed, (index, report)) in receiver.iter().enumerate() {
+                progress(BatchProgress {
+                    completed: completed + 1,
+                    total: jobs.len(),
+                    document: &report,
+                });
+                documents[index] = Some(report);
+            }
+        });
+
+        BatchReport {
+            documents: documents.into_iter().flatten().collect(),
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+impl BatchConverter {
+    /// Convert only the jobs whose source, attachments or options changed since the conversion
+    /// recorded in the manifest, and remove the outputs of pages that are no longer part of it.
+    ///
+    /// The manifest is updated accordingly. Pages that fail to convert are removed from it, so
+    /// that they are converted again in the next run.
+    pub fn convert_incremental<F: FnMut(BatchProgress<'_>)>(
+        &self,
+        jobs: &[BatchJob],
+        manifest: &mut Manifest,
+        progress: F,
+    ) -> (BatchReport, ManifestChanges) {
+        let options = self.converter.options();
+        let options_hash = manifest::options_hash(options);
+        let compatible = manifest.is_compatible(&options_hash);
+        let mut changes = ManifestChanges::default();
+
+        let mut pending = Vec::new();
+        for job in jobs {
+            let key = job.key();
+            // Inputs that cannot be read are converted anyway, so that the failure is reported.
+            let entry = manifest::file_hash(&job.input).ok().map(|source_hash| {
+                let attachment_hashes = manifest::attachment_hashes(options, job.page_id.as_ref());
+                ManifestEntry::new(source_hash, job.output.clone(), attachment_hashes)
+            });
+            match entry {
+                Some(entry) if compatible && manifest.is_current(&key, &entry) => {
+                    changes.unchanged.push(key);
+                }
+                entry => pending.push((job.clone(), key, entry)),
+            }
+        }
+
+        let pending_jobs: Vec<_> = pending.iter().map(|(job, _, _)| job.clone()).collect();
+        let report = self.convert(&pending_jobs, progress);
+        for ((job, key, entry), document) in pending.into_iter().zip(report.documents()) {
+            let Some(entry) = entry.filter(|_| document.is_success()) else {
+                manifest.remove(&key);
+                continue;
+            };
+            match manifest.insert(key.clone(), entry) {
+                Some(previous) => {
+                    if previous.output() != job.output() {
+                        let _ = manifest::remove_output(previous.output());
+                    }
+                    changes.modified.push(key);
+                }
+                None => changes.added.push(key),
+            }
+        }
+
+        let keys: HashSet<_> = jobs.iter().map(BatchJob::key).collect();
+        let removed: Vec<_> = manifest
+            .entries()
+            .keys()
+            .filter(|key| !keys.contains(*key))
+            .cloned()
+            .collect();
+        for key in removed {
+            // Outputs that cannot be removed stay in the manifest to be removed in the next run.
+            if let Some(entry) = manifest.get(&key)
+                && manifest::remove_output(entry.output()).is_ok()
+            {
+                manifest.remove(&key);
+                changes.removed.push(key);
+            }
+        }
+        manifest.set_options_hash(options_hash);
+
+        (report, changes)
+    }
+}
+
+impl From<Converter> for BatchConverter {
+    fn from(converter: Converter) -> Self {
+        Self::new(converter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BatchConverter, BatchJob};
+    use crate::{
+        Attachment, ConfluencePageId, Converter, InMemoryAttachmentProvider, JiraIssue,
+        JiraIssueProvider, Manifest, ParseOptions,
+    };
+    use std::fs;
+    use std::num::NonZeroUsize;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct PanickingProvider;
+
+    impl JiraIssueProvider for PanickingProvider {
+        fn issue(&self, _key: &str) -> Option<JiraIssue> {
+            panic!("provider failed")
+        }
+
+        fn search(&self, _jql: &str) -> Option<Vec<JiraIssue>> {
+            panic!("provider failed")
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("html2md-confluence-{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_failures_are_isolated() {
+        let dir = temp_dir("batch");
+        let mut jobs = Vec::new();
+        for (name, source) in [
+            ("good", "<p>Good <b>page</b></p>"),
+            ("malformed", "<p>Unclosed</div>"),
+            // The Jira issue provider panics.
+            (
+                "panic",
+                r#"<ac:structured-macro ac:name="jira"><ac:parameter ac:name="key">A-1</ac:parameter></ac:structured-macro>"#,
+            ),
+            ("other", "<h1>Other</h1>"),
+        ] {
+            let input = dir.join(format!("{name}.xml"));
+            fs::write(&input, source).unwrap();
+            jobs.push(BatchJob::new(
+                input,
+                dir.join("out").join(format!("{name}.md")),
+            ));
+        }
+        jobs.push(BatchJob::new(
+            dir.join("missing.xml"),
+            dir.join("missing.md"),
+        ));
+
+        let mut completed = Vec::new();
+        let options = ParseOptions::default().with_jira_issue_provider(Arc::new(PanickingProvider));
+        let report = BatchConverter::new(Converter::new(options))
+            .with_workers(NonZeroUsize::new(2).unwrap())
+            .convert(&jobs, |progress| {
+                assert_eq!(progress.total, 5);
+                completed.push(progress.completed);
+            });
+
+        assert_eq!(completed, [1, 2, 3, 4, 5]);
+        let succeeded: Vec<_> = report.successes().map(|doc| doc.input()).collect();
+        assert_eq!(succeeded, [jobs[0].input(), jobs[3].input()]);
+        assert_eq!(
+            fs::read_to_string(jobs[0].output()).unwrap(),
+            "Good **page**"
+        );
+        assert_eq!(
+            fs::read_to_string(jobs[3].output()).unwrap(),
+            "Other\n=========="
+        );
+        assert!(!jobs[1].output().exists());
+        let failures: Vec<_> = report.failures().map(|doc| doc.error().unwrap()).collect();
+        assert_eq!(failures.len(), 3);
+        assert!(failures[1].starts_with("conversion panicked: provider failed"));
+
+        let summary = report.summary();
+        assert!(summary.starts_with("Converted 2 of 5 documents in "));
+        assert!(summary.contains("(3 failed)\n"));
+        assert!(summary.contains("malformed.xml: "));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_conversion() {
+        let dir = temp_dir("incremental");
+        let jobs: Vec<_> = (1..=3)
+            .map(|id| {
+                let input = dir.join(format!("{id}.xml"));
+                fs::write(&input, format!("<p>Page {id}</p>")).unwrap();
+                BatchJob::new(input, dir.join(format!("{id}.md")))
+                    .with_page_id(ConfluencePageId::from(id))
+            })
+            .collect();
+        let convert = |jobs: &[BatchJob], manifest: &mut Manifest, attachment: &str| {
+            let provider = InMemoryAttachmentProvider::default()
+                .with_attachment(ConfluencePageId::from(2), Attachment::new(attachment));
+            let options = ParseOptions::default().with_attachment_provider(Arc::new(provider));
+            let (report, changes) = BatchConverter::new(Converter::new(options))
+                .convert_incremental(jobs, manifest, |_| ());
+            assert_eq!(report.failures().count(), 0);
+            changes
+        };
+
+        let mut manifest = Manifest::default();
+        let changes = convert(&jobs, &mut manifest, "a.png");
+        assert_eq!(changes.added(), ["1", "2", "3"]);
+        let changes = convert(&jobs, &mut manifest, "a.png");
+        assert_eq!(changes.unchanged(), ["1", "2", "3"]);
+
+        fs::write(jobs[0].input(), "<p>Changed</p>").unwrap();
+        let changes = convert(&jobs[..2], &mut manifest, "b.png");
+        assert_eq!(changes.modified(), ["1", "2"]);
+        assert_eq!(changes.removed(), ["3"]);
+        assert_eq!(fs::read_to_string(jobs[0].output()).unwrap(), "Changed");
+        assert!(!jobs[2].output().exists());
+        assert_eq!(manifest.entries().len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+use crate::document::{Block, Inline};
+use crate::flavor::MarkdownFlavor;
+use crate::manifest::debug_hash;
+use crate::util::{get_text_content, html_escape};
+use html2md::anchors::AnchorHandler;
+use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory, common::get_tag_attr};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How a smart link is displayed in Confluence Cloud.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CardAppearance {
+    /// A link inside the text.
+    Inline,
+    /// A card with a preview of the linked content.
+    Block,
+    /// The linked content itself, embedded into the page.
+    Embed,
+}
+
+impl FromStr for CardAppearance {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inline" => Ok(Self::Inline),
+            "block" => Ok(Self::Block),
+            "embed" => Ok(Self::Embed),
+            _ => Err("invalid card appearance"),
+        }
+    }
+}
+
+/// Source of the titles of smart links, which Confluence resolves when the page is viewed.
+pub trait LinkTitleProvider: fmt::Debug + Send + Sync {
+    /// Returns the title of the linked content, if known.
+    fn title(&self, url: &str) -> Option<String>;
+
+    /// Returns a value that changes whenever the provided titles change. See
+    /// [`JiraIssueProvider::fingerprint`](crate::JiraIssueProvider::fingerprint).
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
+}
+
+/// [`LinkTitleProvider`] that holds the titles in memory.
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryLinkTitleProvider {
+    titles: HashMap<String, String>,
+}
+
+impl InMemoryLinkTitleProvider {
+    pub fn insert<S: Into<String>, T: Into<String>>(&mut self, url: S, title: T) {
+        self.titles.insert(url.into(), title.into());
+    }
+
+    pub fn with_title<S: Into<String>, T: Into<String>>(mut self, url: S, title: T) -> Self {
+        self.insert(url, title);
+        self
+    }
+}
+
+impl LinkTitleProvider for InMemoryLinkTitleProvider {
+    fn title(&self, url: &str) -> Option<String> {
+        self.titles.get(url).cloned()
+    }
+
+    fn fingerprint(&self) -> Option<String> {
+        let titles: BTreeMap<_, _> = self.titles.iter().collect();
+        Some(debug_hash(&titles))
+    }
+}
+
+/// Returns a readable title for URLs of Jira issues, Confluence pages and GitHub repositories, or
+/// the URL without its scheme otherwise.
+pub(crate) fn prettify_url(url: &str) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let without_query = without_scheme
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('/');
+    let (host, path) = without_query.split_once('/').unwrap_or((without_query, ""));
+    let segments: Vec<_> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let decode = |segment: &str| {
+        urlencoding::decode(&segment.replace('+', " "))
+            .map(|s| s.into_owned())
+            .unwrap_or_else(|_| segment.to_string())
+    };
+
+    if host == "github.com" || host == "www.github.com" {
+        match segments.as_slice() {
+            [owner, repo, "pull" | "issues", number, ..] => {
+                return format!("{owner}/{repo}#{number}");
+            }
+            [owner, repo, "commit", sha, ..] => {
+                let short = sha.get(..7).unwrap_or(sha);
+                return format!("{owner}/{repo}@{short}");
+            }
+            [owner, repo, "blob" | "tree", _, path @ ..] if !path.is_empty() => {
+                return format!("{owner}/{repo}: {}", path.join("/"));
+            }
+            [owner, repo, ..] => return format!("{owner}/{repo}"),
+            _ => (),
+        }
+    }
+    if let Some(position) = segments.iter().position(|s| *s == "browse")
+        && let Some(key) = segments.get(position + 1)
+    {
+        return (*key).to_string();
+    }
+    if let Some(position) = segments.iter().position(|s| *s == "spaces")
+        && let ["pages", _, title, ..] = &segments[position + 2..]
+    {
+        return decode(title);
+    }
+    if let Some(position) = segments.iter().position(|s| *s == "display")
+        && let [_, title] = &segments[position + 1..]
+    {
+        return decode(title);
+    }
+    without_query
+        .strip_prefix("www.")
+        .unwrap_or(without_query)
+        .to_string()
+}
+
+/// Renders smart links according to their card appearance.
+#[derive(Debug, Clone)]
+pub(crate) struct CardRenderer {
+    flavor: MarkdownFlavor,
+    title_provider: Option<Arc<dyn LinkTitleProvider>>,
+}
+
+impl CardRenderer {
+    pub fn new(flavor: MarkdownFlavor, title_provider: Option<Arc<dyn LinkTitleProvider>>) -> Self {
+        Self {
+            flavor,
+            title_provider,
+        }
+    }
+
+    /// Returns the title from the title provider, the link text unless it is just the URL, or the
+    /// prettified URL.
+    fn title(&self, url: &str, text: &str) -> String {
+        let text = text.trim();
+        self.title_provider
+            .as_ref()
+            .and_then(|provider| provider.title(url))
+            .or_else(|| (!text.is_empty() && text != url).then(|| text.to_string()))
+            .unwrap_or_else(|| prettify_url(url))
+    }
+
+    pub fn render(
+        &self,
+        url: &str,
+        text: &str,
+        appearance: CardAppearance,
+        printer: &mut StructuredPrinter,
+    ) {
+        let title = self.title(url, text);
+        let link = Inline::Link {
+            content: vec![Inline::Text(title.clone())],
+            url: url.to_string(),
+        };
+        let block = match appearance {
+            CardAppearance::Inline => {
+                printer.append_str(&link.to_markdown());
+                return;
+            }
+            CardAppearance::Embed if self.flavor.allows_embeds() => Block::Markdown(format!(
+                r#"<iframe src="{src}" title="{title}" frameborder="0" allowfullscreen></iframe>"#,
+                src = html_escape(url),
+                title = html_escape(&title),
+            )),
+            CardAppearance::Block | CardAppearance::Embed => Block::Paragraph(vec![link]),
+        };
+        printer.insert_newline();
+        printer.insert_newline();
+        printer.append_str(&block.to_markdown(self.flavor));
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+}
+
+/// Handler for `a` elements, which renders smart links (with a `data-card-appearance`) as cards
+/// and all other links like html2md.
+pub struct SmartLinkHandler {
+    card_renderer: CardRenderer,
+    anchor: Option<AnchorHandler>,
+}
+
+impl TagHandler for SmartLinkHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let appearance = get_tag_attr(tag, "data-card-appearance")
+            .and_then(|appearance| CardAppearance::from_str(&appearance).ok());
+        let url = get_tag_attr(tag, "href").filter(|url| !url.is_empty());
+        if let Some((appearance, url)) = appearance.zip(url) {
+            self.card_renderer
+                .render(&url, &get_text_content(tag), appearance, printer);
+        } else {
+            let mut anchor = AnchorHandler::default();
+            anchor.handle(tag, printer);
+            self.anchor = Some(anchor);
+        }
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if let Some(anchor) = self.anchor.as_mut() {
+            anchor.after_handle(printer);
+        }
+    }
+
+    fn skip_descendants(&self) -> bool {
+        self.anchor.is_none()
+    }
+}
+
+pub struct SmartLinkHandlerFactory {
+    card_renderer: CardRenderer,
+}
+
+impl SmartLinkHandlerFactory {
+    pub fn new(card_renderer: CardRenderer) -> Self {
+        Self { card_renderer }
+    }
+}
+
+impl TagHandlerFactory for SmartLinkHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(SmartLinkHandler {
+            card_renderer: self.card_renderer.clone(),
+            anchor: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ParseOptions, parse_confluence};
+
+    #[test]
+    fn test_prettify_url() {
+        for (url, title) in [
+            ("https://jira.example.com/browse/CONF-12", "CONF-12"),
+            (
+                "https://example.atlassian.net/wiki/spaces/DOC/pages/123/Release+Notes",
+                "Release Notes",
+            ),
+            (
+                "https://example.com/confluence/display/DOC/Release%20Notes",
+                "Release Notes",
+            ),
+            (
+                "https://github.com/rust-lang/rust/pull/42",
+                "rust-lang/rust#42",
+            ),
+            (
+                "https://github.com/rust-lang/rust/commit/0123456789abcdef",
+                "rust-lang/rust@0123456",
+            ),
+            (
+                "https://github.com/rust-lang/rust/blob/main/README.md",
+                "rust-lang/rust: README.md",
+            ),
+            ("https://github.com/rust-lang/rust/", "rust-lang/rust"),
+            (
+                "https://www.example.com/some/path?q=1",
+                "example.com/some/path",
+            ),
+        ] {
+            assert_eq!(prettify_url(url), title);
+        }
+    }
+
+    #[test]
+    fn test_smart_links() {
+        let source = r#"<p>See <a href="https://jira.example.com/browse/CONF-12" data-card-appearance="inline">https://jira.example.com/browse/CONF-12</a> and <a href="https://example.com/">Example</a>.</p>
+<p><a href="https://github.com/rust-lang/rust" data-card-appearance="block">https://github.com/rust-lang/rust</a></p>
+<p><a href="https://www.youtube.com/watch?v=1" data-card-appearance="embed">https://www.youtube.com/watch?v=1</a></p>"#;
+        let titles = InMemoryLinkTitleProvider::default()
+            .with_title("https://www.youtube.com/watch?v=1", "A video");
+        let options = ParseOptions::default().with_link_title_provider(Arc::new(titles));
+        assert_eq!(
+            parse_confluence(source, &options),
+            "\
+See [CONF-12](https://jira.example.com/browse/CONF-12) and [Example](https://example.com/).
+
+[rust-lang/rust](https://github.com/rust-lang/rust)
+
+[A video](https://www.youtube.com/watch?v=1)"
+        );
+        let options = options.with_flavor(MarkdownFlavor::CommonMark);
+        assert!(parse_confluence(source, &options).ends_with(
+            r#"<iframe src="https://www.youtube.com/watch?v=1" title="A video" frameborder="0" allowfullscreen></iframe>"#
+        ));
+    }
+}
@@ -0,0 +1,431 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::converter::Converter;
+use crate::document::Inline;
+use crate::manifest::debug_hash;
+use crate::util::ConfluencePageId;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+/// An inline or footer comment on a Confluence page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    id: String,
+    body: String,
+    author: Option<String>,
+    created: Option<String>,
+    inline_ref: Option<String>,
+}
+
+impl Comment {
+    /// Creates a footer comment with the given body in storage format.
+    pub fn new<S: Into<String>, T: Into<String>>(id: S, body: T) -> Self {
+        Self {
+            id: id.into(),
+            body: body.into(),
+            author: None,
+            created: None,
+            inline_ref: None,
+        }
+    }
+
+    pub fn with_author<S: Into<String>>(mut self, author: S) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    pub fn with_created<S: Into<String>>(mut self, created: S) -> Self {
+        self.created = Some(created.into());
+        self
+    }
+
+    /// Makes this an inline comment attached to the `ac:inline-comment-marker` with the given
+    /// `ac:ref`.
+    pub fn with_inline_ref<S: Into<String>>(mut self, inline_ref: S) -> Self {
+        self.inline_ref = Some(inline_ref.into());
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn created(&self) -> Option<&str> {
+        self.created.as_deref()
+    }
+
+    pub fn inline_ref(&self) -> Option<&str> {
+        self.inline_ref.as_deref()
+    }
+}
+
+/// Source of the comments of a page, e.g. from a space export.
+pub trait CommentProvider: fmt::Debug + Send + Sync {
+    /// Returns all inline and footer comments of the page with the given id, in thread order.
+    fn comments(&self, page_id: &ConfluencePageId) -> Vec<Comment>;
+
+    /// Returns a value that changes whenever the provided comments change. See
+    /// [`JiraIssueProvider::fingerprint`](crate::JiraIssueProvider::fingerprint).
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
+}
+
+/// [`CommentProvider`] that holds the comments in memory.
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryCommentProvider {
+    comments: HashMap<ConfluencePageId, Vec<Comment>>,
+}
+
+impl InMemoryCommentProvider {
+    pub fn insert(&mut self, page_id: ConfluencePageId, comment: Comment) {
+        self.comments.entry(page_id).or_default().push(comment);
+    }
+
+    pub fn with_comment(mut self, page_id: ConfluencePageId, comment: Comment) -> Self {
+        self.insert(page_id, comment);
+        self
+    }
+}
+
+impl CommentProvider for InMemoryCommentProvider {
+    fn comments(&self, page_id: &ConfluencePageId) -> Vec<Comment> {
+        self.comments.get(page_id).cloned().unwrap_or_default()
+    }
+
+    fn fingerprint(&self) -> Option<String> {
+        let comments: BTreeMap<_, _> = self
+            .comments
+            .iter()
+            .map(|(page_id, comments)| (page_id.to_string(), comments))
+            .collect();
+        Some(debug_hash(&comments))
+    }
+}
+
+/// Where the footer comments of a page are rendered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FooterCommentPlacement {
+    /// A "Comments" section at the end of the page.
+    #[default]
+    Section,
+    /// Only in [`ConfluencePage::footer_comments`](crate::ConfluencePage::footer_comments), e.g.
+    /// to write them to a sidecar file.
+    Sidecar,
+}
+
+impl FromStr for FooterCommentPlacement {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "section" => Ok(Self::Section),
+            "sidecar" => Ok(Self::Sidecar),
+            _ => Err("invalid footer comment placement"),
+        }
+    }
+}
+
+/// A comment whose body has been converted to Markdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedComment {
+    id: String,
+    author: Option<String>,
+    created: Option<String>,
+    markdown: String,
+}
+
+impl RenderedComment {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    /// Returns the creation date, formatted with the configured date format.
+    pub fn created(&self) -> Option<&str> {
+        self.created.as_deref()
+    }
+
+    pub fn markdown(&self) -> &str {
+        &self.markdown
+    }
+
+    /// Returns the author and date, e.g. `**Jane Doe**, 2025-03-01`.
+    fn byline(&self) -> String {
+        let author = self.author.as_deref().unwrap_or("Anonymous").to_string();
+        let author = Inline::Strong(vec![Inline::Text(author)]).to_markdown();
+        match &self.created {
+            Some(created) => format!("{author}, {}", Inline::Text(created.clone()).to_markdown()),
+            None => author,
+        }
+    }
+}
+
+/// Converts the comment body with the converter for comments, which uses the options of the
+/// page without the comment provider, because comments have no comments of their own. Links and
+/// attachments are resolved relative to the page with the given id, i.e. the commented page.
+///
+/// A malformed comment body is replaced by a placeholder, so that it does not fail the page.
+fn render_comment(
+    comment: &Comment,
+    page_id: &ConfluencePageId,
+    converter: &Converter,
+) -> RenderedComment {
+    let markdown = converter
+        .try_convert_page_with_id(&comment.body, page_id)
+        .map(String::from)
+        .unwrap_or_else(|error| {
+            Inline::Emphasis(vec![Inline::Text(format!(
+                "Comment could not be converted ({error})"
+            ))])
+            .to_markdown()
+        });
+    RenderedComment {
+        id: comment.id.clone(),
+        author: comment.author.clone(),
+        created: comment
+            .created
+            .as_deref()
+            .map(|created| converter.options().date_format.format(created)),
+        markdown,
+    }
+}
+
+/// Returns the rendered footer comments.
+pub(crate) fn render_footer_comments(
+    comments: &[Comment],
+    page_id: &ConfluencePageId,
+    converter: &Converter,
+) -> Vec<RenderedComment> {
+    comments
+        .iter()
+        .filter(|comment| comment.inline_ref.is_none())
+        .map(|comment| render_comment(comment, page_id, converter))
+        .collect()
+}
+
+/// Returns the footnote definitions for the inline comments referenced on the page, where
+/// `references` are the `ac:ref` ids in footnote order. Every reference gets a definition, with a
+/// placeholder for comments that are unknown or cannot be converted without a comment provider.
+pub(crate) fn inline_comment_footnotes(
+    comments: &[Comment],
+    references: &[String],
+    page_id: Option<&ConfluencePageId>,
+    converter: Option<&Converter>,
+) -> String {
+    let mut footnotes = Vec::new();
+    for (index, reference) in references.iter().enumerate() {
+        let thread: Vec<_> = page_id
+            .zip(converter)
+            .into_iter()
+            .flat_map(|(page_id, converter)| {
+                comments
+                    .iter()
+                    .filter(|comment| comment.inline_ref.as_ref() == Some(reference))
+                    .map(move |comment| {
+                        let comment = render_comment(comment, page_id, converter);
+                        format!("{}: {}", comment.byline(), comment.markdown)
+                    })
+            })
+            .collect();
+        let thread = if thread.is_empty() {
+            vec![
+                Inline::Emphasis(vec![Inline::Text(
+                    "Inline comment not available".to_string(),
+                )])
+                .to_markdown(),
+            ]
+        } else {
+            thread
+        };
+        // Continuation lines of a footnote must be indented.
+        let footnote = thread.join("\n\n").replace('\n', "\n    ");
+        footnotes.push(format!(
+            "[^comment-{number}]: {footnote}",
+            number = index + 1,
+            footnote = footnote.replace("\n    \n", "\n\n"),
+        ));
+    }
+    footnotes.join("\n")
+}
+
+/// Renders the footer comments as Markdown section with the given heading prefix (e.g. `##`).
+pub(crate) fn comments_section(comments: &[RenderedComment], heading: &str) -> String {
+    let mut section = format!("{heading} Comments\n");
+    for comment in comments {
+        section.push_str(&format!("\n{}\n\n{}\n", comment.byline(), comment.markdown));
+    }
+    section
+}
+
+/// Renders the footer comments as JSON array.
+pub(crate) fn comments_json(comments: &[RenderedComment]) -> String {
+    let comments: Vec<_> = comments
+        .iter()
+        .map(|comment| {
+            json!({
+                "id": comment.id,
+                "author": comment.author,
+                "created": comment.created,
+                "body": comment.markdown,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&comments).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Comment, ConfluencePageId, ConfluenceServer, Converter, FooterCommentPlacement,
+        InMemoryCommentProvider, ParseOptions, parse_confluence_page,
+    };
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    const PAGE: &str = r#"<p>The <ac:inline-comment-marker ac:ref="c1">launch date</ac:inline-comment-marker> is fixed.</p>"#;
+
+    fn options() -> ParseOptions {
+        let page_id = ConfluencePageId::from(1);
+        let provider = InMemoryCommentProvider::default()
+            .with_comment(
+                page_id.clone(),
+                Comment::new("10", "<p>Is this <strong>confirmed</strong>?</p>")
+                    .with_author("Jane Doe")
+                    .with_created("2025-03-01")
+                    .with_inline_ref("c1"),
+            )
+            .with_comment(
+                page_id.clone(),
+                Comment::new("11", "<p>Yes.</p>")
+                    .with_author("John Doe")
+                    .with_inline_ref("c1"),
+            )
+            .with_comment(
+                page_id.clone(),
+                Comment::new("12", "<p>Looks good to me.</p>")
+                    .with_author("Max Mustermann")
+                    .with_created("2025-03-02T10:00:00Z"),
+            );
+        ParseOptions::default()
+            .with_default_page_id(page_id)
+            .with_comment_provider(Arc::new(provider))
+    }
+
+    #[test]
+    fn test_comments_section() {
+        let page = parse_confluence_page(PAGE, &options());
+        assert_eq!(
+            page.markdown(),
+            "The launch date[^comment-1] is fixed.
+
+[^comment-1]: **Jane Doe**, 2025-03-01: Is this **confirmed**?
+
+    **John Doe**: Yes.
+
+## Comments
+
+**Max Mustermann**, 2025-03-02T10:00:00Z
+
+Looks good to me."
+        );
+    }
+
+    #[test]
+    fn test_malformed_comment() {
+        let page_id = ConfluencePageId::from(1);
+        let provider = InMemoryCommentProvider::default()
+            .with_comment(page_id.clone(), Comment::new("13", "<p>Broken <b>text</p>"));
+        let options = ParseOptions::default()
+            .with_default_page_id(page_id)
+            .with_comment_provider(Arc::new(provider));
+        let page = parse_confluence_page("<p>Text</p>", &options);
+        assert_eq!(
+            page.markdown(),
+            r"Text
+
+## Comments
+
+**Anonymous**
+
+*Comment could not be converted (malformed storage format at 1:18: expected \`\</b>\`, but \`\</p>\` was found)*"
+        );
+    }
+
+    #[test]
+    fn test_comment_of_other_page() {
+        let page_id = ConfluencePageId::from(2);
+        let provider = InMemoryCommentProvider::default().with_comment(
+            page_id.clone(),
+            Comment::new(
+                "14",
+                r#"<p>See <ac:link><ri:attachment ri:filename="a.txt" /><ac:plain-text-link-body>a.txt</ac:plain-text-link-body></ac:link></p>"#,
+            )
+            .with_author("*Jane* [admin]"),
+        );
+        let options = ParseOptions::default()
+            .with_confluence_server(ConfluenceServer::from_str("https://example.com").unwrap())
+            .with_default_page_id(ConfluencePageId::from(1))
+            .with_comment_provider(Arc::new(provider));
+        let page = Converter::new(options).convert_page_with_id("<p>Text</p>", &page_id);
+        assert_eq!(
+            page.markdown(),
+            r"Text
+
+## Comments
+
+**\*Jane\* \[admin\]**
+
+See [a.txt](https://example.com/download/attachments/2/a.txt)"
+        );
+    }
+
+    #[test]
+    fn test_comments_sidecar() {
+        let options = options().with_footer_comment_placement(FooterCommentPlacement::Sidecar);
+        let page = parse_confluence_page(PAGE, &options);
+        assert!(!page.markdown().contains("## Comments"));
+        assert_eq!(page.footer_comments().len(), 1);
+        assert_eq!(
+            page.footer_comments_markdown(),
+            "# Comments\n\n**Max Mustermann**, 2025-03-02T10:00:00Z\n\nLooks good to me.\n"
+        );
+        assert_eq!(
+            page.footer_comments_json(),
+            r#"[
+  {
+    "author": "Max Mustermann",
+    "body": "Looks good to me.",
+    "created": "2025-03-02T10:00:00Z",
+    "id": "12"
+  }
+]"#
+        );
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::document::{Block, ContentRenderer, table};
+use crate::flavor::MarkdownFlavor;
+use crate::util::{child_elements, get_tag_name};
+use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory, common::get_tag_attr};
+
+/// Returns true if the printer is currently inside a table cell, where only inline content is
+/// possible.
+pub(crate) fn is_inside_table_cell(printer: &StructuredPrinter) -> bool {
+    printer
+        .parent_chain
+        .iter()
+        .any(|tag| tag == "td" || tag == "th")
+}
+
+fn is_list_item(tag: &Handle) -> bool {
+    get_tag_name(tag).is_some_and(|name| name == "li")
+}
+
+fn is_table_cell(tag: &Handle) -> bool {
+    get_tag_name(tag).is_some_and(|name| name == "td" || name == "th")
+}
+
+/// Returns the rows of the table, without those of nested tables.
+fn table_rows(tag: &Handle) -> Vec<Handle> {
+    child_elements(tag, |_| true)
+        .into_iter()
+        .flat_map(|child| match get_tag_name(&child).as_deref() {
+            Some("tr") => vec![child],
+            Some("thead" | "tbody" | "tfoot") => table_rows(&child),
+            _ => vec![],
+        })
+        .collect()
+}
+
+/// Handler for lists, block quotes and tables, whose content is rendered with the handlers of the
+/// conversion and then wrapped, so that nested macros are indented, quoted or flattened
+/// correctly.
+pub struct ContainerHandler {
+    renderer: ContentRenderer,
+    flavor: MarkdownFlavor,
+}
+
+impl ContainerHandler {
+    fn new(renderer: ContentRenderer, flavor: MarkdownFlavor) -> Self {
+        Self { renderer, flavor }
+    }
+
+    fn list(&self, tag: &Handle, printer: &StructuredPrinter, ordered: bool) -> Block {
+        // Confluence sometimes nests lists directly in lists instead of in the preceding item.
+        let mut items: Vec<Vec<Handle>> = Vec::new();
+        for child in child_elements(tag, |_| true) {
+            match items.last_mut() {
+                Some(item) if !is_list_item(&child) => item.push(child),
+                _ => items.push(vec![child]),
+            }
+        }
+
+        let items = items
+            .iter()
+            .map(|nodes| {
+                let content = match nodes.split_first() {
+                    Some((item, rest)) if is_list_item(item) => {
+                        let mut content = self.renderer.render_children(item, printer);
+                        for node in rest {
+                            content.push('\n');
+                            content.push_str(&self.renderer.render_nodes(
+                                tag,
+                                std::iter::once(node),
+                                printer,
+                            ));
+                        }
+                        content
+                    }
+                    _ => self.renderer.render_nodes(tag, nodes.iter(), printer),
+                };
+                vec![Block::Markdown(content)]
+            })
+            .collect();
+        Block::List { ordered, items }
+    }
+
+    fn table(&self, tag: &Handle, printer: &StructuredPrinter) -> Option<String> {
+        let line_break = if self.flavor.allows_raw_html() {
+            "<br/>"
+        } else {
+            " "
+        };
+        let row_elements = table_rows(tag);
+        let rows: Vec<Vec<String>> = row_elements
+            .iter()
+            .map(|row| {
+                child_elements(row, is_table_cell)
+                    .iter()
+                    .map(|cell| {
+                        let content = self.renderer.render_children(cell, printer);
+                        content
+                            .lines()
+                            .map(str::trim_end)
+                            .filter(|line| !line.trim().is_empty())
+                            .collect::<Vec<_>>()
+                            .join(line_break)
+                            .replace('|', "\\|")
+                            .replace("\\\\|", "\\|")
+                    })
+                    .collect()
+            })
+            .collect();
+        let alignments: Vec<_> = row_elements
+            .first()
+            .map(|row| child_elements(row, is_table_cell))
+            .unwrap_or_default()
+            .iter()
+            .map(|cell| get_tag_attr(cell, "align"))
+            .collect();
+        table(&rows, &alignments)
+    }
+}
+
+impl TagHandler for ContainerHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let markdown = match get_tag_name(tag).as_deref() {
+            Some("ul" | "menu") => self.list(tag, printer, false).to_markdown(self.flavor),
+            Some("ol") => self.list(tag, printer, true).to_markdown(self.flavor),
+            Some("blockquote") => {
+                let content = self.renderer.render_children(tag, printer);
+                Block::BlockQuote(vec![Block::Markdown(content)]).to_markdown(self.flavor)
+            }
+            Some("table") => match self.table(tag, printer) {
+                Some(table) => table,
+                None => return,
+            },
+            _ => return,
+        };
+
+        // Nested lists are kept tight.
+        let nested_list = get_tag_name(tag).is_some_and(|name| name != "table")
+            && printer.parent_chain.iter().any(|name| name == "li");
+        printer.insert_newline();
+        if !nested_list {
+            printer.insert_newline();
+        }
+        printer.append_str(&markdown);
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+pub struct ContainerHandlerFactory {
+    renderer: ContentRenderer,
+    flavor: MarkdownFlavor,
+}
+
+impl ContainerHandlerFactory {
+    pub(crate) fn new(renderer: ContentRenderer, flavor: MarkdownFlavor) -> Self {
+        Self { renderer, flavor }
+    }
+}
+
+impl TagHandlerFactory for ContainerHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(ContainerHandler::new(self.renderer.clone(), self.flavor))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_macros_in_list_item() {
+        crate::markdown_assert_eq!(
+            r#"<ul><li>Item<ac:structured-macro ac:name="info"><ac:rich-text-body>
+<p>Info in list</p>
+<ul><li>a<ul><li>b</li></ul></li></ul>
+<ac:structured-macro ac:name="expand"><ac:rich-text-body><ac:task-list>
+<ac:task><ac:task-status>complete</ac:task-status><ac:task-body><p>One</p><p>Two</p></ac:task-body></ac:task>
+</ac:task-list></ac:rich-text-body></ac:structured-macro>
+</ac:rich-text-body></ac:structured-macro></li><li>Next</li></ul>"#,
+            "\
+* Item
+
+  > [!IMPORTANT]
+  >
+  > Info in list
+  >
+  > * a
+  >   * b
+  >
+  > <details><summary>Click here to expand...</summary>
+  >
+  > - [x] One
+  >
+  >   Two
+  >
+  > </details>
+* Next"
+        );
+    }
+
+    #[test]
+    fn test_macros_in_table_cell() {
+        crate::markdown_assert_eq!(
+            r#"<ac:structured-macro ac:name="panel"><ac:rich-text-body><table><tbody>
+<tr><th>Note</th><th>Status</th></tr>
+<tr><td><ac:structured-macro ac:name="info"><ac:parameter ac:name="title">Heads up</ac:parameter><ac:rich-text-body><p>x | y</p><p>z</p></ac:rich-text-body></ac:structured-macro></td><td><p>done</p></td></tr>
+</tbody></table></ac:rich-text-body></ac:structured-macro>"#,
+            "\
+> [!NOTE]
+>
+> |             Note              |Status|
+> |-------------------------------|------|
+> |**Info: Heads up** x \\| y<br/>z| done |"
+        );
+    }
+
+    #[test]
+    fn test_admonition_in_blockquote() {
+        crate::markdown_assert_eq!(
+            r#"<blockquote><p>Quote</p><ac:structured-macro ac:name="note"><ac:rich-text-body><p>Nested</p></ac:rich-text-body></ac:structured-macro></blockquote><p>After</p>"#,
+            "> Quote\n>\n> > [!NOTE]\n> >\n> > Nested\n\nAfter"
+        );
+    }
+
+    #[test]
+    fn test_ordered_list_indentation() {
+        let items: String = (1..10).map(|index| format!("<li>{index}</li>")).collect();
+        crate::markdown_assert_eq!(
+            &format!("<ol>{items}<li><p>ten</p><pre>code</pre></li></ol>"),
+            "1. 1\n2. 2\n3. 3\n4. 4\n5. 5\n6. 6\n7. 7\n8. 8\n9. 9\n10. ten\n\n    ```\n    code\n    ```"
+        );
+    }
+}
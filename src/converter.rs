@@ -0,0 +1,610 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::attachment::AttachmentResolver;
+use crate::comment::{self, FooterCommentPlacement};
+use crate::date::{self, DateFormat};
+use crate::diagnostic::ParseError;
+use crate::document::{ContentRenderer, TagHandlerFactories};
+use crate::emoticon::{self, EmoticonStyle};
+use crate::flavor::{MarkdownFlavor, RawHtmlPolicy};
+use crate::index::PageIndex;
+use crate::jira::JiraIssueProvider;
+use crate::layout::{self, LayoutRenderer};
+use crate::link::{self, LinkHandlerUrlBuilder};
+use crate::macros::{self, DocumentContext, MacroContext, status::StatusStyle};
+use crate::metadata::{ConfluencePage, PageMetadata};
+use crate::storage::{self, StorageError};
+use crate::util::{ConfluencePageId, ConfluenceServer, JiraServerMap};
+use crate::{ParseOptions, adf, card, container, document, dummy, image, marker, task};
+use html2md::{StructuredPrinter, walk};
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::rc::Rc;
+use std::sync::{Arc, Weak};
+
+/// Settings derived from the [`ParseOptions`], which are shared by all conversions of a
+/// [`Converter`].
+#[derive(Debug)]
+pub struct ConversionContext {
+    pub options: ParseOptions,
+    pub jira_server_map: JiraServerMap,
+    pub jira_issue_provider: Option<Arc<dyn JiraIssueProvider>>,
+    pub confluence_server: Option<ConfluenceServer>,
+    pub default_space_key: Option<String>,
+    pub page_index: Option<Arc<PageIndex>>,
+    pub flavor: MarkdownFlavor,
+    pub status_style: StatusStyle,
+    pub emoticon_style: EmoticonStyle,
+    pub emoticon_mappings: Arc<HashMap<String, String>>,
+    pub layout: LayoutRenderer,
+    pub date_format: Arc<DateFormat>,
+    pub raw_html_policy: RawHtmlPolicy,
+    card_renderer: card::CardRenderer,
+    /// Converter for comment bodies, i.e. without comment provider.
+    comment_converter: Option<Converter>,
+}
+
+impl ConversionContext {
+    fn new(options: ParseOptions) -> Self {
+        let comment_converter = options.comment_provider.as_ref().map(|_| {
+            let mut options = options.clone();
+            options.comment_provider = None;
+            Converter::new(options)
+        });
+        Self {
+            jira_server_map: options.jira_server_map.clone(),
+            jira_issue_provider: options.jira_issue_provider.clone(),
+            confluence_server: options.confluence_server.clone(),
+            default_space_key: options.default_space_key.clone(),
+            page_index: options.page_index.clone(),
+            flavor: options.flavor,
+            status_style: options
+                .status_style
+                .unwrap_or_else(|| StatusStyle::default_for(options.flavor)),
+            emoticon_style: options
+                .emoticon_style
+                .unwrap_or_else(|| EmoticonStyle::default_for(options.flavor)),
+            emoticon_mappings: Arc::new(options.emoticon_mappings.clone()),
+            layout: LayoutRenderer::new(options.layout_style, options.flavor),
+            date_format: Arc::new(options.date_format.clone()),
+            raw_html_policy: if options.flavor.allows_raw_html() {
+                options.raw_html_policy
+            } else {
+                RawHtmlPolicy::Strip
+            },
+            card_renderer: card::CardRenderer::new(
+                options.flavor,
+                options.link_title_provider.clone(),
+            ),
+            comment_converter,
+            options,
+        }
+    }
+
+    /// Creates the tag handler factories, which get the state of the current document from the
+    /// document context.
+    fn handlers(
+        &self,
+        document: &DocumentContext,
+        renderer: &ContentRenderer,
+    ) -> TagHandlerFactories {
+        let options = &self.options;
+        let mut handlers: TagHandlerFactories = HashMap::new();
+        // Legacy `ac:macro` elements from before Confluence 4 have the same structure.
+        for tag in ["ac:structured-macro", "ac:macro"] {
+            handlers.insert(
+                String::from(tag),
+                Box::new(macros::StructuredMacroHandlerFactory::new(document.clone())),
+            );
+        }
+        handlers.insert(
+            String::from("ac:adf-extension"),
+            Box::new(adf::AdfExtensionHandlerFactory::new(document.clone())),
+        );
+        handlers.insert(
+            String::from("ac:parameter"),
+            Box::new(dummy::RecursiveDummyHandlerFactory {}),
+        );
+        handlers.insert(
+            String::from("ac:emoticon"),
+            Box::new(emoticon::EmoticonHandlerFactory::new(
+                self.emoticon_style,
+                Arc::clone(&self.emoticon_mappings),
+            )),
+        );
+        handlers.insert(
+            String::from("ac:image"),
+            Box::new(image::ImageHandlerFactory::new(
+                document.clone(),
+                self.flavor,
+            )),
+        );
+        for tag_name in ["ac:layout", "ac:layout-section", "ac:layout-cell"] {
+            handlers.insert(
+                String::from(tag_name),
+                Box::new(layout::LayoutHandlerFactory::new(
+                    document.clone(),
+                    self.layout,
+                )),
+            );
+        }
+        for tag_name in ["ac:task-list", "ac:task"] {
+            handlers.insert(
+                String::from(tag_name),
+                Box::new(task::TaskHandlerFactory::new(renderer.clone(), self.flavor)),
+            );
+        }
+        for tag_name in ["ul", "ol", "menu", "blockquote", "table"] {
+            handlers.insert(
+                String::from(tag_name),
+                Box::new(container::ContainerHandlerFactory::new(
+                    renderer.clone(),
+                    self.flavor,
+                )),
+            );
+        }
+        for tag_name in ["ac:task-id", "ac:task-uuid", "ac:task-status"] {
+            handlers.insert(
+                String::from(tag_name),
+                Box::new(dummy::RecursiveDummyHandlerFactory {}),
+            );
+        }
+        handlers.insert(
+            String::from("ac:placeholder"),
+            Box::new(marker::PlaceholderHandlerFactory::new(
+                options.placeholders_as_comments && self.flavor.allows_raw_html(),
+            )),
+        );
+        handlers.insert(
+            String::from("ac:inline-comment-marker"),
+            Box::new(marker::InlineCommentMarkerHandlerFactory::new(
+                document.clone(),
+                options.inline_comment_references,
+            )),
+        );
+        for tag_name in ["ac:adf-mark", "ac:adf-fragment-mark"] {
+            handlers.insert(
+                String::from(tag_name),
+                Box::new(marker::UnwrapHandlerFactory),
+            );
+        }
+        // `ac:adf-node` is only reached for unknown node types, which use `ac:adf-fallback` instead.
+        for tag_name in ["ac:adf-node", "ac:adf-fragment-mark-detail"] {
+            handlers.insert(
+                String::from(tag_name),
+                Box::new(dummy::RecursiveDummyHandlerFactory {}),
+            );
+        }
+        handlers.insert(
+            String::from("time"),
+            Box::new(date::TimeHandlerFactory::new(Arc::clone(&self.date_format))),
+        );
+        handlers.insert(
+            String::from("ac:link"),
+            Box::new(
+                link::LinkHandlerFactory::with_url_builder(LinkHandlerUrlBuilder::new(
+                    self.confluence_server.clone(),
+                    self.default_space_key.clone(),
+                    None,
+                ))
+                .with_document(document.clone())
+                .with_renderer(renderer.clone())
+                .with_card_renderer(self.card_renderer.clone()),
+            ),
+        );
+        handlers.insert(
+            String::from("a"),
+            Box::new(card::SmartLinkHandlerFactory::new(
+                self.card_renderer.clone(),
+            )),
+        );
+        handlers
+    }
+}
+
+/// The tag handler factories of a converter, which get the state of the current document from the
+/// document context.
+#[derive(Clone)]
+struct ThreadHandlers {
+    factories: Rc<OnceCell<TagHandlerFactories>>,
+    document: DocumentContext,
+}
+
+struct ThreadHandlersEntry {
+    context: Weak<ConversionContext>,
+    handlers: ThreadHandlers,
+}
+
+thread_local! {
+    /// The tag handler factories are not `Send`, so they are created once per converter on each
+    /// thread that uses it and reused for all documents.
+    static THREAD_HANDLERS: RefCell<Vec<ThreadHandlersEntry>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Converts Confluence storage format documents to Markdown.
+///
+/// The converter is built once from the [`ParseOptions`] and can be shared between threads to
+/// convert many documents, e.g. all pages of a space export.
+#[derive(Debug, Clone)]
+pub struct Converter {
+    context: Arc<ConversionContext>,
+}
+
+impl Converter {
+    pub fn new(options: ParseOptions) -> Self {
+        Self {
+            context: Arc::new(ConversionContext::new(options)),
+        }
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.context.options
+    }
+
+    /// Convert the source to Markdown.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source is malformed and the converter uses [`ParseMode::Strict`](crate::ParseMode::Strict). Use
+    /// [`Converter::try_convert_page`] to handle the error instead.
+    pub fn convert<S: AsRef<str>>(&self, source: S) -> String {
+        self.convert_page(source).into()
+    }
+
+    /// Like [`Converter::convert`], but returns an error for malformed sources in strict mode.
+    pub fn try_convert<S: AsRef<str>>(&self, source: S) -> Result<String, ParseError> {
+        self.try_convert_page(source).map(Into::into)
+    }
+
+    /// Convert the source to Markdown and collect the page metadata (e.g. Page Properties).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source is malformed and the converter uses [`ParseMode::Strict`](crate::ParseMode::Strict).
+    pub fn convert_page<S: AsRef<str>>(&self, source: S) -> ConfluencePage {
+        self.try_convert_page(source)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Convert the page with the given id, which is used instead of the default page id to
+    /// resolve its attachments and comments.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source is malformed and the converter uses [`ParseMode::Strict`](crate::ParseMode::Strict).
+    pub fn convert_page_with_id<S: AsRef<str>>(
+        &self,
+        source: S,
+        page_id: &ConfluencePageId,
+    ) -> ConfluencePage {
+        self.try_convert_page_with_id(source, page_id)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like [`Converter::convert_page`], but returns an error for malformed sources in strict
+    /// mode.
+    pub fn try_convert_page<S: AsRef<str>>(&self, source: S) -> Result<ConfluencePage, ParseError> {
+        self.convert_str(source.as_ref(), self.options().default_page_id.as_ref())
+    }
+
+    /// Like [`Converter::convert_page_with_id`], but returns an error for malformed sources in
+    /// strict mode.
+    pub fn try_convert_page_with_id<S: AsRef<str>>(
+        &self,
+        source: S,
+        page_id: &ConfluencePageId,
+    ) -> Result<ConfluencePage, ParseError> {
+        self.convert_str(source.as_ref(), Some(page_id))
+    }
+
+    /// Read the source from the input and write the Markdown to the output.
+    ///
+    /// The source is parsed directly into the document tree while it is read, so it is never
+    /// held in memory as a whole. The Markdown is buffered and only written once the page has been
+    /// converted, because it is cleaned up and followed by the comments as a whole. In strict
+    /// mode, malformed sources (including invalid UTF-8) are reported as
+    /// [`io::ErrorKind::InvalidData`] with a [`ParseError`] as inner error.
+    pub fn convert_stream<R: Read, W: Write>(
+        &self,
+        input: R,
+        output: W,
+    ) -> io::Result<PageMetadata> {
+        let page_id = self.options().default_page_id.as_ref();
+        self.convert_reader(input, output, page_id)
+    }
+
+    /// Like [`Converter::convert_stream`], but for the page with the given id.
+    pub fn convert_stream_with_id<R: Read, W: Write>(
+        &self,
+        input: R,
+        output: W,
+        page_id: &ConfluencePageId,
+    ) -> io::Result<PageMetadata> {
+        self.convert_reader(input, output, Some(page_id))
+    }
+
+    fn convert_reader<R: Read, W: Write>(
+        &self,
+        input: R,
+        mut output: W,
+        page_id: Option<&ConfluencePageId>,
+    ) -> io::Result<PageMetadata> {
+        let page = self.convert_document(BufReader::new(input), page_id)?;
+        output.write_all(page.markdown().as_bytes())?;
+        output.flush()?;
+        Ok(page.metadata().clone())
+    }
+
+    /// Returns the tag handler factories of this converter for the current thread.
+    fn thread_handlers(&self) -> ThreadHandlers {
+        THREAD_HANDLERS.with_borrow_mut(|entries| {
+            entries.retain(|entry| entry.context.strong_count() > 0);
+            // The handlers are busy if a document is converted while converting another one.
+            let cached = entries.iter().find(|entry| {
+                Weak::as_ptr(&entry.context) == Arc::as_ptr(&self.context)
+                    && !entry.handlers.document.is_active()
+            });
+            if let Some(entry) = cached {
+                return entry.handlers.clone();
+            }
+
+            // The factories are only known after all of them have been created, but container
+            // handlers need them to render their content.
+            let factories = Rc::new(OnceCell::new());
+            let document = DocumentContext::default();
+            let _ = factories.set(
+                self.context
+                    .handlers(&document, &ContentRenderer::new(&factories)),
+            );
+            let handlers = ThreadHandlers {
+                factories,
+                document,
+            };
+            entries.push(ThreadHandlersEntry {
+                context: Arc::downgrade(&self.context),
+                handlers: handlers.clone(),
+            });
+            handlers
+        })
+    }
+
+    fn convert_str(
+        &self,
+        source: &str,
+        page_id: Option<&ConfluencePageId>,
+    ) -> Result<ConfluencePage, ParseError> {
+        self.convert_document(source.as_bytes(), page_id)
+            .map_err(|error| match error {
+                StorageError::Parse(error) => error,
+                StorageError::Io(error) => unreachable!("reading from memory failed: {error}"),
+            })
+    }
+
+    fn convert_document<R: BufRead>(
+        &self,
+        input: R,
+        page_id: Option<&ConfluencePageId>,
+    ) -> Result<ConfluencePage, StorageError> {
+        let options = self.options();
+        let jira_key_autolink = options
+            .jira_key_pattern
+            .as_ref()
+            .zip(self.context.jira_server_map.default_server());
+        let document = storage::parse_storage_format(input, options.parse_mode, jira_key_autolink)?;
+        let metadata = Rc::new(RefCell::new(PageMetadata::default()));
+        metadata.borrow_mut().add_diagnostics(document.diagnostics);
+        let tree = document.tree;
+
+        let comments = options
+            .comment_provider
+            .as_deref()
+            .zip(page_id)
+            .map(|(provider, page_id)| provider.comments(page_id))
+            .unwrap_or_default();
+        let known_comments = comments
+            .iter()
+            .filter_map(|comment| comment.inline_ref().map(str::to_string))
+            .collect();
+
+        let handlers = self.thread_handlers();
+        let context = MacroContext::new(
+            Arc::clone(&self.context),
+            AttachmentResolver::new(
+                self.context.confluence_server.clone(),
+                page_id.cloned(),
+                options.attachment_provider.clone(),
+            )
+            .with_page_index(
+                options.page_index.clone(),
+                options.default_space_key.clone(),
+            ),
+            page_id.cloned(),
+            Rc::clone(&metadata),
+            known_comments,
+            ContentRenderer::new(&handlers.factories),
+        );
+        let mut printer = StructuredPrinter::default();
+        {
+            let _guard = handlers.document.enter(context);
+            let factories = handlers.factories.get().expect("missing handlers");
+            walk(&tree, &mut printer, factories);
+        }
+        drop(tree);
+        let mut markdown = document::clean_markdown(&printer.data);
+        drop(printer);
+
+        let metadata = Rc::try_unwrap(metadata)
+            .map(RefCell::into_inner)
+            .unwrap_or_else(|metadata| metadata.borrow().clone());
+
+        let footnotes = comment::inline_comment_footnotes(
+            &comments,
+            metadata.inline_comment_refs(),
+            page_id,
+            self.context.comment_converter.as_ref(),
+        );
+        if !footnotes.is_empty() {
+            markdown.push_str("\n\n");
+            markdown.push_str(&footnotes);
+        }
+        let Some((comment_converter, page_id)) =
+            self.context.comment_converter.as_ref().zip(page_id)
+        else {
+            return Ok(ConfluencePage::new(markdown, metadata));
+        };
+        let footer_comments =
+            comment::render_footer_comments(&comments, page_id, comment_converter);
+        if options.footer_comment_placement == FooterCommentPlacement::Section
+            && !footer_comments.is_empty()
+        {
+            markdown.push_str("\n\n");
+            markdown.push_str(comment::comments_section(&footer_comments, "##").trim_end());
+        }
+        Ok(ConfluencePage::new(markdown, metadata).with_footer_comments(footer_comments))
+    }
+}
+
+impl From<ParseOptions> for Converter {
+    fn from(options: ParseOptions) -> Self {
+        Self::new(options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Converter, THREAD_HANDLERS};
+    use crate::{ConfluencePageId, ConfluenceServer, ParseMode, ParseOptions};
+    use std::io;
+    use std::str::FromStr;
+    use std::sync::{Arc, Weak};
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_converter_is_send_and_sync() {
+        assert_send_sync::<Converter>();
+    }
+
+    #[test]
+    fn test_concurrent_conversion() {
+        let converter = Converter::new(
+            ParseOptions::default()
+                .with_confluence_server(
+                    ConfluenceServer::from_str("https://example.com/confluence").unwrap(),
+                )
+                .with_default_space_key("DOCS".to_string()),
+        );
+        let results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|index| {
+                    let converter = &converter;
+                    scope.spawn(move || {
+                        converter.convert_page_with_id(
+                            format!(
+                                r#"<p>Page {index}: <ac:link><ri:attachment ri:filename="a.pdf" /><ac:plain-text-link-body>file</ac:plain-text-link-body></ac:link></p>"#
+                            ),
+                            &ConfluencePageId::from(index),
+                        )
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap().markdown().to_string())
+                .collect()
+        });
+        for (index, markdown) in results.iter().enumerate() {
+            assert_eq!(
+                markdown,
+                &format!(
+                    "Page {index}: [file](https://example.com/confluence/download/attachments/{index}/a.pdf)"
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_handlers_are_reused() {
+        let converter = Converter::new(
+            ParseOptions::default()
+                .with_confluence_server(
+                    ConfluenceServer::from_str("https://example.com/confluence").unwrap(),
+                )
+                .with_default_space_key("DOCS".to_string()),
+        );
+        let source = r#"<ac:link><ri:attachment ri:filename="a.pdf" /><ac:plain-text-link-body>file</ac:plain-text-link-body></ac:link>"#;
+        for index in 1..=2 {
+            assert_eq!(
+                converter
+                    .convert_page_with_id(source, &ConfluencePageId::from(index))
+                    .markdown(),
+                format!(
+                    "[file](https://example.com/confluence/download/attachments/{index}/a.pdf)"
+                )
+            );
+        }
+        let cached = THREAD_HANDLERS.with_borrow(|entries| {
+            entries
+                .iter()
+                .filter(|entry| Weak::as_ptr(&entry.context) == Arc::as_ptr(&converter.context))
+                .count()
+        });
+        assert_eq!(cached, 1);
+    }
+
+    #[test]
+    fn test_convert_stream() {
+        let converter = Converter::new(ParseOptions::default());
+        let source = "<h1>Title</h1><p>Caf\u{e9} &amp; <code>a&nbsp;b</code></p><br/>";
+        let mut output = Vec::new();
+        converter
+            .convert_stream(source.as_bytes(), &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            converter.convert(source)
+        );
+
+        let error = converter
+            .convert_stream("<p>unclosed</div>".as_bytes(), io::sink())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let source: &[u8] = b"<p>Caf\xe9 <b>bold</b></p>";
+        let error = Converter::new(ParseOptions::default())
+            .convert_stream(source, io::sink())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "malformed storage format at 1:7: invalid UTF-8 was replaced by U+FFFD"
+        );
+
+        let converter = Converter::new(ParseOptions::default().with_parse_mode(ParseMode::Lenient));
+        let mut output = Vec::new();
+        let metadata = converter.convert_stream(source, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "Caf\u{fffd} **bold**");
+        assert_eq!(
+            metadata.diagnostics()[0].to_string(),
+            "1:7: invalid UTF-8 was replaced by U+FFFD"
+        );
+    }
+}
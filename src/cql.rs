@@ -0,0 +1,413 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Parser and evaluator for the subset of the Confluence Query Language (CQL) that is needed to
+//! statically render the `detailssummary` and `contentbylabel` macros.
+//!
+//! Supported are the `label`, `space`, `ancestor` and `type` fields with the `=`, `!=`, `in` and
+//! `not in` operators, combined with `and`, `or`, `not` and parentheses.
+
+use crate::index::IndexedPage;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CqlError(String);
+
+impl fmt::Display for CqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid CQL query: {}", self.0)
+    }
+}
+
+impl std::error::Error for CqlError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Label,
+    Space,
+    Ancestor,
+    Type,
+}
+
+impl FromStr for Field {
+    type Err = CqlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "label" => Ok(Self::Label),
+            "space" | "space.key" => Ok(Self::Space),
+            "ancestor" => Ok(Self::Ancestor),
+            "type" => Ok(Self::Type),
+            other => Err(CqlError(format!("unsupported field {other:?}"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Literal(String),
+    CurrentSpace,
+}
+
+/// Values available to functions like `currentSpace()` during evaluation.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CqlContext<'a> {
+    pub current_space: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CqlQuery {
+    Clause {
+        field: Field,
+        values: Vec<Value>,
+        negated: bool,
+    },
+    Not(Box<CqlQuery>),
+    And(Box<CqlQuery>, Box<CqlQuery>),
+    Or(Box<CqlQuery>, Box<CqlQuery>),
+}
+
+impl CqlQuery {
+    fn clause(field: Field, values: Vec<String>) -> Self {
+        Self::Clause {
+            field,
+            values: values.into_iter().map(Value::Literal).collect(),
+            negated: false,
+        }
+    }
+
+    /// Build a query from the legacy `labels`/`spaces`/`operator` macro parameters.
+    pub fn from_labels_and_spaces(labels: &[String], spaces: &[String], match_all: bool) -> Self {
+        let label_query = if match_all {
+            labels
+                .iter()
+                .map(|label| Self::clause(Field::Label, vec![label.clone()]))
+                .reduce(|lhs, rhs| Self::And(Box::new(lhs), Box::new(rhs)))
+        } else if labels.is_empty() {
+            None
+        } else {
+            Some(Self::clause(Field::Label, labels.to_vec()))
+        };
+
+        let space_query = if spaces.is_empty() {
+            None
+        } else {
+            Some(Self::Clause {
+                field: Field::Space,
+                values: spaces
+                    .iter()
+                    .map(|space| match space.as_str() {
+                        "@self" | "currentSpace()" => Value::CurrentSpace,
+                        _ => Value::Literal(space.clone()),
+                    })
+                    .collect(),
+                negated: false,
+            })
+        };
+
+        match (label_query, space_query) {
+            (Some(lhs), Some(rhs)) => Self::And(Box::new(lhs), Box::new(rhs)),
+            (Some(query), None) | (None, Some(query)) => query,
+            (None, None) => Self::clause(Field::Type, vec!["page".to_string()]),
+        }
+    }
+
+    pub fn matches(&self, page: &IndexedPage, context: &CqlContext<'_>) -> bool {
+        match self {
+            Self::Clause {
+                field,
+                values,
+                negated,
+            } => {
+                let mut resolved = values.iter().filter_map(|value| match value {
+                    Value::Literal(s) => Some(s.as_str()),
+                    Value::CurrentSpace => context.current_space,
+                });
+                let found = match field {
+                    Field::Label => resolved.any(|value| {
+                        page.labels()
+                            .iter()
+                            .any(|label| label.eq_ignore_ascii_case(value))
+                    }),
+                    Field::Space => resolved.any(|value| page.space_key() == value),
+                    Field::Ancestor => resolved.any(|value| {
+                        page.ancestors()
+                            .iter()
+                            .any(|ancestor| ancestor.to_string() == value)
+                    }),
+                    Field::Type => resolved.any(|value| value.eq_ignore_ascii_case("page")),
+                };
+                found != *negated
+            }
+            Self::Not(query) => !query.matches(page, context),
+            Self::And(lhs, rhs) => lhs.matches(page, context) && rhs.matches(page, context),
+            Self::Or(lhs, rhs) => lhs.matches(page, context) || rhs.matches(page, context),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Function(String),
+    Quoted(String),
+    Equals,
+    NotEquals,
+    OpenParen,
+    CloseParen,
+    Comma,
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Self::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, CqlError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&ch) = chars.peek() {
+        match ch {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::OpenParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::CloseParen);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            '=' => {
+                chars.next();
+                tokens.push(Token::Equals);
+            }
+            '!' => {
+                chars.next();
+                if chars.next() != Some('=') {
+                    return Err(CqlError("expected '=' after '!'".to_string()));
+                }
+                tokens.push(Token::NotEquals);
+            }
+            '"' | '\'' => {
+                let quote = ch;
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => value.extend(chars.next()),
+                        Some(c) if c == quote => break,
+                        Some(c) => value.push(c),
+                        None => return Err(CqlError("unterminated string".to_string())),
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '~' | ':') {
+                        word.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if word.is_empty() {
+                    return Err(CqlError(format!("unexpected character {ch:?}")));
+                }
+
+                let mut lookahead = chars.clone();
+                if lookahead.next() == Some('(') && lookahead.next() == Some(')') {
+                    chars.next();
+                    chars.next();
+                    tokens.push(Token::Function(word));
+                } else {
+                    tokens.push(Token::Word(word));
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), CqlError> {
+        match self.next() {
+            Some(ref token) if token == expected => Ok(()),
+            other => Err(CqlError(format!("expected {expected:?}, found {other:?}"))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<CqlQuery, CqlError> {
+        let mut query = self.parse_and()?;
+        while self.peek().is_some_and(|token| token.is_keyword("or")) {
+            self.next();
+            query = CqlQuery::Or(Box::new(query), Box::new(self.parse_and()?));
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> Result<CqlQuery, CqlError> {
+        let mut query = self.parse_unary()?;
+        while self.peek().is_some_and(|token| token.is_keyword("and")) {
+            self.next();
+            query = CqlQuery::And(Box::new(query), Box::new(self.parse_unary()?));
+        }
+        Ok(query)
+    }
+
+    fn parse_unary(&mut self) -> Result<CqlQuery, CqlError> {
+        match self.peek() {
+            Some(token) if token.is_keyword("not") => {
+                self.next();
+                Ok(CqlQuery::Not(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::OpenParen) => {
+                self.next();
+                let query = self.parse_or()?;
+                self.expect(&Token::CloseParen)?;
+                Ok(query)
+            }
+            _ => self.parse_clause(),
+        }
+    }
+
+    fn parse_clause(&mut self) -> Result<CqlQuery, CqlError> {
+        let field = match self.next() {
+            Some(Token::Word(word)) => Field::from_str(&word)?,
+            other => return Err(CqlError(format!("expected field, found {other:?}"))),
+        };
+
+        let (values, negated) = match self.next() {
+            Some(Token::Equals) => (vec![self.parse_value()?], false),
+            Some(Token::NotEquals) => (vec![self.parse_value()?], true),
+            Some(token) if token.is_keyword("in") => (self.parse_list()?, false),
+            Some(token) if token.is_keyword("not") => match self.next() {
+                Some(token) if token.is_keyword("in") => (self.parse_list()?, true),
+                other => return Err(CqlError(format!("expected 'in', found {other:?}"))),
+            },
+            other => return Err(CqlError(format!("expected operator, found {other:?}"))),
+        };
+
+        Ok(CqlQuery::Clause {
+            field,
+            values,
+            negated,
+        })
+    }
+
+    fn parse_value(&mut self) -> Result<Value, CqlError> {
+        match self.next() {
+            Some(Token::Word(word) | Token::Quoted(word)) => Ok(Value::Literal(word)),
+            Some(Token::Function(name)) if name.eq_ignore_ascii_case("currentSpace") => {
+                Ok(Value::CurrentSpace)
+            }
+            other => Err(CqlError(format!("expected value, found {other:?}"))),
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Vec<Value>, CqlError> {
+        self.expect(&Token::OpenParen)?;
+        let mut values = vec![self.parse_value()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.next();
+            values.push(self.parse_value()?);
+        }
+        self.expect(&Token::CloseParen)?;
+        Ok(values)
+    }
+}
+
+impl FromStr for CqlQuery {
+    type Err = CqlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let query = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(CqlError(format!("unexpected trailing {token:?}")));
+        }
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::ConfluencePageId;
+
+    fn page() -> IndexedPage {
+        IndexedPage::new(ConfluencePageId::from(42), "DOCS", "Some Page")
+            .with_labels(["runbook", "team-a"])
+            .with_ancestors([ConfluencePageId::from(1)])
+    }
+
+    fn matches(query: &str) -> bool {
+        let context = CqlContext {
+            current_space: Some("DOCS"),
+        };
+        CqlQuery::from_str(query)
+            .unwrap()
+            .matches(&page(), &context)
+    }
+
+    #[test]
+    fn test_clauses() {
+        assert!(matches(r#"label = "runbook""#));
+        assert!(!matches("label = other"));
+        assert!(matches("label in (other, 'team-a')"));
+        assert!(matches("label not in (other)"));
+        assert!(matches("space = DOCS and ancestor = 1"));
+        assert!(matches("space = currentSpace() AND type = page"));
+        assert!(!matches(
+            "space != DOCS or (ancestor = 2 and label = runbook)"
+        ));
+        assert!(matches("not label = other"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(CqlQuery::from_str("creator = me").is_err());
+        assert!(CqlQuery::from_str("label = ").is_err());
+        assert!(CqlQuery::from_str("label in (a, b").is_err());
+        assert!(CqlQuery::from_str(r#"label = "a"#).is_err());
+    }
+}
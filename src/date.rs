@@ -0,0 +1,190 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::util::{get_tag_name, get_text_content};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use html2md::{
+    Handle, NodeData, StructuredPrinter, TagHandler, TagHandlerFactory, common::get_tag_attr,
+};
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How dates (e.g. inline `<time>` elements) are rendered.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum DateFormat {
+    /// ISO 8601 as stored by Confluence, e.g. `2025-03-01`.
+    #[default]
+    Iso,
+    /// English locale style, e.g. `Mar 1, 2025`.
+    Locale,
+    /// A custom `strftime` pattern, e.g. `%d.%m.%Y`.
+    Pattern(String),
+}
+
+impl DateFormat {
+    /// Formats the ISO 8601 date or date-time. Values that cannot be parsed or formatted are
+    /// returned unchanged.
+    pub fn format(&self, value: &str) -> String {
+        let value = value.trim();
+        let Some((datetime, has_time)) = parse_datetime(value) else {
+            return value.to_string();
+        };
+
+        let pattern = match self {
+            Self::Iso => return value.to_string(),
+            Self::Locale if has_time => "%b %-d, %Y %H:%M",
+            Self::Locale => "%b %-d, %Y",
+            Self::Pattern(pattern) => pattern,
+        };
+        // Unlike `to_string()`, writing an invalid pattern returns an error instead of panicking.
+        let mut formatted = String::new();
+        match write!(formatted, "{}", datetime.format(pattern)) {
+            Ok(()) => formatted,
+            Err(_) => value.to_string(),
+        }
+    }
+}
+
+impl FromStr for DateFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "iso" => Ok(Self::Iso),
+            "locale" => Ok(Self::Locale),
+            pattern if pattern.contains('%') => Ok(Self::Pattern(pattern.to_string())),
+            _ => Err("invalid date format"),
+        }
+    }
+}
+
+/// Parses a date or date-time and returns whether the value included a time.
+fn parse_datetime(value: &str) -> Option<(NaiveDateTime, bool)> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0).map(|datetime| (datetime, false));
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|datetime| datetime.naive_local())
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f"))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f"))
+        .ok()
+        .map(|datetime| (datetime, true))
+}
+
+/// Returns the text content of the tag like [`get_text_content`], but with the dates of `<time>`
+/// elements formatted.
+pub fn get_text_content_with_dates(tag: &Handle, date_format: &DateFormat) -> String {
+    if get_tag_name(tag).is_some_and(|name| name == "time")
+        && let Some(datetime) = get_tag_attr(tag, "datetime")
+    {
+        return date_format.format(&datetime);
+    }
+    match tag.data {
+        NodeData::Text { .. } => get_text_content(tag),
+        _ => tag
+            .children
+            .borrow()
+            .iter()
+            .map(|child| get_text_content_with_dates(child, date_format))
+            .collect(),
+    }
+}
+
+/// Handler for `<time datetime="…">` elements, which Confluence uses for inline dates.
+pub struct TimeHandler {
+    date_format: Arc<DateFormat>,
+    has_datetime: bool,
+}
+
+impl TagHandler for TimeHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let Some(datetime) = get_tag_attr(tag, "datetime") else {
+            return;
+        };
+        self.has_datetime = true;
+        printer.append_str(&self.date_format.format(&datetime));
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        self.has_datetime
+    }
+}
+
+pub struct TimeHandlerFactory {
+    date_format: Arc<DateFormat>,
+}
+
+impl TimeHandlerFactory {
+    pub fn new(date_format: Arc<DateFormat>) -> Self {
+        Self { date_format }
+    }
+}
+
+impl TagHandlerFactory for TimeHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(TimeHandler {
+            date_format: Arc::clone(&self.date_format),
+            has_datetime: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DateFormat;
+    use crate::{ParseOptions, parse_confluence};
+
+    #[test]
+    fn test_date_format() {
+        assert_eq!(DateFormat::Iso.format("2025-03-01"), "2025-03-01");
+        assert_eq!(DateFormat::Locale.format("2025-03-01"), "Mar 1, 2025");
+        assert_eq!(
+            DateFormat::Locale.format("2025-03-01T14:30:00.000+01:00"),
+            "Mar 1, 2025 14:30"
+        );
+        assert_eq!(
+            DateFormat::Pattern("%d.%m.%Y".to_string()).format("2025-03-01"),
+            "01.03.2025"
+        );
+        assert_eq!(
+            DateFormat::Pattern("%Q".to_string()).format("2025-03-01"),
+            "2025-03-01"
+        );
+        assert_eq!(DateFormat::Locale.format("next week"), "next week");
+    }
+
+    #[test]
+    fn test_time() {
+        crate::markdown_assert_eq!(
+            r#"<p>Release on <time datetime="2025-03-01" />.</p>"#,
+            "Release on 2025-03-01."
+        );
+    }
+
+    #[test]
+    fn test_time_with_format() {
+        let options = ParseOptions::default().with_date_format(DateFormat::Locale);
+        assert_eq!(
+            parse_confluence(
+                r#"<p>Release on <time datetime="2025-03-01" />.</p>"#,
+                &options
+            ),
+            "Release on Mar 1, 2025."
+        );
+    }
+}
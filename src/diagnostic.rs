@@ -0,0 +1,98 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::error::Error;
+use std::fmt;
+
+/// How malformed storage format is handled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Reject documents that are not well-formed with a [`ParseError`]. Unknown entities are
+    /// still kept as text and recorded as [`Diagnostic`]s.
+    #[default]
+    Strict,
+    /// Repair documents that are not well-formed where possible (e.g. by closing unclosed tags)
+    /// and record each repair as a [`Diagnostic`] in the page metadata.
+    Lenient,
+}
+
+/// A 1-based line and column (in bytes) in the source document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A problem in the source document that was recovered from in lenient mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    position: Position,
+    message: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new<S: Into<String>>(position: Position, message: S) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+/// Error for a source document that is not well-formed, in strict mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(Diagnostic);
+
+impl ParseError {
+    pub(crate) fn new<S: Into<String>>(position: Position, message: S) -> Self {
+        Self(Diagnostic::new(position, message))
+    }
+
+    pub fn position(&self) -> Position {
+        self.0.position
+    }
+
+    pub fn message(&self) -> &str {
+        &self.0.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed storage format at {}", self.0)
+    }
+}
+
+impl Error for ParseError {}
@@ -0,0 +1,521 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::flavor::MarkdownFlavor;
+use crate::util::{get_tag_name, html_escape};
+use html2md::{Handle, StructuredPrinter, TagHandlerFactory, walk};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+/// The tag handler factories used for a conversion, keyed by tag name.
+pub(crate) type TagHandlerFactories = HashMap<String, Box<dyn TagHandlerFactory>>;
+
+lazy_static! {
+    static ref EMPTY_LINE_PATTERN: Regex = Regex::new("(?m)^ +$").unwrap();
+    static ref EXCESSIVE_NEWLINE_PATTERN: Regex = Regex::new("\\n{3,}").unwrap();
+    static ref TRAILING_SPACE_PATTERN: Regex = Regex::new("(?m)(\\S) $").unwrap();
+}
+
+/// Applies the same cleanup as html2md does to a complete document.
+pub(crate) fn clean_markdown(markdown: &str) -> String {
+    let markdown = EMPTY_LINE_PATTERN.replace_all(markdown, "");
+    let markdown = EXCESSIVE_NEWLINE_PATTERN.replace_all(&markdown, "\n\n");
+    let markdown = TRAILING_SPACE_PATTERN.replace_all(&markdown, "$1");
+    markdown.trim_start_matches('\n').trim_end().to_string()
+}
+
+/// Renders the content of elements (e.g. a macro body) to Markdown with the handlers of the
+/// current conversion, so that handlers for container elements can wrap the finished content
+/// instead of modifying the output of their descendants.
+#[derive(Clone, Default)]
+pub(crate) struct ContentRenderer {
+    // Weak, because the handler factories themselves hold a renderer.
+    handlers: Weak<OnceCell<TagHandlerFactories>>,
+}
+
+impl fmt::Debug for ContentRenderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContentRenderer").finish_non_exhaustive()
+    }
+}
+
+impl ContentRenderer {
+    pub fn new(handlers: &Rc<OnceCell<TagHandlerFactories>>) -> Self {
+        Self {
+            handlers: Rc::downgrade(handlers),
+        }
+    }
+
+    /// Renders the children of the tag, which is currently handled with the given printer.
+    pub fn render_children(&self, tag: &Handle, printer: &StructuredPrinter) -> String {
+        let children = tag.children.borrow();
+        self.render_nodes(tag, children.iter(), printer)
+    }
+
+    /// Renders the given child nodes of the tag, which is currently handled with the given
+    /// printer.
+    pub fn render_nodes<'a, I: Iterator<Item = &'a Handle>>(
+        &self,
+        tag: &Handle,
+        nodes: I,
+        printer: &StructuredPrinter,
+    ) -> String {
+        let handlers = self.handlers.upgrade();
+        let empty = TagHandlerFactories::new();
+        let handlers = handlers
+            .as_deref()
+            .and_then(OnceCell::get)
+            .unwrap_or(&empty);
+
+        // Continue with the parent chain of the outer printer, so that e.g. list items are
+        // indented correctly.
+        let mut parent_chain = printer.parent_chain.clone();
+        parent_chain.push(get_tag_name(tag).unwrap_or_default());
+        let depth = parent_chain.len();
+        let mut content = StructuredPrinter {
+            parent_chain,
+            ..Default::default()
+        };
+        content.siblings.insert(depth, Vec::new());
+        for node in nodes {
+            walk(node, &mut content, handlers);
+            if let Some(name) = get_tag_name(node)
+                && let Some(siblings) = content.siblings.get_mut(&depth)
+            {
+                siblings.push(name);
+            }
+        }
+        // Unlike a complete document, the content may be inline, so leading whitespace is
+        // removed as well.
+        clean_markdown(&content.data).trim_start().to_string()
+    }
+}
+
+/// Inline content of a paragraph or link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inline {
+    /// Text that is escaped when rendered.
+    Text(String),
+    /// Already rendered Markdown.
+    Markdown(String),
+    Strong(Vec<Inline>),
+    Emphasis(Vec<Inline>),
+    Code(String),
+    Link {
+        content: Vec<Inline>,
+        url: String,
+    },
+}
+
+impl Inline {
+    pub fn to_markdown(&self) -> String {
+        match self {
+            Self::Text(text) => escape_text(text),
+            Self::Markdown(markdown) => markdown.clone(),
+            Self::Strong(content) => format!("**{}**", inlines_to_markdown(content)),
+            Self::Emphasis(content) => format!("*{}*", inlines_to_markdown(content)),
+            Self::Code(code) => {
+                let fence = "`".repeat(longest_run(code, '`') + 1);
+                if code.starts_with('`') || code.ends_with('`') {
+                    format!("{fence} {code} {fence}")
+                } else {
+                    format!("{fence}{code}{fence}")
+                }
+            }
+            Self::Link { content, url } => {
+                format!("[{}]({})", inlines_to_markdown(content), encode_url(url))
+            }
+        }
+    }
+}
+
+fn inlines_to_markdown(inlines: &[Inline]) -> String {
+    inlines.iter().map(Inline::to_markdown).collect()
+}
+
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Percent-encodes the characters that would end or break a Markdown link destination.
+pub(crate) fn encode_url(url: &str) -> String {
+    let mut encoded = String::with_capacity(url.len());
+    for c in url.chars() {
+        match c {
+            ' ' | '(' | ')' | '<' | '>' | '"' | '\\' | '`' => {
+                encoded.push_str(&format!("%{:02X}", c as u32));
+            }
+            c if c.is_control() => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    encoded.push_str(&format!("%{byte:02X}"));
+                }
+            }
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c)
+        .map(str::len)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Kind of an admonition (i.e. the `info`, `tip`, `note` and `warning` macros).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdmonitionKind {
+    Info,
+    Tip,
+    Note,
+    Warning,
+}
+
+impl AdmonitionKind {
+    /// Returns the GitHub alert type.
+    fn gfm_alert(&self) -> &'static str {
+        match self {
+            Self::Info => "IMPORTANT",
+            Self::Tip => "TIP",
+            Self::Note => "NOTE",
+            Self::Warning => "WARNING",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            Self::Info => "Info",
+            Self::Tip => "Tip",
+            Self::Note => "Note",
+            Self::Warning => "Warning",
+        }
+    }
+}
+
+/// A block of the intermediate document tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    /// Already rendered Markdown.
+    Markdown(String),
+    Paragraph(Vec<Inline>),
+    BlockQuote(Vec<Block>),
+    Admonition {
+        kind: AdmonitionKind,
+        title: Option<String>,
+        content: Vec<Block>,
+    },
+    List {
+        ordered: bool,
+        items: Vec<Vec<Block>>,
+    },
+    /// A task list, where each item is either complete or incomplete.
+    TaskList {
+        items: Vec<(bool, Vec<Block>)>,
+    },
+    CodeBlock {
+        language: Option<String>,
+        code: String,
+    },
+    Table {
+        header: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    /// A collapsible section, rendered as `<details>` element, or as bold title followed by the
+    /// content in flavors without raw HTML.
+    Details {
+        summary: String,
+        content: Vec<Block>,
+    },
+    ThematicBreak,
+}
+
+impl Block {
+    /// Serializes the block to Markdown of the given flavor.
+    pub fn to_markdown(&self, flavor: MarkdownFlavor) -> String {
+        match self {
+            Self::Markdown(markdown) => markdown.clone(),
+            Self::Paragraph(content) => inlines_to_markdown(content),
+            Self::BlockQuote(content) => quote(&blocks_to_markdown(content, flavor)),
+            Self::Admonition {
+                kind,
+                title,
+                content,
+            } => {
+                let content = blocks_to_markdown(content, flavor);
+                match flavor {
+                    MarkdownFlavor::Pandoc => {
+                        let title = title
+                            .as_ref()
+                            .map(|title| format!("**{title}**\n\n"))
+                            .unwrap_or_default();
+                        format!(
+                            "::: {kind}\n{title}{content}\n:::",
+                            kind = kind.label().to_lowercase()
+                        )
+                    }
+                    MarkdownFlavor::Plain => {
+                        let title = match title {
+                            Some(title) => format!("**{}: {title}**", kind.label()),
+                            None => format!("**{}**", kind.label()),
+                        };
+                        quote(&join_blocks([title, content]))
+                    }
+                    MarkdownFlavor::Github | MarkdownFlavor::CommonMark => {
+                        let mut header = format!("[!{}]", kind.gfm_alert());
+                        if let Some(title) = title {
+                            header.push_str(&format!("\n**{title}**"));
+                        }
+                        quote(&join_blocks([header, content]))
+                    }
+                }
+            }
+            Self::List { ordered, items } => items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    let marker = if *ordered {
+                        format!("{}. ", index + 1)
+                    } else {
+                        "* ".to_string()
+                    };
+                    indent(&blocks_to_markdown(item, flavor), &marker)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            // The checkbox is part of the item content, so continuation lines are only indented
+            // by the width of the bullet. Plain Markdown has no task lists, so the checkbox is a
+            // symbol there.
+            Self::TaskList { items } => items
+                .iter()
+                .map(|(complete, item)| {
+                    let checkbox = match (flavor, complete) {
+                        (MarkdownFlavor::Plain, true) => "✔",
+                        (MarkdownFlavor::Plain, false) => "☐",
+                        (_, true) => "[x]",
+                        (_, false) => "[ ]",
+                    };
+                    let content = blocks_to_markdown(item, flavor);
+                    indent(format!("{checkbox} {content}").trim_end(), "- ")
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::CodeBlock { language, code } => {
+                let fence = "`".repeat((longest_run(code, '`') + 1).max(3));
+                format!(
+                    "{fence}{language}\n{code}\n{fence}",
+                    language = language.as_deref().unwrap_or_default()
+                )
+            }
+            Self::Table { header, rows } => {
+                let escape_cell = |cell: &String| cell.replace('|', "\\|").replace('\n', " ");
+                let rows: Vec<Vec<String>> = std::iter::once(header)
+                    .chain(rows)
+                    .map(|row| {
+                        (0..header.len())
+                            .map(|index| row.get(index).map(escape_cell).unwrap_or_default())
+                            .collect()
+                    })
+                    .collect();
+                table(&rows, &[]).unwrap_or_default()
+            }
+            Self::Details { summary, content } if flavor.allows_raw_html() => join_blocks([
+                format!("<details><summary>{}</summary>", html_escape(summary)),
+                blocks_to_markdown(content, flavor),
+                "</details>".to_string(),
+            ]),
+            Self::Details { summary, content } => join_blocks([
+                Inline::Strong(vec![Inline::Text(summary.clone())]).to_markdown(),
+                blocks_to_markdown(content, flavor),
+            ]),
+            Self::ThematicBreak => "---".to_string(),
+        }
+    }
+}
+
+/// Serializes the blocks to Markdown, separated by blank lines.
+pub fn blocks_to_markdown(blocks: &[Block], flavor: MarkdownFlavor) -> String {
+    join_blocks(blocks.iter().map(|block| block.to_markdown(flavor)))
+}
+
+fn join_blocks<I: IntoIterator<Item = String>>(blocks: I) -> String {
+    blocks
+        .into_iter()
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Prefixes every line with `> `.
+fn quote(markdown: &str) -> String {
+    markdown
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Prefixes the first line with the list marker and indents the following lines accordingly.
+fn indent(markdown: &str, marker: &str) -> String {
+    let padding = " ".repeat(marker.len());
+    markdown
+        .lines()
+        .enumerate()
+        .map(|(index, line)| match (index, line.is_empty()) {
+            (0, _) => format!("{marker}{line}"),
+            (_, true) => String::new(),
+            (_, false) => format!("{padding}{line}"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn pad_cell(text: &str, width: usize) -> String {
+    let padding = width - text.chars().count();
+    match padding {
+        0 => text.to_string(),
+        1 => format!("{text} "),
+        _ => format!(
+            "{left}{text}{right}",
+            left = " ".repeat(padding / 2),
+            right = " ".repeat(padding - padding / 2)
+        ),
+    }
+}
+
+/// Lays out the rows (whose cells are already escaped) as table in the same style as html2md,
+/// with the first row as header and the cells centered in their columns. Returns `None` if there
+/// are no cells.
+pub(crate) fn table(rows: &[Vec<String>], alignments: &[Option<String>]) -> Option<String> {
+    let column_count = rows.iter().map(Vec::len).max().filter(|count| *count > 0)?;
+    let mut widths = vec![3; column_count];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for (index, row) in rows.iter().enumerate() {
+        if index > 0 {
+            table.push('\n');
+        }
+        table.push('|');
+        for (column, width) in widths.iter().enumerate() {
+            table.push_str(&pad_cell(
+                row.get(column).map(String::as_str).unwrap_or_default(),
+                *width,
+            ));
+            table.push('|');
+        }
+
+        if index == 0 {
+            table.push_str("\n|");
+            for (column, width) in widths.iter().enumerate() {
+                table.push_str(&match alignments.get(column).and_then(Option::as_deref) {
+                    Some("left") => format!(":{}", "-".repeat(width - 1)),
+                    Some("center") => format!(":{}:", "-".repeat(width - 2)),
+                    Some("right") => format!("{}:", "-".repeat(width - 1)),
+                    _ => "-".repeat(*width),
+                });
+                table.push('|');
+            }
+        }
+    }
+    Some(table)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AdmonitionKind, Block, Inline};
+    use crate::MarkdownFlavor;
+
+    #[test]
+    fn test_nested_blocks() {
+        let document = Block::Admonition {
+            kind: AdmonitionKind::Warning,
+            title: Some("Careful".to_string()),
+            content: vec![
+                Block::Paragraph(vec![
+                    Inline::Text("Run ".to_string()),
+                    Inline::Code("make *".to_string()),
+                    Inline::Text(" first:".to_string()),
+                ]),
+                Block::List {
+                    ordered: true,
+                    items: vec![
+                        vec![Block::CodeBlock {
+                            language: Some("sh".to_string()),
+                            code: "make\nmake install".to_string(),
+                        }],
+                        vec![Block::BlockQuote(vec![Block::Markdown("Done".to_string())])],
+                    ],
+                },
+            ],
+        };
+        assert_eq!(
+            document.to_markdown(MarkdownFlavor::Github),
+            "\
+> [!WARNING]
+> **Careful**
+>
+> Run `make *` first:
+>
+> 1. ```sh
+>    make
+>    make install
+>    ```
+> 2. > Done"
+        );
+    }
+
+    #[test]
+    fn test_admonition_flavors() {
+        let admonition = Block::Admonition {
+            kind: AdmonitionKind::Tip,
+            title: None,
+            content: vec![Block::Paragraph(vec![Inline::Link {
+                content: vec![Inline::Text("[docs]".to_string())],
+                url: "docs/read me.md".to_string(),
+            }])],
+        };
+        assert_eq!(
+            admonition.to_markdown(MarkdownFlavor::Pandoc),
+            "::: tip\n[\\[docs\\]](docs/read%20me.md)\n:::"
+        );
+        assert_eq!(
+            admonition.to_markdown(MarkdownFlavor::Plain),
+            "> **Tip**\n>\n> [\\[docs\\]](docs/read%20me.md)"
+        );
+    }
+}
@@ -13,45 +13,147 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::flavor::MarkdownFlavor;
 use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory, common::get_tag_attr};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
 
-#[derive(Default)]
-pub struct EmoticonHandler;
+/// How emoticons and emoji are rendered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EmoticonStyle {
+    /// GitHub-style shortcodes, e.g. `:smiley:`.
+    #[default]
+    Shortcode,
+    /// Unicode emoji, e.g. `😃`.
+    Unicode,
+}
+
+impl EmoticonStyle {
+    pub fn default_for(flavor: MarkdownFlavor) -> Self {
+        match flavor {
+            MarkdownFlavor::Github => Self::Shortcode,
+            _ => Self::Unicode,
+        }
+    }
+}
+
+impl FromStr for EmoticonStyle {
+    type Err = &'static str;
 
-fn confluence_emoticon_to_markdown<S: AsRef<str>>(name: S) -> Option<&'static str> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shortcode" => Ok(Self::Shortcode),
+            "unicode" => Ok(Self::Unicode),
+            _ => Err("invalid emoticon style"),
+        }
+    }
+}
+
+/// Maps the names of the classic Confluence emoticons to a shortcode and a Unicode emoji.
+fn confluence_emoticon<S: AsRef<str>>(name: S) -> Option<(&'static str, &'static str)> {
     match name.as_ref() {
-        "smile" => ":slightly_smiling_face:".into(),    // 🙂
-        "sad" => ":slightly_frowning_face:".into(),     // 🙁
-        "cheeky" => ":stuck_out_tongue:".into(),        // 😛
-        "laugh" => ":smiley:".into(),                   // 😃
-        "wink" => ":wink:".into(),                      // 😉
-        "thumbs-up" => ":thumbsup:".into(),             // 👍
-        "thumbs-down" => ":thumbsdown:".into(),         // 👎
-        "information" => ":information_source:".into(), // ℹ️
-        "tick" => ":white_check_mark:".into(),          // ✅
-        "cross" => ":x:".into(),                        // ❌
-        "warning" => ":warning:".into(),                // ⚠️
-        "plus" => ":heavy_plus_sign:".into(),           // ➕
-        "minus" => ":heavy_minus_sign:".into(),         // ➖
-        "question" => ":question:".into(),              // ❓
-        "light-on" => ":bulb:".into(),                  // 💡
-        "light-off" => ":bulb: (off)".into(),           // 💡
-        "yellow-star" => ":star:".into(),               // ⭐
-        "red-star" => ":star: (red)".into(),            // ⭐
-        "green-star" => ":star: (green)".into(),        // ⭐
-        "blue-star" => ":star: (blue)".into(),          // ⭐
+        "smile" => (":slightly_smiling_face:", "🙂").into(),
+        "sad" => (":slightly_frowning_face:", "🙁").into(),
+        "cheeky" => (":stuck_out_tongue:", "😛").into(),
+        "laugh" => (":smiley:", "😃").into(),
+        "wink" => (":wink:", "😉").into(),
+        "thumbs-up" => (":thumbsup:", "👍").into(),
+        "thumbs-down" => (":thumbsdown:", "👎").into(),
+        "information" => (":information_source:", "ℹ️").into(),
+        "tick" => (":white_check_mark:", "✅").into(),
+        "cross" => (":x:", "❌").into(),
+        "warning" => (":warning:", "⚠️").into(),
+        "plus" => (":heavy_plus_sign:", "➕").into(),
+        "minus" => (":heavy_minus_sign:", "➖").into(),
+        "question" => (":question:", "❓").into(),
+        "light-on" => (":bulb:", "💡").into(),
+        "light-off" => (":bulb:", "💡").into(),
+        "yellow-star" | "red-star" | "green-star" | "blue-star" => (":star:", "⭐").into(),
         _ => None,
     }
 }
 
+/// Resolve an Atlassian emoji id to Unicode.
+///
+/// Standard emoji use their hyphen-separated code points as id (e.g. `1f44d-1f3fb`), the classic
+/// emoticons use an `atlassian-` prefix followed by their name (e.g. `atlassian-blue_star`).
+fn emoji_id_to_unicode(emoji_id: &str) -> Option<String> {
+    if let Some(name) = emoji_id.strip_prefix("atlassian-") {
+        return confluence_emoticon(name.replace('_', "-")).map(|(_, unicode)| unicode.to_string());
+    }
+
+    emoji_id
+        .split('-')
+        .map(|codepoint| {
+            (codepoint.len() <= 6)
+                .then(|| u32::from_str_radix(codepoint, 16).ok())
+                .flatten()
+                .and_then(char::from_u32)
+        })
+        .collect()
+}
+
+fn is_shortcode(s: &str) -> bool {
+    s.len() > 2 && s.starts_with(':') && s.ends_with(':')
+}
+
+#[derive(Default)]
+pub struct EmoticonHandler {
+    style: EmoticonStyle,
+    custom_mappings: Arc<HashMap<String, String>>,
+}
+
+impl EmoticonHandler {
+    pub fn new(style: EmoticonStyle, custom_mappings: Arc<HashMap<String, String>>) -> Self {
+        Self {
+            style,
+            custom_mappings,
+        }
+    }
+
+    fn render(&self, tag: &Handle) -> Option<String> {
+        let name = get_tag_attr(tag, "ac:name");
+        let shortname = get_tag_attr(tag, "ac:emoji-shortname");
+        let emoji_id = get_tag_attr(tag, "ac:emoji-id");
+        let fallback = get_tag_attr(tag, "ac:emoji-fallback");
+
+        // Confluence Cloud sets `ac:name="blue-star"` as placeholder on emoji, so the name only
+        // identifies legacy emoticons without any emoji attributes.
+        let name = name.filter(|_| shortname.is_none() && emoji_id.is_none() && fallback.is_none());
+
+        let custom = [&name, &shortname, &emoji_id]
+            .into_iter()
+            .flatten()
+            .find_map(|key| self.custom_mappings.get(key));
+        if let Some(custom) = custom {
+            return Some(custom.clone());
+        }
+
+        let emoticon = name.as_deref().and_then(confluence_emoticon);
+        match self.style {
+            EmoticonStyle::Shortcode => shortname
+                .filter(|shortname| is_shortcode(shortname))
+                .or_else(|| emoticon.map(|(shortcode, _)| shortcode.to_string()))
+                .or_else(|| emoji_id.as_deref().and_then(emoji_id_to_unicode))
+                .or(fallback),
+            EmoticonStyle::Unicode => emoji_id
+                .as_deref()
+                .and_then(emoji_id_to_unicode)
+                .or_else(|| emoticon.map(|(_, unicode)| unicode.to_string()))
+                .or_else(|| fallback.filter(|fallback| !is_shortcode(fallback)))
+                .or(shortname),
+        }
+    }
+}
+
 impl TagHandler for EmoticonHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
-        let name = get_tag_attr(tag, "ac:name");
-        let Some(emoticon) = name.as_deref().and_then(confluence_emoticon_to_markdown) else {
+        let Some(emoticon) = self.render(tag) else {
             return;
         };
 
-        printer.append_str(emoticon);
+        printer.append_str(&emoticon);
     }
 
     fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
@@ -61,17 +163,33 @@ impl TagHandler for EmoticonHandler {
     }
 }
 
-pub struct EmoticonHandlerFactory;
+pub struct EmoticonHandlerFactory {
+    style: EmoticonStyle,
+    custom_mappings: Arc<HashMap<String, String>>,
+}
+
+impl EmoticonHandlerFactory {
+    pub fn new(style: EmoticonStyle, custom_mappings: Arc<HashMap<String, String>>) -> Self {
+        Self {
+            style,
+            custom_mappings,
+        }
+    }
+}
 
 impl TagHandlerFactory for EmoticonHandlerFactory {
     fn instantiate(&self) -> Box<dyn TagHandler> {
-        Box::new(EmoticonHandler {})
+        Box::new(EmoticonHandler::new(
+            self.style,
+            Arc::clone(&self.custom_mappings),
+        ))
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::markdown_assert_eq;
+    use crate::{EmoticonStyle, ParseOptions, parse_confluence};
 
     #[test]
     fn test_tick() {
@@ -82,4 +200,40 @@ mod test {
     fn test_laugh() {
         markdown_assert_eq!(r#"<ac:emoticon ac:name="laugh"/>"#, ":smiley:");
     }
+
+    #[test]
+    fn test_unicode() {
+        let options = ParseOptions::default().with_emoticon_style(EmoticonStyle::Unicode);
+        let md = parse_confluence(r#"<ac:emoticon ac:name="blue-star"/>"#, &options);
+        assert_eq!(md, "⭐");
+    }
+
+    #[test]
+    fn test_emoji_attributes() {
+        let html = r#"<ac:emoticon ac:name="blue-star" ac:emoji-shortname=":thumbsup_tone1:" ac:emoji-id="1f44d-1f3fb" ac:emoji-fallback="👍🏻"/>"#;
+        markdown_assert_eq!(html, ":thumbsup_tone1:");
+
+        let options = ParseOptions::default().with_emoticon_style(EmoticonStyle::Unicode);
+        assert_eq!(parse_confluence(html, &options), "👍🏻");
+    }
+
+    #[test]
+    fn test_custom_emoji() {
+        let html = r#"<ac:emoticon ac:name="blue-star" ac:emoji-shortname=":partyparrot:" ac:emoji-id="f3c27e0c-2c49-4a4e-a4b5-b2e2c1e0d001" ac:emoji-fallback="Party parrot"/>"#;
+        markdown_assert_eq!(html, ":partyparrot:");
+
+        let options = ParseOptions::default().with_emoticon_style(EmoticonStyle::Unicode);
+        assert_eq!(parse_confluence(html, &options), "Party parrot");
+    }
+
+    #[test]
+    fn test_custom_mapping() {
+        let options = ParseOptions::default()
+            .with_emoticon_mapping("f3c27e0c-2c49-4a4e-a4b5-b2e2c1e0d001", "🦜");
+        let md = parse_confluence(
+            r#"<ac:emoticon ac:name="blue-star" ac:emoji-shortname=":partyparrot:" ac:emoji-id="f3c27e0c-2c49-4a4e-a4b5-b2e2c1e0d001" ac:emoji-fallback=":partyparrot:"/>"#,
+            &options,
+        );
+        assert_eq!(md, "🦜");
+    }
 }
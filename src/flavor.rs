@@ -0,0 +1,87 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::str::FromStr;
+
+/// The Markdown dialect of the generated output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MarkdownFlavor {
+    /// GitHub Flavored Markdown, including alerts and raw HTML.
+    #[default]
+    Github,
+    /// CommonMark with raw HTML.
+    CommonMark,
+    /// Pandoc Markdown, including attribute syntax like `{width=50%}`.
+    Pandoc,
+    /// Markdown without any raw HTML.
+    Plain,
+}
+
+impl MarkdownFlavor {
+    /// Whether raw HTML (e.g. `<img>` or `<details>`) may be emitted.
+    pub fn allows_raw_html(&self) -> bool {
+        !matches!(self, Self::Plain)
+    }
+
+    /// Whether embedded media (`<video>` and `<iframe>`) may be emitted. GitHub strips them from
+    /// rendered Markdown, so linked thumbnails are used instead.
+    pub fn allows_embeds(&self) -> bool {
+        matches!(self, Self::CommonMark | Self::Pandoc)
+    }
+
+    /// Whether attributes in curly braces after images and spans are supported.
+    pub fn supports_attributes(&self) -> bool {
+        matches!(self, Self::Pandoc)
+    }
+}
+
+impl FromStr for MarkdownFlavor {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" | "gfm" => Ok(Self::Github),
+            "commonmark" => Ok(Self::CommonMark),
+            "pandoc" => Ok(Self::Pandoc),
+            "plain" => Ok(Self::Plain),
+            _ => Err("invalid flavor"),
+        }
+    }
+}
+
+/// How raw HTML from `html` macros is handled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RawHtmlPolicy {
+    /// Keep the HTML, but only allowlisted elements and attributes and URLs with a safe scheme.
+    #[default]
+    Sanitize,
+    /// Keep the HTML unchanged.
+    Keep,
+    /// Convert the HTML to Markdown, dropping all tags without a Markdown equivalent.
+    Strip,
+}
+
+impl FromStr for RawHtmlPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sanitize" => Ok(Self::Sanitize),
+            "keep" => Ok(Self::Keep),
+            "strip" => Ok(Self::Strip),
+            _ => Err("invalid raw HTML policy"),
+        }
+    }
+}
@@ -13,40 +13,186 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::util::{ConfluencePageId, ConfluenceServer, get_tag_name};
+use crate::attachment::AttachmentResolver;
+use crate::document::{Inline, encode_url};
+use crate::flavor::MarkdownFlavor;
+use crate::macros::DocumentContext;
+use crate::util::{find_child, get_tag_name, get_text_content, html_escape};
 use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory, common::get_tag_attr};
+use std::rc::Rc;
+
+/// Display attributes of an `ac:image`.
+#[derive(Debug, Default)]
+struct ImageAttributes {
+    alt: String,
+    width: Option<String>,
+    height: Option<String>,
+    align: Option<String>,
+    border: bool,
+    thumbnail: bool,
+    caption: Option<String>,
+}
+
+impl ImageAttributes {
+    fn from_tag(tag: &Handle) -> Self {
+        let bool_attr = |name| get_tag_attr(tag, name).is_some_and(|value| value == "true");
+        Self {
+            alt: get_tag_attr(tag, "ac:title")
+                .or_else(|| get_tag_attr(tag, "ac:alt"))
+                .unwrap_or_default(),
+            width: get_tag_attr(tag, "ac:width"),
+            height: get_tag_attr(tag, "ac:height"),
+            align: get_tag_attr(tag, "ac:align").filter(|align| align != "none"),
+            border: bool_attr("ac:border"),
+            thumbnail: bool_attr("ac:thumbnail"),
+            caption: find_child(tag, "ac:caption")
+                .map(|caption| get_text_content(&caption).trim().to_string())
+                .filter(|caption| !caption.is_empty()),
+        }
+    }
+
+    fn has_layout(&self) -> bool {
+        self.width.is_some() || self.height.is_some() || self.align.is_some() || self.border
+    }
+
+    fn render_markdown(&self, url: &str) -> String {
+        format!(
+            "![{alt}]({url})",
+            alt = Inline::Text(self.alt.clone()).to_markdown(),
+            url = encode_url(url)
+        )
+    }
+
+    fn render_pandoc(&self, url: &str) -> String {
+        let mut attributes = Vec::new();
+        if let Some(width) = &self.width {
+            attributes.push(format!("width={width}"));
+        }
+        if let Some(height) = &self.height {
+            attributes.push(format!("height={height}"));
+        }
+        if let Some(align) = &self.align {
+            attributes.push(format!(".align-{align}"));
+        }
+        if self.border {
+            attributes.push(".border".to_string());
+        }
+
+        // Pandoc renders an image that is alone in a paragraph as figure with the alt text as
+        // caption.
+        let alt = Inline::Text(self.caption.clone().unwrap_or_else(|| self.alt.clone()));
+        let (alt, url) = (alt.to_markdown(), encode_url(url));
+        if attributes.is_empty() {
+            format!("![{alt}]({url})")
+        } else {
+            format!("![{alt}]({url}){{{}}}", attributes.join(" "))
+        }
+    }
+
+    fn render_html(&self, url: &str) -> String {
+        let mut img = format!(
+            r#"<img src="{src}" alt="{alt}""#,
+            src = html_escape(url),
+            alt = html_escape(&self.alt)
+        );
+        if let Some(width) = &self.width {
+            img.push_str(&format!(r#" width="{}""#, html_escape(width)));
+        }
+        if let Some(height) = &self.height {
+            img.push_str(&format!(r#" height="{}""#, html_escape(height)));
+        }
+        if let Some(align) = self.align.as_deref().filter(|align| *align != "center") {
+            img.push_str(&format!(r#" align="{}""#, html_escape(align)));
+        }
+        if self.border {
+            img.push_str(r#" border="1""#);
+        }
+        img.push('>');
+        img
+    }
+}
 
 #[derive(Default)]
 pub struct ImageHandler {
-    page: Option<(ConfluenceServer, ConfluencePageId)>,
+    attachments: Rc<AttachmentResolver>,
+    flavor: MarkdownFlavor,
 }
 
 impl ImageHandler {
-    pub fn with_confluence_page(page: Option<(ConfluenceServer, ConfluencePageId)>) -> Self {
-        Self { page }
+    pub fn new(attachments: Rc<AttachmentResolver>, flavor: MarkdownFlavor) -> Self {
+        Self {
+            attachments,
+            flavor,
+        }
+    }
+
+    fn render(&self, attributes: &ImageAttributes, url: &str) -> String {
+        if self.flavor.supports_attributes() {
+            let image = attributes.render_pandoc(url);
+            return if attributes.thumbnail {
+                format!("[{image}]({url})", url = encode_url(url))
+            } else {
+                image
+            };
+        }
+
+        if !self.flavor.allows_raw_html()
+            || (!attributes.has_layout() && attributes.caption.is_none())
+        {
+            let image = attributes.render_markdown(url);
+            let image = if attributes.thumbnail {
+                format!("[{image}]({url})", url = encode_url(url))
+            } else {
+                image
+            };
+            return match &attributes.caption {
+                Some(caption) => {
+                    let caption = Inline::Emphasis(vec![Inline::Text(caption.clone())]);
+                    format!(
+                        "\n\n{image}\n{caption}\n\n",
+                        caption = caption.to_markdown()
+                    )
+                }
+                None => image,
+            };
+        }
+
+        let mut image = attributes.render_html(url);
+        if attributes.thumbnail {
+            image = format!(r#"<a href="{href}">{image}</a>"#, href = html_escape(url));
+        }
+        if let Some(caption) = &attributes.caption {
+            image = format!(
+                "<figure>{image}<figcaption>{caption}</figcaption></figure>",
+                caption = html_escape(caption)
+            );
+        }
+        if attributes.align.as_deref() == Some("center") {
+            // A `<figure>` cannot be nested in a `<p>`.
+            image = format!(r#"<div align="center">{image}</div>"#);
+        }
+        if attributes.caption.is_some() || attributes.align.as_deref() == Some("center") {
+            image = format!("\n\n{image}\n\n");
+        }
+        image
     }
 }
 
 impl TagHandler for ImageHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
-        let caption = get_tag_attr(tag, "ac:title").or_else(|| get_tag_attr(tag, "ac:alt"));
-
         let children = tag.children.borrow();
         let Some(url) = children.iter().find_map(|child| {
             get_tag_name(child).as_deref().and_then(|name| match name {
                 "ri:url" => get_tag_attr(child, "ri:value"),
-                "ri:attachment" => self.page.as_ref().and_then(|(server, page_id)| {
-                    get_tag_attr(child, "ri:filename")
-                        .map(|filename| server.attachment_url(page_id, filename))
-                }),
+                "ri:attachment" => self.attachments.reference_url(child),
                 _ => None,
             })
         }) else {
             return;
         };
 
-        let title = caption.as_deref().unwrap_or("");
-        printer.append_str(&format!("![{title}]({url})"));
+        let attributes = ImageAttributes::from_tag(tag);
+        printer.append_str(&self.render(&attributes, &url));
     }
 
     fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
@@ -56,17 +202,107 @@ impl TagHandler for ImageHandler {
     }
 }
 pub struct ImageHandlerFactory {
-    page: Option<(ConfluenceServer, ConfluencePageId)>,
+    document: DocumentContext,
+    flavor: MarkdownFlavor,
 }
 
 impl ImageHandlerFactory {
-    pub fn with_confluence_page(page: Option<(ConfluenceServer, ConfluencePageId)>) -> Self {
-        Self { page }
+    pub(crate) fn new(document: DocumentContext, flavor: MarkdownFlavor) -> Self {
+        Self { document, flavor }
     }
 }
 
 impl TagHandlerFactory for ImageHandlerFactory {
     fn instantiate(&self) -> Box<dyn TagHandler> {
-        Box::new(ImageHandler::with_confluence_page(self.page.clone()))
+        Box::new(ImageHandler::new(
+            self.document.get().attachments,
+            self.flavor,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::markdown_assert_eq;
+    use crate::{MarkdownFlavor, ParseOptions, parse_confluence};
+
+    const SIZED_IMAGE: &str = r#"
+<ac:image ac:width="300" ac:align="right" ac:border="true" ac:title="Logo">
+  <ri:url ri:value="https://example.com/logo.png" />
+</ac:image>"#;
+
+    const CAPTIONED_THUMBNAIL: &str = r#"
+<ac:image ac:thumbnail="true" ac:align="center" ac:alt="Screenshot">
+  <ri:url ri:value="https://example.com/screen.png" />
+  <ac:caption><p>The main screen</p></ac:caption>
+</ac:image>"#;
+
+    #[test]
+    fn test_plain_image() {
+        markdown_assert_eq!(
+            r#"<ac:image ac:alt="Logo"><ri:url ri:value="https://example.com/logo.png" /></ac:image>"#,
+            "![Logo](https://example.com/logo.png)"
+        );
+    }
+
+    #[test]
+    fn test_html_fallback() {
+        markdown_assert_eq!(
+            SIZED_IMAGE,
+            r#"<img src="https://example.com/logo.png" alt="Logo" width="300" align="right" border="1">"#
+        );
+    }
+
+    #[test]
+    fn test_html_figure() {
+        markdown_assert_eq!(
+            CAPTIONED_THUMBNAIL,
+            r#"<div align="center"><figure><a href="https://example.com/screen.png"><img src="https://example.com/screen.png" alt="Screenshot"></a><figcaption>The main screen</figcaption></figure></div>"#
+        );
+    }
+
+    #[test]
+    fn test_pandoc_attributes() {
+        let options = ParseOptions::default().with_flavor(MarkdownFlavor::Pandoc);
+        assert_eq!(
+            parse_confluence(SIZED_IMAGE, &options),
+            "![Logo](https://example.com/logo.png){width=300 .align-right .border}"
+        );
+        assert_eq!(
+            parse_confluence(CAPTIONED_THUMBNAIL, &options),
+            "[![The main screen](https://example.com/screen.png){.align-center}](https://example.com/screen.png)"
+        );
+    }
+
+    #[test]
+    fn test_plain_flavor() {
+        let options = ParseOptions::default().with_flavor(MarkdownFlavor::Plain);
+        assert_eq!(
+            parse_confluence(CAPTIONED_THUMBNAIL, &options),
+            "[![Screenshot](https://example.com/screen.png)](https://example.com/screen.png)\n*The main screen*"
+        );
+        assert_eq!(
+            parse_confluence(
+                r#"<ac:image><ri:url ri:value="https://example.com/a.png" /><ac:caption>*Fig_1* [draft]</ac:caption></ac:image>"#,
+                &options
+            ),
+            "![](https://example.com/a.png)\n*\\*Fig\\_1\\* \\[draft\\]*"
+        );
+    }
+
+    #[test]
+    fn test_alt_and_caption_are_escaped() {
+        let image = r#"<ac:image ac:alt="a [b]"><ri:url ri:value="https://example.com/a (1).png" /><ac:caption>Fig_1 [draft]</ac:caption></ac:image>"#;
+        let plain_image = r#"<ac:image ac:alt="a [b]"><ri:url ri:value="https://example.com/a (1).png" /></ac:image>"#;
+        markdown_assert_eq!(
+            plain_image,
+            "![a \\[b\\]](https://example.com/a%20%281%29.png)"
+        );
+
+        let options = ParseOptions::default().with_flavor(MarkdownFlavor::Pandoc);
+        assert_eq!(
+            parse_confluence(image, &options),
+            "![Fig\\_1 \\[draft\\]](https://example.com/a%20%281%29.png)"
+        );
     }
 }
@@ -0,0 +1,210 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::document::Inline;
+use crate::manifest::debug_hash;
+use crate::metadata::PageMetadata;
+use crate::util::{ConfluencePageId, ConfluenceServer};
+use std::collections::{BTreeMap, HashMap};
+
+/// A page known to the [`PageIndex`].
+#[derive(Debug, Clone)]
+pub struct IndexedPage {
+    id: ConfluencePageId,
+    space_key: String,
+    title: String,
+    labels: Vec<String>,
+    ancestors: Vec<ConfluencePageId>,
+    metadata: PageMetadata,
+    path: Option<String>,
+}
+
+impl IndexedPage {
+    pub fn new<S: Into<String>, T: Into<String>>(
+        id: ConfluencePageId,
+        space_key: S,
+        title: T,
+    ) -> Self {
+        Self {
+            id,
+            space_key: space_key.into(),
+            title: title.into(),
+            labels: Vec::new(),
+            ancestors: Vec::new(),
+            metadata: PageMetadata::default(),
+            path: None,
+        }
+    }
+
+    pub fn with_labels<I: IntoIterator<Item = S>, S: Into<String>>(mut self, labels: I) -> Self {
+        self.labels = labels.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_ancestors<I: IntoIterator<Item = ConfluencePageId>>(
+        mut self,
+        ancestors: I,
+    ) -> Self {
+        self.ancestors = ancestors.into_iter().collect();
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: PageMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Sets the path of the converted Markdown file, which is used when linking to this page.
+    pub fn with_path<S: Into<String>>(mut self, path: S) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn id(&self) -> &ConfluencePageId {
+        &self.id
+    }
+
+    pub fn space_key(&self) -> &str {
+        &self.space_key
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    pub fn ancestors(&self) -> &[ConfluencePageId] {
+        &self.ancestors
+    }
+
+    pub fn metadata(&self) -> &PageMetadata {
+        &self.metadata
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Returns a link to the page, or just its title if it cannot be linked.
+    pub fn to_link(&self, server: Option<&ConfluenceServer>) -> Inline {
+        let url = self.path.clone().or_else(|| {
+            server.map(|server| server.page_url_with_space_and_title(&self.space_key, &self.title))
+        });
+        let title = Inline::Text(self.title.clone());
+        match url {
+            Some(url) => Inline::Link {
+                content: vec![title],
+                url,
+            },
+            None => title,
+        }
+    }
+
+    /// Returns a Markdown link to the page, or just its title if it cannot be linked.
+    pub fn to_markdown_link(&self, server: Option<&ConfluenceServer>) -> String {
+        self.to_link(server).to_markdown()
+    }
+}
+
+/// Index of converted pages, used to statically render macros that aggregate content across pages
+/// (e.g. `detailssummary` or `contentbylabel`).
+#[derive(Debug, Default, Clone)]
+pub struct PageIndex {
+    pages: Vec<IndexedPage>,
+    positions: HashMap<ConfluencePageId, usize>,
+}
+
+impl PageIndex {
+    /// Adds the page, replacing a page with the same id.
+    pub fn insert(&mut self, page: IndexedPage) {
+        match self.positions.get(&page.id) {
+            Some(&position) => self.pages[position] = page,
+            None => {
+                self.positions.insert(page.id.clone(), self.pages.len());
+                self.pages.push(page);
+            }
+        }
+    }
+
+    /// Returns all pages in insertion order.
+    pub fn pages(&self) -> &[IndexedPage] {
+        &self.pages
+    }
+
+    pub fn by_id(&self, page_id: &ConfluencePageId) -> Option<&IndexedPage> {
+        self.positions
+            .get(page_id)
+            .map(|&position| &self.pages[position])
+    }
+
+    /// Returns a hash of all pages, which changes whenever a page changes.
+    pub(crate) fn fingerprint(&self) -> String {
+        let pages: BTreeMap<_, _> = self
+            .pages
+            .iter()
+            .map(|page| (page.id.to_string(), page))
+            .collect();
+        debug_hash(&pages)
+    }
+}
+
+impl FromIterator<IndexedPage> for PageIndex {
+    fn from_iter<I: IntoIterator<Item = IndexedPage>>(pages: I) -> Self {
+        let mut index = Self::default();
+        for page in pages {
+            index.insert(page);
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_insert_replaces_page() {
+        let mut index: PageIndex = [
+            IndexedPage::new(ConfluencePageId::from(1), "DOCS", "One"),
+            IndexedPage::new(ConfluencePageId::from(2), "DOCS", "Two"),
+        ]
+        .into_iter()
+        .collect();
+        index.insert(IndexedPage::new(ConfluencePageId::from(1), "DOCS", "Uno"));
+
+        let titles: Vec<_> = index.pages().iter().map(IndexedPage::title).collect();
+        assert_eq!(titles, ["Uno", "Two"]);
+        assert_eq!(
+            index
+                .by_id(&ConfluencePageId::from(2))
+                .map(IndexedPage::title),
+            Some("Two")
+        );
+        assert!(index.by_id(&ConfluencePageId::from(3)).is_none());
+    }
+
+    #[test]
+    fn test_markdown_link_is_escaped() {
+        let page = IndexedPage::new(ConfluencePageId::from(1), "DOCS", "[Draft] *v2*")
+            .with_path("drafts/v2 (old).md");
+        assert_eq!(
+            page.to_markdown_link(None),
+            "[\\[Draft\\] \\*v2\\*](drafts/v2%20%28old%29.md)"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::manifest::debug_hash;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+/// Cached metadata of a single Jira issue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JiraIssue {
+    key: String,
+    fields: HashMap<String, String>,
+}
+
+impl JiraIssue {
+    pub fn new<S: Into<String>>(key: S) -> Self {
+        Self {
+            key: key.into(),
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn with_field<S: Into<String>, T: Into<String>>(mut self, name: S, value: T) -> Self {
+        self.fields.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn summary(&self) -> Option<&str> {
+        self.field("summary")
+    }
+
+    pub fn status(&self) -> Option<&str> {
+        self.field("status")
+    }
+
+    /// Returns the value of a field, using the column names of the Jira macro (e.g. `type` or
+    /// `due`) or the Jira field ids (e.g. `issuetype` or `duedate`).
+    pub fn field<S: AsRef<str>>(&self, name: S) -> Option<&str> {
+        let name = name.as_ref().to_ascii_lowercase();
+        let name = match name.as_str() {
+            "key" => return Some(&self.key),
+            "type" => "issuetype",
+            "due" => "duedate",
+            other => other,
+        };
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+/// Source of Jira issue metadata used to render the `jira` macro without access to the server.
+pub trait JiraIssueProvider: fmt::Debug + Send + Sync {
+    /// Returns the issue with the given key, if known.
+    fn issue(&self, key: &str) -> Option<JiraIssue>;
+
+    /// Returns the issues matching the JQL query, or `None` if the result is unknown.
+    fn search(&self, jql: &str) -> Option<Vec<JiraIssue>>;
+
+    /// Returns a value that changes whenever the provided issues change (e.g. a hash or a
+    /// revision), so that incremental conversions detect outdated pages.
+    ///
+    /// Without a fingerprint, incremental conversions convert all pages again.
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
+}
+
+/// [`JiraIssueProvider`] backed by a JSON file.
+///
+/// The file contains an `issues` array in the format of Jira's REST search response (each issue
+/// having a `key` and a `fields` object) and an optional `queries` object that maps JQL queries to
+/// the keys of the matching issues:
+///
+/// ```json
+/// {
+///   "issues": [
+///     {"key": "CONF-1", "fields": {"summary": "Fix it", "status": {"name": "Open"}}}
+///   ],
+///   "queries": {"project = CONF": ["CONF-1"]}
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct JsonJiraIssueProvider {
+    issues: HashMap<String, JiraIssue>,
+    queries: HashMap<String, Vec<String>>,
+}
+
+/// Convert a field value of Jira's REST API to a display string.
+fn field_value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Array(values) => {
+            let values: Vec<_> = values.iter().filter_map(field_value_to_string).collect();
+            Some(values.join(", "))
+        }
+        Value::Object(object) => ["displayName", "name", "value", "key"]
+            .iter()
+            .find_map(|name| object.get(*name))
+            .and_then(field_value_to_string),
+    }
+}
+
+impl JsonJiraIssueProvider {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> io::Result<Self> {
+        let value: Value = serde_json::from_reader(reader)?;
+        Self::try_from(value).map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, msg))
+    }
+}
+
+impl TryFrom<Value> for JsonJiraIssueProvider {
+    type Error = &'static str;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let issues = value
+            .get("issues")
+            .and_then(Value::as_array)
+            .ok_or("missing issues array")?
+            .iter()
+            .map(|issue| {
+                let key = issue
+                    .get("key")
+                    .and_then(Value::as_str)
+                    .ok_or("missing issue key")?;
+                let fields = issue
+                    .get("fields")
+                    .and_then(Value::as_object)
+                    .map(|fields| {
+                        fields
+                            .iter()
+                            .filter_map(|(name, value)| {
+                                field_value_to_string(value).map(|value| (name.clone(), value))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let issue = JiraIssue {
+                    key: key.to_string(),
+                    fields,
+                };
+                Ok((issue.key.clone(), issue))
+            })
+            .collect::<Result<_, Self::Error>>()?;
+
+        let queries = value
+            .get("queries")
+            .and_then(Value::as_object)
+            .map(|queries| {
+                queries
+                    .iter()
+                    .map(|(jql, keys)| {
+                        let keys = keys
+                            .as_array()
+                            .map(|keys| {
+                                keys.iter()
+                                    .filter_map(Value::as_str)
+                                    .map(str::to_string)
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+                        (jql.trim().to_string(), keys)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self { issues, queries })
+    }
+}
+
+impl JiraIssueProvider for JsonJiraIssueProvider {
+    fn issue(&self, key: &str) -> Option<JiraIssue> {
+        self.issues.get(key).cloned()
+    }
+
+    fn search(&self, jql: &str) -> Option<Vec<JiraIssue>> {
+        self.queries
+            .get(jql.trim())
+            .map(|keys| keys.iter().filter_map(|key| self.issue(key)).collect())
+    }
+
+    fn fingerprint(&self) -> Option<String> {
+        let issues: BTreeMap<_, BTreeMap<_, _>> = self
+            .issues
+            .iter()
+            .map(|(key, issue)| (key, issue.fields.iter().collect()))
+            .collect();
+        let queries: BTreeMap<_, _> = self.queries.iter().collect();
+        Some(debug_hash(&(issues, queries)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json_provider() {
+        let json = r#"{
+  "issues": [
+    {"key": "CONF-1", "fields": {"summary": "First", "status": {"name": "Open"},
+     "issuetype": {"name": "Bug"}, "assignee": {"displayName": "Jane Doe"}, "labels": ["a", "b"]}},
+    {"key": "CONF-2", "fields": {"summary": "Second", "status": {"name": "Done"}, "assignee": null}}
+  ],
+  "queries": {"project = CONF": ["CONF-2", "CONF-1", "CONF-3"]}
+}"#;
+        let provider = JsonJiraIssueProvider::from_reader(json.as_bytes()).unwrap();
+
+        let issue = provider.issue("CONF-1").unwrap();
+        assert_eq!(issue.summary(), Some("First"));
+        assert_eq!(issue.status(), Some("Open"));
+        assert_eq!(issue.field("type"), Some("Bug"));
+        assert_eq!(issue.field("assignee"), Some("Jane Doe"));
+        assert_eq!(issue.field("labels"), Some("a, b"));
+        assert_eq!(provider.issue("CONF-2").unwrap().field("assignee"), None);
+
+        let keys: Vec<_> = provider
+            .search(" project = CONF ")
+            .unwrap()
+            .into_iter()
+            .map(|issue| issue.key)
+            .collect();
+        assert_eq!(keys, ["CONF-2", "CONF-1"]);
+        assert!(provider.search("project = OTHER").is_none());
+    }
+}
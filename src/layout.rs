@@ -0,0 +1,310 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::flavor::MarkdownFlavor;
+use crate::macros::DocumentContext;
+use crate::metadata::SharedPageMetadata;
+use crate::util::{child_elements, get_parent, get_tag_name, html_escape};
+use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory, common::get_tag_attr};
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// How multi-column page layouts and `section`/`column` macros are rendered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutStyle {
+    /// The cells in reading order, separated by thematic breaks.
+    #[default]
+    Linear,
+    /// A grid of columns, using `<div>` elements or Pandoc's column divs. Falls back to
+    /// [`LayoutStyle::Linear`] if the flavor does not allow raw HTML.
+    Grid,
+}
+
+impl FromStr for LayoutStyle {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Self::Linear),
+            "grid" => Ok(Self::Grid),
+            _ => Err("invalid layout style"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayoutMode {
+    Linear,
+    Html,
+    Pandoc,
+}
+
+/// Renders the start and end of multi-column sections and their cells.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct LayoutRenderer {
+    style: LayoutStyle,
+    flavor: MarkdownFlavor,
+}
+
+impl LayoutRenderer {
+    pub fn new(style: LayoutStyle, flavor: MarkdownFlavor) -> Self {
+        Self { style, flavor }
+    }
+
+    fn mode(&self) -> LayoutMode {
+        match self.style {
+            LayoutStyle::Grid if self.flavor.supports_attributes() => LayoutMode::Pandoc,
+            LayoutStyle::Grid if self.flavor.allows_raw_html() => LayoutMode::Html,
+            _ => LayoutMode::Linear,
+        }
+    }
+
+    pub fn open_section(&self, printer: &mut StructuredPrinter, metadata: &SharedPageMetadata) {
+        metadata.borrow_mut().add_section();
+        printer.insert_newline();
+        printer.insert_newline();
+        match self.mode() {
+            LayoutMode::Linear => (),
+            LayoutMode::Html => printer.append_str(r#"<div style="display: flex; gap: 1em">"#),
+            LayoutMode::Pandoc => printer.append_str(":::: columns"),
+        }
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+
+    pub fn close_section(&self, printer: &mut StructuredPrinter) {
+        printer.insert_newline();
+        printer.insert_newline();
+        match self.mode() {
+            LayoutMode::Linear => (),
+            LayoutMode::Html => printer.append_str("</div>"),
+            LayoutMode::Pandoc => printer.append_str("::::"),
+        }
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+
+    /// Starts the cell with the given index and records its width in the page metadata.
+    pub fn open_cell(
+        &self,
+        printer: &mut StructuredPrinter,
+        metadata: &SharedPageMetadata,
+        index: usize,
+        width: Option<&str>,
+    ) {
+        metadata.borrow_mut().add_column_width(width);
+        printer.insert_newline();
+        printer.insert_newline();
+        match self.mode() {
+            LayoutMode::Linear => {
+                if index > 0 {
+                    printer.append_str("---");
+                    printer.insert_newline();
+                    printer.insert_newline();
+                }
+            }
+            LayoutMode::Html => match width {
+                Some(width) => printer.append_str(&format!(
+                    r#"<div style="flex: 0 0 {width}">"#,
+                    width = html_escape(width)
+                )),
+                None => printer.append_str(r#"<div style="flex: 1">"#),
+            },
+            LayoutMode::Pandoc => match width {
+                Some(width) => printer.append_str(&format!(r#"::: {{.column width="{width}"}}"#)),
+                None => printer.append_str("::: column"),
+            },
+        }
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+
+    pub fn close_cell(&self, printer: &mut StructuredPrinter) {
+        printer.insert_newline();
+        printer.insert_newline();
+        match self.mode() {
+            LayoutMode::Linear => (),
+            LayoutMode::Html => printer.append_str("</div>"),
+            LayoutMode::Pandoc => printer.append_str(":::"),
+        }
+        printer.insert_newline();
+    }
+}
+
+/// Returns the column widths of the given `ac:layout-section` type. Equal columns have no width.
+fn section_widths(section_type: &str) -> &'static [&'static str] {
+    match section_type {
+        "two_left_sidebar" => &["30%", "70%"],
+        "two_right_sidebar" => &["70%", "30%"],
+        "three_with_sidebars" => &["25%", "50%", "25%"],
+        _ => &[],
+    }
+}
+
+fn layout_cells(section: &Handle) -> Vec<Handle> {
+    child_elements(section, |child| {
+        get_tag_name(child).is_some_and(|name| name == "ac:layout-cell")
+    })
+}
+
+/// Handler for `ac:layout`, `ac:layout-section` and `ac:layout-cell` elements. Sections with a
+/// single cell are rendered as if there was no layout.
+pub struct LayoutHandler {
+    renderer: LayoutRenderer,
+    metadata: SharedPageMetadata,
+    opened: Option<String>,
+}
+
+impl LayoutHandler {
+    fn new(renderer: LayoutRenderer, metadata: SharedPageMetadata) -> Self {
+        Self {
+            renderer,
+            metadata,
+            opened: None,
+        }
+    }
+}
+
+impl TagHandler for LayoutHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        match get_tag_name(tag).as_deref() {
+            Some("ac:layout-section") if layout_cells(tag).len() > 1 => {
+                self.renderer.open_section(printer, &self.metadata);
+                self.opened = get_tag_name(tag);
+            }
+            Some("ac:layout-cell") => {
+                let Some(section) = get_parent(tag) else {
+                    return;
+                };
+                let cells = layout_cells(&section);
+                if cells.len() < 2 {
+                    return;
+                }
+                let index = cells
+                    .iter()
+                    .position(|cell| Rc::ptr_eq(cell, tag))
+                    .unwrap_or_default();
+                let width = get_tag_attr(&section, "ac:type")
+                    .and_then(|section_type| section_widths(&section_type).get(index).copied());
+                self.renderer
+                    .open_cell(printer, &self.metadata, index, width);
+                self.opened = get_tag_name(tag);
+            }
+            _ => {
+                printer.insert_newline();
+                printer.insert_newline();
+            }
+        }
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        match self.opened.as_deref() {
+            Some("ac:layout-section") => self.renderer.close_section(printer),
+            Some("ac:layout-cell") => self.renderer.close_cell(printer),
+            _ => {
+                printer.insert_newline();
+                printer.insert_newline();
+            }
+        }
+    }
+}
+
+pub struct LayoutHandlerFactory {
+    document: DocumentContext,
+    renderer: LayoutRenderer,
+}
+
+impl LayoutHandlerFactory {
+    pub(crate) fn new(document: DocumentContext, renderer: LayoutRenderer) -> Self {
+        Self { document, renderer }
+    }
+}
+
+impl TagHandlerFactory for LayoutHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(LayoutHandler::new(
+            self.renderer,
+            self.document.get().metadata,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        LayoutStyle, MarkdownFlavor, ParseOptions, parse_confluence, parse_confluence_page,
+    };
+
+    const LAYOUT: &str = r#"
+<ac:layout>
+  <ac:layout-section ac:type="single">
+    <ac:layout-cell><h1>Overview</h1></ac:layout-cell>
+  </ac:layout-section>
+  <ac:layout-section ac:type="two_left_sidebar">
+    <ac:layout-cell><p>Sidebar</p></ac:layout-cell>
+    <ac:layout-cell><p>Main content</p></ac:layout-cell>
+  </ac:layout-section>
+</ac:layout>"#;
+
+    #[test]
+    fn test_layout_linear() {
+        assert_eq!(
+            parse_confluence(LAYOUT, &ParseOptions::default()),
+            "Overview\n==========\n\nSidebar\n\n---\n\nMain content"
+        );
+        let page = parse_confluence_page(LAYOUT, &ParseOptions::default());
+        assert_eq!(
+            page.metadata().column_widths(),
+            [vec![Some("30%".to_string()), Some("70%".to_string())]]
+        );
+    }
+
+    #[test]
+    fn test_layout_grid() {
+        let options = ParseOptions::default().with_layout_style(LayoutStyle::Grid);
+        assert_eq!(
+            parse_confluence(LAYOUT, &options),
+            r#"Overview
+==========
+
+<div style="display: flex; gap: 1em">
+
+<div style="flex: 0 0 30%">
+
+Sidebar
+
+</div>
+
+<div style="flex: 0 0 70%">
+
+Main content
+
+</div>
+
+</div>"#
+        );
+    }
+
+    #[test]
+    fn test_layout_pandoc() {
+        let options = ParseOptions::default()
+            .with_layout_style(LayoutStyle::Grid)
+            .with_flavor(MarkdownFlavor::Pandoc);
+        assert_eq!(
+            parse_confluence(LAYOUT, &options),
+            "Overview\n==========\n\n:::: columns\n\n::: {.column width=\"30%\"}\n\nSidebar\n\n:::\n\n::: {.column width=\"70%\"}\n\nMain content\n\n:::\n\n::::"
+        );
+    }
+}
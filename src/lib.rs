@@ -13,43 +13,81 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+mod adf;
+mod attachment;
+mod batch;
+mod card;
+mod comment;
+mod container;
+mod converter;
+mod cql;
+mod date;
+mod diagnostic;
+mod document;
 mod dummy;
 mod emoticon;
+mod flavor;
 mod image;
+mod index;
+mod jira;
+mod layout;
 mod link;
 mod macros;
+mod manifest;
+mod marker;
+mod metadata;
+mod storage;
+mod task;
 mod util;
 
-use html2md::{TagHandlerFactory, parse_html_custom};
-use quick_xml::{errors::Result, events::Event, reader::Reader, writer::Writer};
+pub use attachment::{Attachment, AttachmentProvider, InMemoryAttachmentProvider};
+pub use batch::{BatchConverter, BatchJob, BatchProgress, BatchReport, DocumentReport};
+pub use card::{InMemoryLinkTitleProvider, LinkTitleProvider};
+pub use comment::{
+    Comment, CommentProvider, FooterCommentPlacement, InMemoryCommentProvider, RenderedComment,
+};
+pub use converter::Converter;
+pub use date::DateFormat;
+pub use diagnostic::{Diagnostic, ParseError, ParseMode, Position};
+pub use document::{AdmonitionKind, Block, Inline};
+pub use emoticon::EmoticonStyle;
+pub use flavor::{MarkdownFlavor, RawHtmlPolicy};
+pub use index::{IndexedPage, PageIndex};
+pub use jira::{JiraIssue, JiraIssueProvider, JsonJiraIssueProvider};
+pub use layout::LayoutStyle;
+pub use macros::status::StatusStyle;
+pub use manifest::{MANIFEST_FILE_NAME, Manifest, ManifestChanges, ManifestEntry};
+pub use metadata::{ConfluencePage, PageMetadata, PageProperties};
+use regex::Regex;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::io::{BufRead, Write};
+use std::sync::Arc;
 use util::JiraServerMap;
 pub use util::{ConfluencePageId, ConfluenceServer, JiraServer};
 
-fn remove_cdata<R: BufRead, W: Write>(
-    reader: &mut Reader<R>,
-    writer: &mut Writer<W>,
-) -> Result<()> {
-    let mut buf = Vec::with_capacity(2048);
-
-    loop {
-        let event = match reader.read_event_into(&mut buf)? {
-            Event::CData(text) => Event::Text(text.escape()?),
-            Event::Eof => break Ok(()),
-            other_event => other_event,
-        };
-
-        writer.write_event(event.borrow())?;
-    }
-}
-
 #[derive(Debug, Default, Clone)]
 pub struct ParseOptions {
     jira_server_map: JiraServerMap,
+    jira_issue_provider: Option<Arc<dyn JiraIssueProvider>>,
+    jira_key_pattern: Option<Regex>,
     confluence_server: Option<ConfluenceServer>,
+    attachment_provider: Option<Arc<dyn AttachmentProvider>>,
     default_space_key: Option<String>,
     default_page_id: Option<ConfluencePageId>,
+    page_index: Option<Arc<PageIndex>>,
+    link_title_provider: Option<Arc<dyn LinkTitleProvider>>,
+    flavor: MarkdownFlavor,
+    status_style: Option<StatusStyle>,
+    emoticon_style: Option<EmoticonStyle>,
+    layout_style: LayoutStyle,
+    date_format: DateFormat,
+    raw_html_policy: RawHtmlPolicy,
+    placeholders_as_comments: bool,
+    inline_comment_references: bool,
+    comment_provider: Option<Arc<dyn CommentProvider>>,
+    footer_comment_placement: FooterCommentPlacement,
+    emoticon_mappings: HashMap<String, String>,
+    parse_mode: ParseMode,
 }
 
 impl ParseOptions {
@@ -58,11 +96,61 @@ impl ParseOptions {
         self
     }
 
+    /// Register a Jira server together with its display name, which is used to resolve `jira`
+    /// macros whose `serverId` is unknown (e.g. after the application link was re-created).
+    pub fn with_named_jira_server(
+        mut self,
+        server_id: String,
+        server_name: String,
+        jira_server: JiraServer,
+    ) -> ParseOptions {
+        self.jira_server_map
+            .insert_name(server_name, server_id.clone());
+        self.jira_server_map.insert(server_id, jira_server);
+        self
+    }
+
+    /// Use this Jira server if a `jira` macro's server cannot be resolved by id or name, and for
+    /// auto-linking bare issue keys.
+    pub fn with_default_jira_server(mut self, jira_server: JiraServer) -> ParseOptions {
+        self.jira_server_map.set_default(jira_server);
+        self
+    }
+
+    /// Link bare Jira issue keys (e.g. `CONF-1234`) in ordinary text to the default Jira server.
+    ///
+    /// The pattern matches the project key part, e.g. `[A-Z][A-Z0-9]+` or `CONF|JRA`.
+    pub fn with_jira_key_autolink(mut self, project_key_pattern: &Regex) -> ParseOptions {
+        let pattern = format!(r"\b(?:{})-[0-9]+\b", project_key_pattern.as_str());
+        self.jira_key_pattern = Some(Regex::new(&pattern).expect("invalid project key pattern"));
+        self
+    }
+
+    /// Use cached issue metadata to render summaries, statuses and JQL result tables in `jira`
+    /// macros.
+    pub fn with_jira_issue_provider(
+        mut self,
+        jira_issue_provider: Arc<dyn JiraIssueProvider>,
+    ) -> ParseOptions {
+        self.jira_issue_provider = Some(jira_issue_provider);
+        self
+    }
+
     pub fn with_confluence_server(mut self, confluence_server: ConfluenceServer) -> ParseOptions {
         self.confluence_server = Some(confluence_server);
         self
     }
 
+    /// Use the given attachment metadata, e.g. from a space export, to render attachment-based
+    /// macros and to link exported attachment files.
+    pub fn with_attachment_provider(
+        mut self,
+        attachment_provider: Arc<dyn AttachmentProvider>,
+    ) -> ParseOptions {
+        self.attachment_provider = Some(attachment_provider);
+        self
+    }
+
     pub fn with_default_page_id(mut self, default_page_id: ConfluencePageId) -> ParseOptions {
         self.default_page_id = Some(default_page_id);
         self
@@ -72,50 +160,228 @@ impl ParseOptions {
         self.default_space_key = Some(default_space_key);
         self
     }
+
+    /// Set the Markdown dialect of the output, which determines the default rendering of
+    /// constructs without a Markdown equivalent.
+    pub fn with_flavor(mut self, flavor: MarkdownFlavor) -> ParseOptions {
+        self.flavor = flavor;
+        self
+    }
+
+    /// Override how status lozenges are rendered (the default depends on the flavor).
+    pub fn with_status_style(mut self, status_style: StatusStyle) -> ParseOptions {
+        self.status_style = Some(status_style);
+        self
+    }
+
+    /// Override how emoticons are rendered (the default depends on the flavor).
+    pub fn with_emoticon_style(mut self, emoticon_style: EmoticonStyle) -> ParseOptions {
+        self.emoticon_style = Some(emoticon_style);
+        self
+    }
+
+    /// Render the emoticon with the given name, emoji shortname or emoji id as the replacement
+    /// text, e.g. for site-specific custom emoji.
+    pub fn with_emoticon_mapping<S: Into<String>, T: Into<String>>(
+        mut self,
+        emoticon: S,
+        replacement: T,
+    ) -> ParseOptions {
+        self.emoticon_mappings
+            .insert(emoticon.into(), replacement.into());
+        self
+    }
+
+    /// Override how multi-column page layouts are rendered (linear by default).
+    pub fn with_layout_style(mut self, layout_style: LayoutStyle) -> ParseOptions {
+        self.layout_style = layout_style;
+        self
+    }
+
+    /// Override how dates (e.g. inline dates and page property values) are rendered (ISO 8601 by
+    /// default).
+    pub fn with_date_format(mut self, date_format: DateFormat) -> ParseOptions {
+        self.date_format = date_format;
+        self
+    }
+
+    /// Override how the content of `html` macros is handled (sanitized by default). Flavors
+    /// without raw HTML always use [`RawHtmlPolicy::Strip`].
+    pub fn with_raw_html_policy(mut self, raw_html_policy: RawHtmlPolicy) -> ParseOptions {
+        self.raw_html_policy = raw_html_policy;
+        self
+    }
+
+    /// Keep the instructional text of template placeholders as HTML comments instead of dropping
+    /// it.
+    pub fn with_placeholders_as_comments(mut self, placeholders_as_comments: bool) -> ParseOptions {
+        self.placeholders_as_comments = placeholders_as_comments;
+        self
+    }
+
+    /// Add footnote references like `[^comment-1]` after text with inline comments.
+    pub fn with_inline_comment_references(
+        mut self,
+        inline_comment_references: bool,
+    ) -> ParseOptions {
+        self.inline_comment_references = inline_comment_references;
+        self
+    }
+
+    /// Use the given provider to render the inline comments of the page as footnotes and its
+    /// footer comments as "Comments" section (or sidecar, see
+    /// [`ParseOptions::with_footer_comment_placement`]).
+    pub fn with_comment_provider(
+        mut self,
+        comment_provider: Arc<dyn CommentProvider>,
+    ) -> ParseOptions {
+        self.comment_provider = Some(comment_provider);
+        self
+    }
+
+    /// Override where footer comments are rendered (a section at the end of the page by default).
+    pub fn with_footer_comment_placement(
+        mut self,
+        footer_comment_placement: FooterCommentPlacement,
+    ) -> ParseOptions {
+        self.footer_comment_placement = footer_comment_placement;
+        self
+    }
+
+    /// Use the given index of already converted pages to render macros like `detailssummary` and
+    /// `contentbylabel`.
+    pub fn with_page_index(mut self, page_index: PageIndex) -> ParseOptions {
+        self.page_index = Some(Arc::new(page_index));
+        self
+    }
+
+    /// Use the given titles for smart links, whose text is often just the URL. Without a title,
+    /// the URL is shortened (e.g. to the issue key of Jira URLs).
+    pub fn with_link_title_provider(
+        mut self,
+        link_title_provider: Arc<dyn LinkTitleProvider>,
+    ) -> ParseOptions {
+        self.link_title_provider = Some(link_title_provider);
+        self
+    }
+
+    /// Override how malformed storage format is handled (rejected by default).
+    pub fn with_parse_mode(mut self, parse_mode: ParseMode) -> ParseOptions {
+        self.parse_mode = parse_mode;
+        self
+    }
+}
+
+/// Returns true if both are unset or point to the same provider.
+fn same_provider<T: ?Sized>(a: &Option<Arc<T>>, b: &Option<Arc<T>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+impl ParseOptions {
+    /// Returns true if converters built from both options produce the same output. Providers are
+    /// compared by identity.
+    fn is_equivalent(&self, other: &ParseOptions) -> bool {
+        // Destructured, so that new options cannot be forgotten.
+        let ParseOptions {
+            jira_server_map,
+            jira_issue_provider,
+            jira_key_pattern,
+            confluence_server,
+            attachment_provider,
+            default_space_key,
+            default_page_id,
+            page_index,
+            link_title_provider,
+            flavor,
+            status_style,
+            emoticon_style,
+            layout_style,
+            date_format,
+            raw_html_policy,
+            placeholders_as_comments,
+            inline_comment_references,
+            comment_provider,
+            footer_comment_placement,
+            emoticon_mappings,
+            parse_mode,
+        } = self;
+        *jira_server_map == other.jira_server_map
+            && same_provider(jira_issue_provider, &other.jira_issue_provider)
+            && jira_key_pattern.as_ref().map(Regex::as_str)
+                == other.jira_key_pattern.as_ref().map(Regex::as_str)
+            && *confluence_server == other.confluence_server
+            && same_provider(attachment_provider, &other.attachment_provider)
+            && *default_space_key == other.default_space_key
+            && *default_page_id == other.default_page_id
+            && same_provider(page_index, &other.page_index)
+            && same_provider(link_title_provider, &other.link_title_provider)
+            && *flavor == other.flavor
+            && *status_style == other.status_style
+            && *emoticon_style == other.emoticon_style
+            && *layout_style == other.layout_style
+            && *date_format == other.date_format
+            && *raw_html_policy == other.raw_html_policy
+            && *placeholders_as_comments == other.placeholders_as_comments
+            && *inline_comment_references == other.inline_comment_references
+            && same_provider(comment_provider, &other.comment_provider)
+            && *footer_comment_placement == other.footer_comment_placement
+            && *emoticon_mappings == other.emoticon_mappings
+            && *parse_mode == other.parse_mode
+    }
+}
+
+thread_local! {
+    /// The converter of the last call to [`parse_confluence_page`] on this thread, which is
+    /// reused (including its tag handlers) as long as the options are equivalent.
+    static LAST_CONVERTER: RefCell<Option<Converter>> = const { RefCell::new(None) };
 }
 
 pub fn parse_confluence<S: AsRef<str>>(source: S, options: &ParseOptions) -> String {
-    let mut handlers: HashMap<_, Box<(dyn TagHandlerFactory + 'static)>> = HashMap::new();
-    handlers.insert(
-        String::from("ac:structured-macro"),
-        Box::new(macros::StructuredMacroHandlerFactory::with_jira_server_map(
-            options.jira_server_map.clone(),
-        )),
-    );
-    handlers.insert(
-        String::from("ac:parameter"),
-        Box::new(dummy::RecursiveDummyHandlerFactory {}),
-    );
-    handlers.insert(
-        String::from("ac:emoticon"),
-        Box::new(emoticon::EmoticonHandlerFactory {}),
-    );
-    handlers.insert(
-        String::from("ac:image"),
-        Box::new(image::ImageHandlerFactory::with_confluence_page(
-            options
-                .confluence_server
-                .clone()
-                .zip(options.default_page_id.clone()),
-        )),
-    );
-    handlers.insert(
-        String::from("ac:link"),
-        Box::new(link::LinkHandlerFactory::with_url_builder(
-            link::LinkHandlerUrlBuilder::new(
-                options.confluence_server.clone(),
-                options.default_space_key.clone(),
-                options.default_page_id.clone(),
-            ),
-        )),
-    );
-
-    let mut reader = Reader::from_str(source.as_ref());
-    let mut buffer = Vec::new();
-    let mut writer = Writer::new(&mut buffer);
-    remove_cdata(&mut reader, &mut writer).unwrap();
-
-    let text = String::from_utf8_lossy(&buffer);
-
-    parse_html_custom(&text, &handlers)
+    parse_confluence_page(source, options).into()
+}
+
+/// Convert the source to Markdown and collect the page metadata (e.g. Page Properties).
+///
+/// To convert many documents with the same options, use a [`Converter`] instead.
+pub fn parse_confluence_page<S: AsRef<str>>(source: S, options: &ParseOptions) -> ConfluencePage {
+    let converter = LAST_CONVERTER.with_borrow_mut(|last| match last {
+        Some(converter) if converter.options().is_equivalent(options) => converter.clone(),
+        _ => last.insert(Converter::new(options.clone())).clone(),
+    });
+    converter.convert_page(source)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_equivalent_options() {
+        let options = ParseOptions::default()
+            .with_page_index(PageIndex::default())
+            .with_jira_key_autolink(&Regex::new("CONF").unwrap());
+        assert!(options.is_equivalent(&options.clone()));
+        assert!(!options.is_equivalent(&options.clone().with_flavor(MarkdownFlavor::Plain)));
+        assert!(!options.is_equivalent(&options.clone().with_page_index(PageIndex::default())));
+    }
+
+    #[test]
+    fn test_jira_key_autolink() {
+        let options = ParseOptions::default()
+            .with_default_jira_server(JiraServer::from_str("http://jira.example.com").unwrap())
+            .with_jira_key_autolink(&Regex::new("CONF|JRA").unwrap());
+        let md = parse_confluence(
+            r#"<p>See CONF-12 and JRA-3, not ABC-1 or <code>CONF-13</code>.</p>"#,
+            &options,
+        );
+        assert_eq!(
+            md,
+            "See [CONF-12](http://jira.example.com/browse/CONF-12) and \
+             [JRA-3](http://jira.example.com/browse/JRA-3), not ABC-1 or `CONF-13`."
+        );
+    }
 }
@@ -13,9 +13,17 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::util::{ConfluencePageId, ConfluenceServer, get_tag_name};
+use crate::attachment::AttachmentResolver;
+use crate::card::{CardAppearance, CardRenderer};
+use crate::document::{ContentRenderer, Inline};
+use crate::macros::DocumentContext;
+use crate::util::{ConfluencePageId, ConfluenceServer, get_tag_name, get_text_content};
 use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory, common::get_tag_attr};
+use std::rc::Rc;
+use std::str::FromStr;
 
+/// Builds the URLs of link targets, which are `None` if the server (or the default space or page
+/// id) is unknown.
 #[derive(Debug, Clone)]
 pub struct LinkHandlerUrlBuilder {
     server: Option<ConfluenceServer>,
@@ -36,65 +44,90 @@ impl LinkHandlerUrlBuilder {
         }
     }
 
+    /// Returns a copy of the builder for the page with the given id.
+    fn for_page(&self, page_id: Option<ConfluencePageId>) -> Self {
+        Self {
+            page_id,
+            ..self.clone()
+        }
+    }
+
     fn url_from_page_space_and_title<S: AsRef<str>, T: AsRef<str>>(
         &self,
         space_key: S,
         page_title: T,
-    ) -> String {
-        let server = self.server.as_ref().expect("missing server");
-        server.page_url_with_space_and_title(space_key.as_ref(), page_title.as_ref())
+    ) -> Option<String> {
+        let server = self.server.as_ref()?;
+        Some(server.page_url_with_space_and_title(space_key.as_ref(), page_title.as_ref()))
     }
 
-    fn url_from_page_title<S: AsRef<str>>(&self, page_title: S) -> String {
-        let default_space = self
-            .default_space
-            .as_deref()
-            .expect("missing default space");
+    fn url_from_page_title<S: AsRef<str>>(&self, page_title: S) -> Option<String> {
+        let default_space = self.default_space.as_deref()?;
         self.url_from_page_space_and_title(default_space, page_title.as_ref())
     }
 
-    fn url_from_attachment_filename<S: AsRef<str>>(&self, filename: S) -> String {
-        let server = self.server.as_ref().expect("missing server");
-        let page_id = self.page_id.as_ref().expect("missing page id");
-        server.attachment_url(page_id, filename.as_ref())
+    fn url_from_attachment_filename<S: AsRef<str>>(&self, filename: S) -> Option<String> {
+        let server = self.server.as_ref()?;
+        let page_id = self.page_id.as_ref()?;
+        Some(server.attachment_url(page_id, filename.as_ref()))
     }
 
-    fn url_from_user_name<S: AsRef<str>>(&self, username: S) -> String {
-        let server = self.server.as_ref().expect("missing server");
-        server.user_url_with_name(username.as_ref())
+    fn url_from_user_name<S: AsRef<str>>(&self, username: S) -> Option<String> {
+        let server = self.server.as_ref()?;
+        Some(server.user_url_with_name(username.as_ref()))
     }
 
-    fn url_from_user_key<S: AsRef<str>>(&self, userkey: S) -> String {
-        let server = self.server.as_ref().expect("missing server");
-        server.user_url_with_key(userkey.as_ref())
+    fn url_from_user_key<S: AsRef<str>>(&self, userkey: S) -> Option<String> {
+        let server = self.server.as_ref()?;
+        Some(server.user_url_with_key(userkey.as_ref()))
     }
 }
 
 pub struct LinkHandler {
-    start_pos: usize,
-    url: Option<String>,
-    url_builder: LinkHandlerUrlBuilder,
+    url_builder: Rc<LinkHandlerUrlBuilder>,
+    renderer: ContentRenderer,
+    card_renderer: Option<CardRenderer>,
+    attachments: Option<Rc<AttachmentResolver>>,
 }
 
 impl LinkHandler {
-    pub fn with_url_builder(url_builder: LinkHandlerUrlBuilder) -> Self {
+    pub fn with_url_builder(url_builder: Rc<LinkHandlerUrlBuilder>) -> Self {
         Self {
-            start_pos: 0,
-            url: None,
             url_builder,
+            renderer: ContentRenderer::default(),
+            card_renderer: None,
+            attachments: None,
         }
     }
+
+    /// Use the given renderer for the link body, so that it is rendered with the handlers of
+    /// the conversion.
+    pub fn with_renderer(mut self, renderer: ContentRenderer) -> Self {
+        self.renderer = renderer;
+        self
+    }
+
+    /// Render links with an `ac:card-appearance` as smart link cards.
+    pub fn with_card_renderer(mut self, card_renderer: CardRenderer) -> Self {
+        self.card_renderer = Some(card_renderer);
+        self
+    }
+
+    /// Resolve attachments with the given resolver, which prefers local files and supports
+    /// attachments of other pages.
+    pub(crate) fn with_attachments(mut self, attachments: Rc<AttachmentResolver>) -> Self {
+        self.attachments = Some(attachments);
+        self
+    }
 }
 
 impl TagHandler for LinkHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
-        self.start_pos = printer.data.len();
-
         let anchor = get_tag_attr(tag, "ac:anchor");
 
         let mut page_title = None;
         let mut space_key = None;
-        let mut attachment_filename = None;
+        let mut attachment = None;
         let mut user_name = None;
         let mut user_key = None;
 
@@ -108,7 +141,7 @@ impl TagHandler for LinkHandler {
                     space_key = get_tag_attr(child, "ri:space-key");
                 }
                 Some("ri:attachment") => {
-                    attachment_filename = get_tag_attr(child, "ri:filename");
+                    attachment = Some(Rc::clone(child));
                 }
                 Some("ri:user") => {
                     user_name = get_tag_attr(child, "ri:username");
@@ -118,69 +151,145 @@ impl TagHandler for LinkHandler {
             }
         }
 
+        let filename = attachment
+            .as_ref()
+            .and_then(|attachment| get_tag_attr(attachment, "ri:filename"));
+        let target_name = page_title
+            .clone()
+            .or_else(|| filename.clone())
+            .or_else(|| user_name.clone());
         let url = if let Some((space, title)) = space_key.as_deref().zip(page_title.as_deref()) {
             self.url_builder.url_from_page_space_and_title(space, title)
         } else if let Some(title) = page_title {
             self.url_builder.url_from_page_title(title)
-        } else if let Some(filename) = attachment_filename {
-            self.url_builder.url_from_attachment_filename(filename)
+        } else if let Some(attachment) = attachment {
+            match &self.attachments {
+                Some(attachments) => attachments.reference_url(&attachment),
+                None => filename
+                    .and_then(|filename| self.url_builder.url_from_attachment_filename(filename)),
+            }
         } else if let Some(name) = user_name {
             self.url_builder.url_from_user_name(name)
         } else if let Some(key) = user_key {
             self.url_builder.url_from_user_key(key)
         } else {
-            String::new()
+            Some(String::new())
+        };
+
+        let url = match (url, anchor) {
+            (Some(url), Some(anchor_name)) => Some(format!("{url}#{anchor_name}")),
+            (url, _) => url,
         };
 
-        self.url = if let Some(anchor_name) = anchor {
-            format!("{url}#{anchor_name}").into()
+        if let Some(card_renderer) = &self.card_renderer
+            && let Some(url) = url.as_deref().filter(|url| !url.is_empty())
+            && let Some(appearance) = get_tag_attr(tag, "ac:card-appearance")
+                .and_then(|appearance| CardAppearance::from_str(&appearance).ok())
+        {
+            card_renderer.render(url, &get_text_content(tag), appearance, printer);
+            return;
+        }
+
+        // The resource identifiers (`ri:page` etc.) have no text content, so only the link body
+        // is rendered. Links without body (e.g. smart links) show the name of the target.
+        let content = self.renderer.render_children(tag, printer);
+        let content = if content.trim().is_empty() {
+            Inline::Text(target_name.unwrap_or_default())
         } else {
-            url.into()
+            Inline::Markdown(content)
+        };
+        // Links to targets without URL (e.g. without Confluence server) are kept as text.
+        let markdown = match url {
+            Some(url) => Inline::Link {
+                content: vec![content],
+                url,
+            }
+            .to_markdown(),
+            None => content.to_markdown(),
         };
+        printer.append_str(&markdown);
     }
 
-    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
-        let url = self.url.as_deref().unwrap();
-        let index = printer.data.len();
-        printer.insert_str(index, &format!("]({url})"));
-        printer.insert_str(self.start_pos, "[");
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
     }
 }
 
 pub struct LinkHandlerFactory {
-    url_builder: LinkHandlerUrlBuilder,
+    url_builder: Rc<LinkHandlerUrlBuilder>,
+    renderer: ContentRenderer,
+    card_renderer: Option<CardRenderer>,
+    document: Option<DocumentContext>,
 }
 
 impl LinkHandlerFactory {
     pub fn with_url_builder(url_builder: LinkHandlerUrlBuilder) -> Self {
-        Self { url_builder }
+        Self {
+            url_builder: Rc::new(url_builder),
+            renderer: ContentRenderer::default(),
+            card_renderer: None,
+            document: None,
+        }
+    }
+
+    pub fn with_renderer(mut self, renderer: ContentRenderer) -> Self {
+        self.renderer = renderer;
+        self
+    }
+
+    pub fn with_card_renderer(mut self, card_renderer: CardRenderer) -> Self {
+        self.card_renderer = Some(card_renderer);
+        self
+    }
+
+    /// Link attachments of the page that is currently converted instead of the page of the URL
+    /// builder.
+    pub(crate) fn with_document(mut self, document: DocumentContext) -> Self {
+        self.document = Some(document);
+        self
     }
 }
 
 impl TagHandlerFactory for LinkHandlerFactory {
     fn instantiate(&self) -> Box<dyn TagHandler> {
-        Box::new(LinkHandler::with_url_builder(self.url_builder.clone()))
+        let handler = match &self.document {
+            Some(document) => {
+                let context = document.get();
+                LinkHandler::with_url_builder(Rc::new(self.url_builder.for_page(context.page_id)))
+                    .with_attachments(context.attachments)
+            }
+            None => LinkHandler::with_url_builder(Rc::clone(&self.url_builder)),
+        };
+        let handler = handler.with_renderer(self.renderer.clone());
+        match &self.card_renderer {
+            Some(card_renderer) => Box::new(handler.with_card_renderer(card_renderer.clone())),
+            None => Box::new(handler),
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::flavor::MarkdownFlavor;
     use html2md::parse_html_custom;
     use std::collections::HashMap;
     use std::str::FromStr;
 
-    fn get_handlers() -> HashMap<String, Box<(dyn TagHandlerFactory + 'static)>> {
-        let mut handlers: HashMap<_, Box<(dyn TagHandlerFactory + 'static)>> = HashMap::new();
+    fn get_handlers() -> HashMap<String, Box<dyn TagHandlerFactory + 'static>> {
+        let mut handlers: HashMap<_, Box<dyn TagHandlerFactory + 'static>> = HashMap::new();
         handlers.insert(
             String::from("ac:link"),
-            Box::new(LinkHandlerFactory::with_url_builder(
-                LinkHandlerUrlBuilder::new(
+            Box::new(
+                LinkHandlerFactory::with_url_builder(LinkHandlerUrlBuilder::new(
                     ConfluenceServer::from_str("https://example.com/confluence").ok(),
                     Some("CONFL".to_string()),
                     Some(ConfluencePageId::from(1337)),
-                ),
-            )),
+                ))
+                .with_card_renderer(CardRenderer::new(MarkdownFlavor::default(), None)),
+            ),
         );
         handlers
     }
@@ -269,4 +378,43 @@ mod test {
             "[**Link to another Confluence Page**](https://example.com/confluence/display/CONFL/Page%20Title)"
         );
     }
+
+    #[test]
+    fn test_link_card_appearance() {
+        markdown_assert_eq!(
+            r#"
+<ac:link ac:card-appearance="inline">
+<ri:page ri:space-key="DOC" ri:content-title="Release Notes" />
+</ac:link>
+"#,
+            "[Release Notes](https://example.com/confluence/display/DOC/Release%20Notes)"
+        );
+    }
+
+    #[test]
+    fn test_link_without_server() {
+        let options = crate::ParseOptions::default();
+        assert_eq!(
+            crate::parse_confluence(
+                r#"<p><ac:link ac:card-appearance="inline"><ri:page ri:space-key="DOC" ri:content-title="Release *Notes*" /></ac:link></p>"#,
+                &options
+            ),
+            r"Release \*Notes\*"
+        );
+        assert_eq!(
+            crate::parse_confluence(
+                r#"<p><ac:link><ri:user ri:username="someuser"/><ac:plain-text-link-body>User Link</ac:plain-text-link-body></ac:link></p>"#,
+                &options
+            ),
+            "User Link"
+        );
+    }
+
+    #[test]
+    fn test_link_without_body() {
+        markdown_assert_eq!(
+            r#"<ac:link><ri:page ri:content-title="Page Title" /></ac:link>"#,
+            "[Page Title](https://example.com/confluence/display/CONFL/Page%20Title)"
+        );
+    }
 }
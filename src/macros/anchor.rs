@@ -0,0 +1,77 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::flavor::MarkdownFlavor;
+use crate::util::{get_macro_parameter, html_escape};
+use html2md::{Handle, StructuredPrinter, TagHandler};
+
+/// Handler for the `anchor` macro, which is rendered as link target for `ac:link ac:anchor`
+/// links, i.e. with the anchor name as id.
+pub struct AnchorMacroHandler {
+    flavor: MarkdownFlavor,
+}
+
+impl AnchorMacroHandler {
+    pub fn new(flavor: MarkdownFlavor) -> Self {
+        Self { flavor }
+    }
+}
+
+impl TagHandler for AnchorMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        // The anchor name is stored in the unnamed default parameter.
+        let Some(name) = get_macro_parameter(tag, "") else {
+            return;
+        };
+        let name = name.trim();
+
+        if self.flavor.supports_attributes() {
+            printer.append_str(&format!("[]{{#{name}}}"));
+        } else if self.flavor.allows_raw_html() {
+            printer.append_str(&format!(r#"<a id="{}"></a>"#, html_escape(name)));
+        }
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{MarkdownFlavor, ParseOptions, parse_confluence};
+
+    const ANCHOR: &str = r#"<h2><ac:structured-macro ac:name="anchor"><ac:parameter ac:name="">setup</ac:parameter></ac:structured-macro>Setup</h2>"#;
+
+    #[test]
+    fn test_anchor() {
+        crate::markdown_assert_eq!(
+            ANCHOR,
+            r#"<a id="setup"></a>Setup
+----------"#
+        );
+    }
+
+    #[test]
+    fn test_anchor_pandoc() {
+        let options = ParseOptions::default().with_flavor(MarkdownFlavor::Pandoc);
+        assert_eq!(
+            parse_confluence(ANCHOR, &options),
+            "[]{#setup}Setup\n----------"
+        );
+    }
+}
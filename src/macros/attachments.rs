@@ -0,0 +1,203 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::MacroContext;
+use crate::attachment::Attachment;
+use crate::document::Inline;
+use crate::util::{get_macro_parameter, markdown_table, split_list_parameter};
+use html2md::{Handle, StructuredPrinter, TagHandler};
+use regex::Regex;
+use std::cmp::Ordering;
+
+/// Handler for the `attachments` macro, which is rendered as a table of the page's attachments.
+pub struct AttachmentsMacroHandler {
+    context: MacroContext,
+}
+
+impl AttachmentsMacroHandler {
+    const HEADER: [&str; 5] = ["File", "Size", "Creator", "Created", "Comment"];
+
+    pub fn with_context(context: MacroContext) -> Self {
+        Self { context }
+    }
+}
+
+/// Compiles the comma-separated `patterns` parameter. Like in Confluence, each pattern is a
+/// case-insensitive regular expression that has to match the whole filename.
+fn filename_patterns(tag: &Handle) -> Vec<Regex> {
+    get_macro_parameter(tag, "patterns")
+        .map(|value| split_list_parameter(&value))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|pattern| Regex::new(&format!("(?i)^(?:{pattern})$")).ok())
+        .collect()
+}
+
+/// Compares two attachments by the `sortBy` parameter value.
+fn compare_attachments(sort_by: &str, a: &Attachment, b: &Attachment) -> Ordering {
+    let by_name = || {
+        a.filename()
+            .to_lowercase()
+            .cmp(&b.filename().to_lowercase())
+    };
+    match sort_by {
+        "size" => a.size().cmp(&b.size()).then_with(by_name),
+        "date" | "createddate" => a.created().cmp(&b.created()).then_with(by_name),
+        _ => by_name(),
+    }
+}
+
+impl TagHandler for AttachmentsMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let patterns = filename_patterns(tag);
+        let mut attachments: Vec<_> = self
+            .context
+            .attachments
+            .attachments()
+            .iter()
+            .filter(|attachment| {
+                patterns.is_empty()
+                    || patterns
+                        .iter()
+                        .any(|pattern| pattern.is_match(attachment.filename()))
+            })
+            .collect();
+        if attachments.is_empty() {
+            return;
+        }
+
+        // Confluence sorts by date by default, showing the most recent attachments first.
+        let sort_by = get_macro_parameter(tag, "sortBy").unwrap_or_else(|| "date".to_string());
+        let descending = match get_macro_parameter(tag, "sortOrder").as_deref() {
+            Some("descending") => true,
+            Some("ascending") => false,
+            _ => sort_by == "date",
+        };
+        attachments.sort_by(|a, b| compare_attachments(&sort_by, a, b));
+        if descending {
+            attachments.reverse();
+        }
+
+        let rows: Vec<Vec<String>> = attachments
+            .iter()
+            .map(|attachment| {
+                let filename = attachment.filename();
+                let name = Inline::Text(filename.to_string());
+                let link = match self.context.attachments.url(filename) {
+                    Some(url) => Inline::Link {
+                        content: vec![name],
+                        url,
+                    },
+                    None => name,
+                }
+                .to_markdown();
+                vec![
+                    link,
+                    attachment.human_readable_size().unwrap_or_default(),
+                    attachment.author().unwrap_or_default().to_string(),
+                    attachment
+                        .created()
+                        .map(|created| self.context.date_format.format(created))
+                        .unwrap_or_default(),
+                    attachment.comment().unwrap_or_default().to_string(),
+                ]
+            })
+            .collect();
+
+        printer.insert_newline();
+        printer.insert_newline();
+        printer.append_str(&markdown_table(&Self::HEADER, &rows));
+        printer.insert_newline();
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Attachment, ConfluencePageId, InMemoryAttachmentProvider, ParseOptions, parse_confluence,
+    };
+    use std::sync::Arc;
+
+    fn options() -> ParseOptions {
+        let page_id = ConfluencePageId::from(1);
+        let provider = InMemoryAttachmentProvider::default()
+            .with_attachment(
+                page_id.clone(),
+                Attachment::new("Report 2024.pdf")
+                    .with_local_path("assets/Report 2024.pdf")
+                    .with_size(1_572_864)
+                    .with_author("Jane Doe")
+                    .with_created("2024-03-01")
+                    .with_comment("Final version"),
+            )
+            .with_attachment(
+                page_id.clone(),
+                Attachment::new("notes.txt")
+                    .with_size(512)
+                    .with_author("John Doe")
+                    .with_created("2024-05-12"),
+            )
+            .with_attachment(
+                page_id.clone(),
+                Attachment::new("diagram.png")
+                    .with_size(20_480)
+                    .with_created("2023-11-30"),
+            );
+        ParseOptions::default()
+            .with_default_page_id(page_id)
+            .with_attachment_provider(Arc::new(provider))
+    }
+
+    #[test]
+    fn test_attachments_default_sort() {
+        let md = parse_confluence(
+            r#"<ac:structured-macro ac:name="attachments" />"#,
+            &options(),
+        );
+        assert_eq!(
+            md,
+            "|                   File                    | Size  |Creator | Created  |   Comment   |
+|-------------------------------------------|-------|--------|----------|-------------|
+|                 notes.txt                 | 512 B |John Doe|2024-05-12|             |
+|[Report 2024.pdf](assets/Report%202024.pdf)|1.5 MB |Jane Doe|2024-03-01|Final version|
+|                diagram.png                |20.0 kB|        |2023-11-30|             |"
+        );
+    }
+
+    #[test]
+    fn test_attachments_patterns_and_sort() {
+        let md = parse_confluence(
+            r#"
+<ac:structured-macro ac:name="attachments">
+  <ac:parameter ac:name="patterns">.*\.PNG, .*\.txt</ac:parameter>
+  <ac:parameter ac:name="sortBy">size</ac:parameter>
+</ac:structured-macro>"#,
+            &options(),
+        );
+        assert_eq!(
+            md,
+            "|   File    | Size  |Creator | Created  |Comment|
+|-----------|-------|--------|----------|-------|
+| notes.txt | 512 B |John Doe|2024-05-12|       |
+|diagram.png|20.0 kB|        |2023-11-30|       |"
+        );
+    }
+}
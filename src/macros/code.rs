@@ -0,0 +1,88 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::document::Block;
+use crate::flavor::MarkdownFlavor;
+use crate::util::{get_macro_parameter, get_plain_text_body};
+use html2md::{Handle, StructuredPrinter, TagHandler};
+
+/// Handler for the `code` and `noformat` macros, whose plain text body is rendered as fenced
+/// code block.
+pub struct CodeMacroHandler {
+    flavor: MarkdownFlavor,
+    highlighted: bool,
+}
+
+impl CodeMacroHandler {
+    pub fn new(flavor: MarkdownFlavor) -> Self {
+        Self {
+            flavor,
+            highlighted: true,
+        }
+    }
+
+    /// Handler for the `noformat` macro, which has no language.
+    pub fn noformat(flavor: MarkdownFlavor) -> Self {
+        Self {
+            flavor,
+            highlighted: false,
+        }
+    }
+}
+
+impl TagHandler for CodeMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let code = get_plain_text_body(tag).unwrap_or_default();
+        let language = get_macro_parameter(tag, "language")
+            .filter(|_| self.highlighted)
+            .map(|language| language.to_lowercase())
+            .filter(|language| language != "none");
+
+        printer.insert_newline();
+        printer.insert_newline();
+        printer.append_str(&Block::CodeBlock { language, code }.to_markdown(self.flavor));
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_code() {
+        crate::markdown_assert_eq!(
+            r#"<ac:structured-macro ac:name="code"><ac:parameter ac:name="language">Rust</ac:parameter><ac:parameter ac:name="title">Example</ac:parameter><ac:plain-text-body><![CDATA[fn main() {
+    println!("```");
+}]]></ac:plain-text-body></ac:structured-macro><ac:structured-macro ac:name="noformat"><ac:plain-text-body><![CDATA[<b>raw</b>]]></ac:plain-text-body></ac:structured-macro>"#,
+            "````rust\nfn main() {\n    println!(\"```\");\n}\n````\n\n```\n<b>raw</b>\n```"
+        );
+    }
+
+    #[test]
+    fn test_legacy_macro_with_default_parameter() {
+        crate::markdown_assert_eq!(
+            r#"<p>Before</p><ac:macro ac:name="code"><ac:default-parameter>java</ac:default-parameter><ac:plain-text-body><![CDATA[class A {}]]></ac:plain-text-body></ac:macro>
+<ac:structured-macro ac:name="code"><ac:parameter ac:name="">python</ac:parameter><ac:plain-text-body><![CDATA[pass]]></ac:plain-text-body></ac:structured-macro>
+<ac:macro ac:name="code"><ac:default-parameter>none</ac:default-parameter><ac:parameter ac:name="language">sql</ac:parameter><ac:plain-text-body><![CDATA[SELECT 1;]]></ac:plain-text-body></ac:macro><p>After</p>"#,
+            "Before\n\n```java\nclass A {}\n```\n\n```python\npass\n```\n\n```sql\nSELECT 1;\n```\n\nAfter"
+        );
+    }
+}
@@ -0,0 +1,236 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::MacroContext;
+use crate::cql::{CqlContext, CqlError, CqlQuery};
+use crate::document::{Block, Inline, blocks_to_markdown};
+use crate::flavor::MarkdownFlavor;
+use crate::index::IndexedPage;
+use crate::util::{get_macro_parameter, split_list_parameter};
+use html2md::{Handle, StructuredPrinter, TagHandler, common::get_tag_attr};
+use std::str::FromStr;
+
+/// Build the query from either the `cql` parameter or the legacy `labels`, `spaces` and
+/// `operator` parameters.
+pub fn query_from_parameters(tag: &Handle) -> Result<Option<CqlQuery>, CqlError> {
+    if let Some(cql) = get_macro_parameter(tag, "cql") {
+        return CqlQuery::from_str(&cql).map(Some);
+    }
+
+    let labels = get_macro_parameter(tag, "labels")
+        .or_else(|| get_macro_parameter(tag, "label"))
+        .map(|value| split_list_parameter(&value))
+        .unwrap_or_default();
+    let spaces = get_macro_parameter(tag, "spaces")
+        .or_else(|| get_macro_parameter(tag, "space"))
+        .map(|value| split_list_parameter(&value))
+        .unwrap_or_default();
+    if labels.is_empty() && spaces.is_empty() {
+        return Ok(None);
+    }
+
+    let match_all = get_macro_parameter(tag, "operator").is_some_and(|op| op == "AND");
+    Ok(Some(CqlQuery::from_labels_and_spaces(
+        &labels, &spaces, match_all,
+    )))
+}
+
+/// Renders a visible placeholder instead of the macro, because its query cannot be evaluated.
+pub fn render_query_error(
+    tag: &Handle,
+    error: &CqlError,
+    flavor: MarkdownFlavor,
+    printer: &mut StructuredPrinter,
+) {
+    let name = get_tag_attr(tag, "ac:name").unwrap_or_default();
+    let placeholder = Block::Paragraph(vec![Inline::Emphasis(vec![Inline::Text(format!(
+        "Skipped {name} macro ({error})"
+    ))])]);
+    printer.insert_newline();
+    printer.insert_newline();
+    printer.append_str(&placeholder.to_markdown(flavor));
+    printer.insert_newline();
+    printer.insert_newline();
+}
+
+/// Returns all pages from the index that match the query, sorted by title.
+pub fn matching_pages<'a>(context: &'a MacroContext, query: &CqlQuery) -> Vec<&'a IndexedPage> {
+    let Some(index) = context.page_index.as_deref() else {
+        return Vec::new();
+    };
+
+    let cql_context = CqlContext {
+        current_space: context.default_space_key.as_deref(),
+    };
+    let mut pages: Vec<_> = index
+        .pages()
+        .iter()
+        .filter(|page| query.matches(page, &cql_context))
+        .collect();
+    pages.sort_by(|a, b| a.title().cmp(b.title()));
+    pages
+}
+
+/// Handler for the `contentbylabel` macro, which is rendered as a list of matching pages.
+pub struct ContentByLabelMacroHandler {
+    context: MacroContext,
+}
+
+impl ContentByLabelMacroHandler {
+    pub fn with_context(context: MacroContext) -> Self {
+        Self { context }
+    }
+}
+
+impl TagHandler for ContentByLabelMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let query = match query_from_parameters(tag) {
+            Ok(Some(query)) => query,
+            Ok(None) => return,
+            Err(error) => {
+                render_query_error(tag, &error, self.context.flavor, printer);
+                return;
+            }
+        };
+
+        let mut pages = matching_pages(&self.context, &query);
+        if get_macro_parameter(tag, "reverse").is_some_and(|value| value == "true") {
+            pages.reverse();
+        }
+        if let Some(max) = get_macro_parameter(tag, "max").and_then(|max| max.parse().ok()) {
+            pages.truncate(max);
+        }
+        if pages.is_empty() {
+            return;
+        }
+
+        let show_labels =
+            get_macro_parameter(tag, "showLabels").is_none_or(|value| value == "true");
+
+        let items = pages
+            .into_iter()
+            .map(|page| {
+                let mut content = vec![page.to_link(self.context.confluence_server.as_ref())];
+                if show_labels && !page.labels().is_empty() {
+                    content.push(Inline::Text(format!(" ({})", page.labels().join(", "))));
+                }
+                vec![Block::Paragraph(content)]
+            })
+            .collect();
+        let title = get_macro_parameter(tag, "title")
+            .map(|title| Block::Paragraph(vec![Inline::Strong(vec![Inline::Text(title)])]));
+        let blocks: Vec<_> = title
+            .into_iter()
+            .chain([Block::List {
+                ordered: false,
+                items,
+            }])
+            .collect();
+
+        printer.insert_newline();
+        printer.insert_newline();
+        printer.append_str(&blocks_to_markdown(&blocks, self.context.flavor));
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ConfluencePageId, IndexedPage, PageIndex, ParseOptions, parse_confluence};
+
+    macro_rules! markdown_assert_eq {
+        ($html:expr, $markdown:expr) => {
+            let index: PageIndex = [
+                IndexedPage::new(ConfluencePageId::from(1), "DOCS", "Runbook B")
+                    .with_labels(["runbook"])
+                    .with_path("runbook-b.md"),
+                IndexedPage::new(ConfluencePageId::from(2), "DOCS", "Runbook A")
+                    .with_labels(["runbook", "database"])
+                    .with_path("runbook-a.md"),
+                IndexedPage::new(ConfluencePageId::from(3), "OTHER", "Runbook C")
+                    .with_labels(["runbook"])
+                    .with_path("runbook-c.md"),
+            ]
+            .into_iter()
+            .collect();
+            let options = ParseOptions::default()
+                .with_default_space_key("DOCS".to_string())
+                .with_page_index(index);
+            let md = parse_confluence($html, &options);
+            assert_eq!(md, $markdown);
+        };
+    }
+
+    #[test]
+    fn test_cql() {
+        markdown_assert_eq!(
+            r#"
+<ac:structured-macro ac:name="contentbylabel">
+  <ac:parameter ac:name="showLabels">false</ac:parameter>
+  <ac:parameter ac:name="cql">label = "runbook" and space = currentSpace()</ac:parameter>
+</ac:structured-macro>
+"#,
+            "\
+* [Runbook A](runbook-a.md)
+* [Runbook B](runbook-b.md)"
+        );
+    }
+
+    #[test]
+    fn test_legacy_parameters() {
+        markdown_assert_eq!(
+            r#"
+<ac:structured-macro ac:name="contentbylabel">
+  <ac:parameter ac:name="labels">runbook,database</ac:parameter>
+  <ac:parameter ac:name="operator">AND</ac:parameter>
+</ac:structured-macro>
+"#,
+            "* [Runbook A](runbook-a.md) (runbook, database)"
+        );
+    }
+
+    #[test]
+    fn test_title() {
+        markdown_assert_eq!(
+            r#"
+<ac:structured-macro ac:name="contentbylabel">
+  <ac:parameter ac:name="title">*Runbooks*</ac:parameter>
+  <ac:parameter ac:name="showLabels">false</ac:parameter>
+  <ac:parameter ac:name="labels">database</ac:parameter>
+</ac:structured-macro>
+"#,
+            "**\\*Runbooks\\***\n\n* [Runbook A](runbook-a.md)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_cql() {
+        markdown_assert_eq!(
+            r#"
+<ac:structured-macro ac:name="contentbylabel">
+  <ac:parameter ac:name="cql">creator = "jdoe"</ac:parameter>
+</ac:structured-macro>
+"#,
+            "*Skipped contentbylabel macro (invalid CQL query: unsupported field \"creator\")*"
+        );
+    }
+}
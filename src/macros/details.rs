@@ -0,0 +1,206 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::MacroContext;
+use crate::date::{DateFormat, get_text_content_with_dates};
+use crate::metadata::PageProperties;
+use crate::util::{find_child, get_macro_parameter, get_tag_name, get_text_content};
+use html2md::{Handle, StructuredPrinter, TagHandler};
+
+/// Handler for the Page Properties (`details`) macro.
+///
+/// The key/value table is rendered as usual and additionally stored in the page metadata. If the
+/// macro is hidden, the table is only stored in the metadata.
+pub struct DetailsMacroHandler {
+    context: MacroContext,
+    hidden: bool,
+}
+
+impl DetailsMacroHandler {
+    pub fn with_context(context: MacroContext) -> Self {
+        Self {
+            context,
+            hidden: false,
+        }
+    }
+}
+
+fn find_descendants(tag: &Handle, name: &str, found: &mut Vec<Handle>) {
+    for child in tag.children.borrow().iter() {
+        if get_tag_name(child).is_some_and(|tag_name| tag_name == name) {
+            found.push(child.clone());
+        } else {
+            find_descendants(child, name, found);
+        }
+    }
+}
+
+fn extract_properties(
+    body: &Handle,
+    id: Option<String>,
+    date_format: &DateFormat,
+) -> PageProperties {
+    let mut properties = PageProperties::new(id);
+
+    let mut tables = Vec::new();
+    find_descendants(body, "table", &mut tables);
+    let Some(table) = tables.first() else {
+        return properties;
+    };
+
+    let mut rows = Vec::new();
+    find_descendants(table, "tr", &mut rows);
+    for row in rows {
+        let children = row.children.borrow();
+        let mut cells = children
+            .iter()
+            .filter(|child| get_tag_name(child).is_some_and(|name| name == "th" || name == "td"));
+        let Some((key, value)) = cells.next().zip(cells.next()) else {
+            continue;
+        };
+
+        let key = get_text_content(key).trim().to_string();
+        if key.is_empty() {
+            continue;
+        }
+        properties.insert(
+            key,
+            get_text_content_with_dates(value, date_format)
+                .trim()
+                .to_string(),
+        );
+    }
+
+    properties
+}
+
+impl TagHandler for DetailsMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        self.hidden = get_macro_parameter(tag, "hidden").is_some_and(|value| value == "true");
+
+        if let Some(body) = find_child(tag, "ac:rich-text-body") {
+            let id = get_macro_parameter(tag, "id");
+            let properties = extract_properties(&body, id, &self.context.date_format);
+            self.context
+                .metadata
+                .borrow_mut()
+                .add_properties(properties);
+        }
+
+        if !self.hidden {
+            printer.insert_newline();
+        }
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if !self.hidden {
+            printer.insert_newline();
+            printer.insert_newline();
+        }
+    }
+
+    fn skip_descendants(&self) -> bool {
+        self.hidden
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::markdown_assert_eq;
+    use crate::{DateFormat, ParseOptions, parse_confluence_page};
+
+    const DETAILS: &str = r#"
+<ac:structured-macro ac:name="details">
+  <ac:parameter ac:name="id">project</ac:parameter>
+  <ac:rich-text-body>
+    <table>
+      <tbody>
+        <tr><th>Owner</th><td>Jane Doe</td></tr>
+        <tr><th>Status</th><td><p>In "progress"</p></td></tr>
+      </tbody>
+    </table>
+  </ac:rich-text-body>
+</ac:structured-macro>
+"#;
+
+    #[test]
+    fn test_table_is_rendered() {
+        markdown_assert_eq!(
+            DETAILS,
+            "\
+|Owner |  Jane Doe   |
+|------|-------------|
+|Status|In \"progress\"|"
+        );
+    }
+
+    #[test]
+    fn test_properties_are_extracted() {
+        let page = parse_confluence_page(DETAILS, &ParseOptions::default());
+        let properties = page.metadata().properties();
+        assert_eq!(properties.len(), 1);
+        assert_eq!(properties[0].id(), Some("project"));
+        assert_eq!(properties[0].get("Owner"), Some("Jane Doe"));
+        assert_eq!(properties[0].get("Status"), Some("In \"progress\""));
+        assert_eq!(
+            page.metadata().to_front_matter(),
+            "\
+---
+\"project\":
+  \"Owner\": \"Jane Doe\"
+  \"Status\": \"In \\\"progress\\\"\"
+---
+"
+        );
+    }
+
+    #[test]
+    fn test_hidden() {
+        let html = r#"
+<p>Before</p>
+<ac:structured-macro ac:name="details">
+  <ac:parameter ac:name="hidden">true</ac:parameter>
+  <ac:rich-text-body>
+    <table><tbody><tr><th>Owner</th><td>Jane Doe</td></tr></tbody></table>
+  </ac:rich-text-body>
+</ac:structured-macro>
+<p>After</p>
+"#;
+        let page = parse_confluence_page(html, &ParseOptions::default());
+        assert_eq!(page.markdown(), "Before\n\nAfter");
+        assert_eq!(
+            page.to_markdown_with_front_matter(),
+            "---\n\"Owner\": \"Jane Doe\"\n---\n\nBefore\n\nAfter"
+        );
+    }
+
+    #[test]
+    fn test_dates_are_formatted() {
+        let html = r#"
+<ac:structured-macro ac:name="details">
+  <ac:parameter ac:name="hidden">true</ac:parameter>
+  <ac:rich-text-body>
+    <table><tbody><tr><th>Due</th><td><time datetime="2025-03-01" /></td></tr></tbody></table>
+  </ac:rich-text-body>
+</ac:structured-macro>
+"#;
+        let options = ParseOptions::default().with_date_format(DateFormat::Locale);
+        let page = parse_confluence_page(html, &options);
+        assert_eq!(
+            page.metadata().properties()[0].get("Due"),
+            Some("Mar 1, 2025")
+        );
+    }
+}
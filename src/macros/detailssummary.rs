@@ -0,0 +1,198 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::MacroContext;
+use super::contentbylabel::{matching_pages, query_from_parameters, render_query_error};
+use crate::index::IndexedPage;
+use crate::util::{get_macro_parameter, markdown_table, split_list_parameter};
+use html2md::{Handle, StructuredPrinter, TagHandler};
+
+/// Handler for the Page Properties Report (`detailssummary`) macro, which is rendered as a table
+/// of the page properties of all matching pages.
+pub struct DetailsSummaryMacroHandler {
+    context: MacroContext,
+}
+
+impl DetailsSummaryMacroHandler {
+    pub fn with_context(context: MacroContext) -> Self {
+        Self { context }
+    }
+}
+
+/// Returns the page properties of the page, optionally restricted to `details` macros with the
+/// given id.
+fn page_properties<'a>(page: &'a IndexedPage, id: Option<&str>) -> Vec<(&'a str, &'a str)> {
+    page.metadata()
+        .properties()
+        .iter()
+        .filter(|properties| id.is_none() || properties.id() == id)
+        .flat_map(|properties| properties.entries())
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect()
+}
+
+impl TagHandler for DetailsSummaryMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let query = match query_from_parameters(tag) {
+            Ok(Some(query)) => query,
+            Ok(None) => return,
+            Err(error) => {
+                render_query_error(tag, &error, self.context.flavor, printer);
+                return;
+            }
+        };
+
+        let id = get_macro_parameter(tag, "id");
+        let pages: Vec<_> = matching_pages(&self.context, &query)
+            .into_iter()
+            .map(|page| (page, page_properties(page, id.as_deref())))
+            .filter(|(_, properties)| !properties.is_empty())
+            .collect();
+        if pages.is_empty() {
+            return;
+        }
+
+        let headings = get_macro_parameter(tag, "headings")
+            .map(|value| split_list_parameter(&value))
+            .unwrap_or_else(|| {
+                let mut headings: Vec<String> = Vec::new();
+                for (key, _) in pages.iter().flat_map(|(_, properties)| properties) {
+                    if !headings.iter().any(|heading| heading == key) {
+                        headings.push(key.to_string());
+                    }
+                }
+                headings
+            });
+        let first_column =
+            get_macro_parameter(tag, "firstcolumn").unwrap_or_else(|| "Title".to_string());
+
+        let mut rows: Vec<Vec<String>> = pages
+            .iter()
+            .map(|(page, properties)| {
+                std::iter::once(page.to_markdown_link(self.context.confluence_server.as_ref()))
+                    .chain(headings.iter().map(|heading| {
+                        properties
+                            .iter()
+                            .find(|(key, _)| key == heading)
+                            .map(|(_, value)| value.to_string())
+                            .unwrap_or_default()
+                    }))
+                    .collect()
+            })
+            .collect();
+
+        if let Some(sort_by) = get_macro_parameter(tag, "sortBy")
+            && let Some(column) = headings.iter().position(|heading| heading == &sort_by)
+        {
+            rows.sort_by(|a, b| a[column + 1].cmp(&b[column + 1]));
+        }
+        if get_macro_parameter(tag, "reverseSort").is_some_and(|value| value == "true") {
+            rows.reverse();
+        }
+
+        let header: Vec<_> = std::iter::once(first_column).chain(headings).collect();
+        printer.insert_newline();
+        printer.insert_newline();
+        printer.append_str(&markdown_table(&header, &rows));
+        printer.insert_newline();
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ConfluencePageId, IndexedPage, PageIndex, ParseOptions, parse_confluence,
+        parse_confluence_page,
+    };
+
+    fn page_with_properties(id: usize, title: &str, owner: &str, status: &str) -> IndexedPage {
+        let source = format!(
+            r#"
+<ac:structured-macro ac:name="details">
+  <ac:rich-text-body>
+    <table><tbody>
+      <tr><th>Owner</th><td>{owner}</td></tr>
+      <tr><th>Status</th><td>{status}</td></tr>
+    </tbody></table>
+  </ac:rich-text-body>
+</ac:structured-macro>
+"#
+        );
+        let page = parse_confluence_page(source, &ParseOptions::default());
+        IndexedPage::new(ConfluencePageId::from(id), "DOCS", title)
+            .with_labels(["project"])
+            .with_ancestors([ConfluencePageId::from(100)])
+            .with_metadata(page.metadata().clone())
+            .with_path(format!("{id}.md"))
+    }
+
+    macro_rules! markdown_assert_eq {
+        ($html:expr, $markdown:expr) => {
+            let index: PageIndex = [
+                page_with_properties(1, "Project B", "Jane", "Done"),
+                page_with_properties(2, "Project A", "John", "Blocked"),
+                IndexedPage::new(ConfluencePageId::from(3), "DOCS", "No Properties")
+                    .with_labels(["project"]),
+            ]
+            .into_iter()
+            .collect();
+            let options = ParseOptions::default().with_page_index(index);
+            let md = parse_confluence($html, &options);
+            assert_eq!(md, $markdown);
+        };
+    }
+
+    #[test]
+    fn test_all_headings() {
+        markdown_assert_eq!(
+            r#"
+<ac:structured-macro ac:name="detailssummary">
+  <ac:parameter ac:name="cql">label = "project" and ancestor = "100"</ac:parameter>
+</ac:structured-macro>
+"#,
+            "\
+|      Title      |Owner|Status |
+|-----------------|-----|-------|
+|[Project A](2.md)|John |Blocked|
+|[Project B](1.md)|Jane | Done  |"
+        );
+    }
+
+    #[test]
+    fn test_headings_and_sort() {
+        markdown_assert_eq!(
+            r#"
+<ac:structured-macro ac:name="detailssummary">
+  <ac:parameter ac:name="label">project</ac:parameter>
+  <ac:parameter ac:name="headings">Status</ac:parameter>
+  <ac:parameter ac:name="firstcolumn">Project</ac:parameter>
+  <ac:parameter ac:name="sortBy">Status</ac:parameter>
+  <ac:parameter ac:name="reverseSort">true</ac:parameter>
+</ac:structured-macro>
+"#,
+            "\
+|     Project     |Status |
+|-----------------|-------|
+|[Project B](1.md)| Done  |
+|[Project A](2.md)|Blocked|"
+        );
+    }
+}
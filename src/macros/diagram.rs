@@ -0,0 +1,218 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::MacroContext;
+use crate::document::{Block, Inline, encode_url};
+use crate::flavor::MarkdownFlavor;
+use crate::util::{get_macro_parameter, get_plain_text_body};
+use html2md::{Handle, StructuredPrinter, TagHandler};
+
+/// Handler for text-based diagram macros like `plantuml` and `mermaid-macro`, whose body is
+/// rendered as fenced code block with the given language.
+pub struct DiagramSourceMacroHandler {
+    flavor: MarkdownFlavor,
+    language: &'static str,
+}
+
+impl DiagramSourceMacroHandler {
+    pub fn new(flavor: MarkdownFlavor, language: &'static str) -> Self {
+        Self { flavor, language }
+    }
+}
+
+impl TagHandler for DiagramSourceMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let Some(source) = get_plain_text_body(tag) else {
+            return;
+        };
+
+        printer.insert_newline();
+        printer.insert_newline();
+        let code_block = Block::CodeBlock {
+            language: Some(self.language.to_string()),
+            code: source,
+        };
+        printer.append_str(&code_block.to_markdown(self.flavor));
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+/// Editor that stores its diagrams as page attachments.
+#[derive(Debug, Clone, Copy)]
+pub enum DiagramEditor {
+    DrawIo,
+    Gliffy,
+}
+
+impl DiagramEditor {
+    fn name_parameter(&self) -> &'static str {
+        match self {
+            Self::DrawIo => "diagramName",
+            Self::Gliffy => "name",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::DrawIo => "draw.io",
+            Self::Gliffy => "Gliffy",
+        }
+    }
+
+    fn source_extension(&self) -> &'static str {
+        match self {
+            Self::DrawIo => "drawio",
+            Self::Gliffy => "gliffy",
+        }
+    }
+}
+
+/// Handler for the `drawio` and `gliffy` macros, which are rendered as image of the exported
+/// diagram attachment followed by a link to the diagram source attachment.
+pub struct AttachmentDiagramMacroHandler {
+    context: MacroContext,
+    editor: DiagramEditor,
+}
+
+impl AttachmentDiagramMacroHandler {
+    pub fn new(context: MacroContext, editor: DiagramEditor) -> Self {
+        Self { context, editor }
+    }
+
+    /// Returns the first candidate that is a known attachment, or the first candidate if the
+    /// attachments of the page are unknown.
+    fn find_attachment(&self, candidates: &[String]) -> Option<String> {
+        let attachments = &self.context.attachments;
+        if attachments.attachments().is_empty() {
+            return candidates.first().cloned();
+        }
+        candidates
+            .iter()
+            .find(|candidate| attachments.attachment(candidate).is_some())
+            .cloned()
+    }
+}
+
+impl TagHandler for AttachmentDiagramMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let Some(name) = get_macro_parameter(tag, self.editor.name_parameter()) else {
+            return;
+        };
+
+        let export = self
+            .find_attachment(&[format!("{name}.png"), format!("{name}.svg")])
+            .and_then(|filename| self.context.attachments.url(filename));
+        let source = self
+            .find_attachment(&[
+                name.clone(),
+                format!("{name}.{}", self.editor.source_extension()),
+            ])
+            .and_then(|filename| self.context.attachments.url(filename));
+
+        printer.insert_newline();
+        printer.insert_newline();
+        let title = Inline::Text(name.clone());
+        match export {
+            Some(url) => printer.append_str(&format!(
+                "![{title}]({url})",
+                title = title.to_markdown(),
+                url = encode_url(&url)
+            )),
+            None => printer.append_str(&Inline::Emphasis(vec![title]).to_markdown()),
+        }
+        if let Some(url) = source {
+            let link = Inline::Link {
+                content: vec![Inline::Text(format!(
+                    "{name} ({label} source)",
+                    label = self.editor.label()
+                ))],
+                url,
+            };
+            printer.insert_newline();
+            printer.insert_newline();
+            printer.append_str(&link.to_markdown());
+        }
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Attachment, ConfluencePageId, ConfluenceServer, InMemoryAttachmentProvider, ParseOptions,
+        parse_confluence,
+    };
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_plantuml() {
+        crate::markdown_assert_eq!(
+            r#"<ac:structured-macro ac:name="plantuml">
+  <ac:plain-text-body><![CDATA[@startuml
+Alice -> Bob: Hello
+@enduml]]></ac:plain-text-body>
+</ac:structured-macro>"#,
+            "```plantuml\n@startuml\nAlice -> Bob: Hello\n@enduml\n```"
+        );
+    }
+
+    #[test]
+    fn test_drawio() {
+        let page_id = ConfluencePageId::from(1);
+        let provider = InMemoryAttachmentProvider::default()
+            .with_attachment(page_id.clone(), Attachment::new("Architecture"))
+            .with_attachment(page_id.clone(), Attachment::new("Architecture.svg"));
+        let options = ParseOptions::default()
+            .with_confluence_server(ConfluenceServer::from_str("https://example.com").unwrap())
+            .with_default_page_id(page_id)
+            .with_attachment_provider(Arc::new(provider));
+        let md = parse_confluence(
+            r#"<ac:structured-macro ac:name="drawio">
+  <ac:parameter ac:name="diagramName">Architecture</ac:parameter>
+  <ac:parameter ac:name="revision">3</ac:parameter>
+</ac:structured-macro>"#,
+            &options,
+        );
+        assert_eq!(
+            md,
+            "![Architecture](https://example.com/download/attachments/1/Architecture.svg)
+
+[Architecture (draw.io source)](https://example.com/download/attachments/1/Architecture)"
+        );
+    }
+
+    #[test]
+    fn test_gliffy_without_attachments() {
+        crate::markdown_assert_eq!(
+            r#"<ac:structured-macro ac:name="gliffy"><ac:parameter ac:name="name">Flow_*v2*</ac:parameter></ac:structured-macro>"#,
+            "*Flow\\_\\*v2\\**"
+        );
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::util::get_macro_parameter;
+use html2md::{Handle, StructuredPrinter, TagHandler};
+
+/// Handler for the `excerpt` macro, whose body is rendered unless the excerpt is hidden.
+pub struct ExcerptMacroHandler {
+    hidden: bool,
+}
+
+impl ExcerptMacroHandler {
+    pub fn new() -> Self {
+        Self { hidden: false }
+    }
+}
+
+impl TagHandler for ExcerptMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        self.hidden = get_macro_parameter(tag, "hidden").is_some_and(|value| value == "true");
+        if !self.hidden {
+            printer.insert_newline();
+        }
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if !self.hidden {
+            printer.insert_newline();
+        }
+    }
+
+    fn skip_descendants(&self) -> bool {
+        self.hidden
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_excerpt() {
+        crate::markdown_assert_eq!(
+            r#"<ac:structured-macro ac:name="excerpt">
+  <ac:parameter ac:name="atlassian-macro-output-type">BLOCK</ac:parameter>
+  <ac:rich-text-body><p>This page describes the <strong>release process</strong>.</p></ac:rich-text-body>
+</ac:structured-macro>
+<ac:structured-macro ac:name="excerpt">
+  <ac:parameter ac:name="hidden">true</ac:parameter>
+  <ac:rich-text-body><p>Hidden summary</p></ac:rich-text-body>
+</ac:structured-macro>
+<p>Details follow.</p>"#,
+            "This page describes the **release process**.\n\nDetails follow."
+        );
+    }
+}
@@ -13,65 +13,58 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::util::{get_tag_name, get_text_content};
-use html2md::{Handle, StructuredPrinter, TagHandler, common::get_tag_attr};
+use super::MacroContext;
+use crate::document::Block;
+use crate::util::{find_child, get_macro_parameter};
+use html2md::{Handle, StructuredPrinter, TagHandler};
 
+/// Handler for the `expand` macro, which is rendered as collapsible `<details>` element.
 pub struct ExpandMacroHandler {
-    start_pos: usize,
-    title: Option<String>,
+    context: MacroContext,
 }
 
 impl ExpandMacroHandler {
-    pub fn new() -> Self {
-        Self {
-            start_pos: 0,
-            title: None,
-        }
+    pub fn with_context(context: MacroContext) -> Self {
+        Self { context }
     }
 }
 
 impl TagHandler for ExpandMacroHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
-        self.start_pos = printer.data.len();
-
-        let children = tag.children.borrow();
-        self.title = children
-            .iter()
-            .filter_map(|child| {
-                if get_tag_name(child).is_some_and(|name| name == "ac:parameter") {
-                    get_tag_attr(child, "ac:name").map(|value| (value, child))
-                } else {
-                    None
-                }
-            })
-            .find_map(|(param_name, param)| {
-                if &param_name == "title" {
-                    let s = get_text_content(param);
-                    if s.is_empty() { None } else { Some(s) }
-                } else {
-                    None
-                }
-            });
+        let summary = get_macro_parameter(tag, "title")
+            .unwrap_or_else(|| "Click here to expand...".to_string());
+        let content = find_child(tag, "ac:rich-text-body")
+            .map(|body| self.context.renderer.render_children(&body, printer))
+            .unwrap_or_default();
 
+        let details = Block::Details {
+            summary,
+            content: vec![Block::Markdown(content)],
+        };
         printer.insert_newline();
-        printer.append_str(&format!(
-            "<details><summary>{title}</summary>",
-            title = self.title.as_deref().unwrap_or("Click here to expand...")
-        ));
+        printer.insert_newline();
+        printer.append_str(&details.to_markdown(self.context.flavor));
         printer.insert_newline();
         printer.insert_newline();
     }
 
-    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
-        printer.append_str("</details>");
-        printer.insert_newline();
-        printer.insert_newline();
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::markdown_assert_eq;
+    use crate::{MarkdownFlavor, ParseOptions, markdown_assert_eq, parse_confluence};
+
+    const EXPAND: &str = r#"
+<ac:structured-macro ac:name="expand">
+  <ac:parameter ac:name="title">Read &lt;more&gt; &amp; *more*</ac:parameter>
+  <ac:rich-text-body><p>Hidden text.</p></ac:rich-text-body>
+</ac:structured-macro>
+"#;
 
     #[test]
     fn test_with_title() {
@@ -115,4 +108,26 @@ This is *important* information.
 </details>"
         );
     }
+
+    #[test]
+    fn test_title_is_escaped() {
+        markdown_assert_eq!(
+            EXPAND,
+            "\
+<details><summary>Read &lt;more&gt; &amp; *more*</summary>
+
+Hidden text.
+
+</details>"
+        );
+    }
+
+    #[test]
+    fn test_plain_flavor() {
+        let options = ParseOptions::default().with_flavor(MarkdownFlavor::Plain);
+        assert_eq!(
+            parse_confluence(EXPAND, &options),
+            "**Read \\<more> & \\*more\\***\n\nHidden text."
+        );
+    }
 }
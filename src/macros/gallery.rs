@@ -0,0 +1,192 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::MacroContext;
+use crate::document::{Inline, encode_url};
+use crate::util::{get_macro_parameter, html_escape, split_list_parameter};
+use html2md::{Handle, StructuredPrinter, TagHandler};
+
+/// Handler for the `gallery` macro, which is rendered as a grid of the page's image attachments.
+pub struct GalleryMacroHandler {
+    context: MacroContext,
+}
+
+impl GalleryMacroHandler {
+    const DEFAULT_COLUMNS: usize = 4;
+
+    pub fn with_context(context: MacroContext) -> Self {
+        Self { context }
+    }
+}
+
+impl TagHandler for GalleryMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let include = get_macro_parameter(tag, "include").map(|value| split_list_parameter(&value));
+        let exclude = get_macro_parameter(tag, "exclude")
+            .map(|value| split_list_parameter(&value))
+            .unwrap_or_default();
+        let columns = get_macro_parameter(tag, "columns")
+            .and_then(|columns| columns.parse().ok())
+            .filter(|columns| *columns > 0)
+            .unwrap_or(Self::DEFAULT_COLUMNS);
+
+        let mut images: Vec<_> = self
+            .context
+            .attachments
+            .attachments()
+            .iter()
+            .filter(|attachment| attachment.is_image())
+            .filter(|attachment| {
+                include.as_ref().is_none_or(|include| {
+                    include
+                        .iter()
+                        .any(|filename| filename == attachment.filename())
+                })
+            })
+            .filter(|attachment| {
+                !exclude
+                    .iter()
+                    .any(|filename| filename == attachment.filename())
+            })
+            .filter_map(|attachment| {
+                self.context
+                    .attachments
+                    .url(attachment.filename())
+                    .map(|url| (attachment.filename().to_string(), url))
+            })
+            .collect();
+        images.sort_by(|(a, _), (b, _)| a.cmp(b));
+        if get_macro_parameter(tag, "reverse").is_some_and(|value| value == "true") {
+            images.reverse();
+        }
+        if images.is_empty() {
+            return;
+        }
+
+        printer.insert_newline();
+        if let Some(title) = get_macro_parameter(tag, "title") {
+            printer.insert_newline();
+            printer.append_str(&Inline::Strong(vec![Inline::Text(title)]).to_markdown());
+            printer.insert_newline();
+        }
+        printer.insert_newline();
+
+        if self.context.flavor.allows_raw_html() {
+            printer.append_str("<table>\n");
+            for row in images.chunks(columns) {
+                printer.append_str("<tr>");
+                for (filename, url) in row {
+                    printer.append_str(&format!(
+                        r#"<td><a href="{url}"><img src="{url}" alt="{filename}" width="200"></a></td>"#,
+                        url = html_escape(url),
+                        filename = html_escape(filename)
+                    ));
+                }
+                printer.append_str("</tr>\n");
+            }
+            printer.append_str("</table>\n");
+        } else {
+            for row in images.chunks(columns) {
+                let row: Vec<_> = row
+                    .iter()
+                    .map(|(filename, url)| {
+                        format!(
+                            "![{alt}]({url})",
+                            alt = Inline::Text(filename.clone()).to_markdown(),
+                            url = encode_url(url)
+                        )
+                    })
+                    .collect();
+                printer.append_str(&row.join(" "));
+                printer.insert_newline();
+                printer.insert_newline();
+            }
+        }
+        printer.insert_newline();
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Attachment, ConfluencePageId, InMemoryAttachmentProvider, MarkdownFlavor, ParseOptions,
+        parse_confluence,
+    };
+    use std::sync::Arc;
+
+    fn options() -> ParseOptions {
+        let page_id = ConfluencePageId::from(1);
+        let provider = InMemoryAttachmentProvider::default()
+            .with_attachment(
+                page_id.clone(),
+                Attachment::new("b.png").with_local_path("assets/b.png"),
+            )
+            .with_attachment(
+                page_id.clone(),
+                Attachment::new("a.jpg").with_local_path("assets/a.jpg"),
+            )
+            .with_attachment(
+                page_id.clone(),
+                Attachment::new("c.png").with_local_path("assets/c.png"),
+            )
+            .with_attachment(
+                page_id.clone(),
+                Attachment::new("doc.pdf").with_local_path("assets/doc.pdf"),
+            );
+        ParseOptions::default()
+            .with_default_page_id(page_id)
+            .with_attachment_provider(Arc::new(provider))
+    }
+
+    const GALLERY: &str = r#"
+<ac:structured-macro ac:name="gallery">
+  <ac:parameter ac:name="columns">2</ac:parameter>
+  <ac:parameter ac:name="exclude">c.png</ac:parameter>
+</ac:structured-macro>"#;
+
+    #[test]
+    fn test_gallery_html() {
+        assert_eq!(
+            parse_confluence(GALLERY, &options()),
+            r#"<table>
+<tr><td><a href="assets/a.jpg"><img src="assets/a.jpg" alt="a.jpg" width="200"></a></td><td><a href="assets/b.png"><img src="assets/b.png" alt="b.png" width="200"></a></td></tr>
+</table>"#
+        );
+    }
+
+    #[test]
+    fn test_gallery_plain() {
+        let options = options().with_flavor(MarkdownFlavor::Plain);
+        let md = parse_confluence(
+            r#"
+<ac:structured-macro ac:name="gallery">
+  <ac:parameter ac:name="title">Screenshots</ac:parameter>
+  <ac:parameter ac:name="columns">2</ac:parameter>
+  <ac:parameter ac:name="include">c.png,a.jpg,doc.pdf</ac:parameter>
+</ac:structured-macro>"#,
+            &options,
+        );
+        assert_eq!(
+            md,
+            "**Screenshots**\n\n![a.jpg](assets/a.jpg) ![c.png](assets/c.png)"
+        );
+    }
+}
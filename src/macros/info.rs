@@ -13,120 +13,85 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::util::{get_tag_name, get_text_content};
+use super::MacroContext;
+use crate::container::is_inside_table_cell;
+use crate::document::{AdmonitionKind, Block, Inline};
+use crate::util::{find_child, get_macro_parameter};
 use html2md::{Handle, StructuredPrinter, TagHandler, common::get_tag_attr};
-use std::str::FromStr;
 
-enum HighlightType {
-    Info,
-    Tip,
-    Note,
-    Warning,
-}
-
-impl HighlightType {
-    const GFM_ALERT_NOTE: &str = "[!NOTE]";
-    const GFM_ALERT_TIP: &str = "[!TIP]";
-    const GFM_ALERT_INFO: &str = "[!IMPORTANT]";
-    const GFM_ALERT_WARNING: &str = "[!WARNING]";
-
-    fn as_gfm_alert(&self) -> &'static str {
-        match self {
-            Self::Info => Self::GFM_ALERT_INFO,
-            Self::Tip => Self::GFM_ALERT_TIP,
-            Self::Note => Self::GFM_ALERT_NOTE,
-            Self::Warning => Self::GFM_ALERT_WARNING,
-        }
+fn admonition_kind(name: &str) -> Option<AdmonitionKind> {
+    match name {
+        "info" => Some(AdmonitionKind::Info),
+        "tip" => Some(AdmonitionKind::Tip),
+        // Panels have no semantic kind, so they are rendered as neutral notes.
+        "note" | "panel" => Some(AdmonitionKind::Note),
+        "warning" => Some(AdmonitionKind::Warning),
+        _ => None,
     }
 }
 
-impl FromStr for HighlightType {
-    type Err = &'static str;
-
-    // Required method
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "info" => Ok(Self::Info),
-            "tip" => Ok(Self::Tip),
-            "note" => Ok(Self::Note),
-            "warning" => Ok(Self::Warning),
-            _ => Err("invalid name"),
+/// Returns an admonition with the given content, or a paragraph starting with its label inside of
+/// table cells.
+pub(crate) fn admonition_block(
+    kind: AdmonitionKind,
+    title: Option<String>,
+    content: String,
+    printer: &StructuredPrinter,
+) -> Block {
+    if is_inside_table_cell(printer) {
+        // Table cells cannot contain block quotes, so only the label is kept.
+        let label = match title {
+            Some(title) => format!("{}: {title}", kind.label()),
+            None => kind.label().to_string(),
+        };
+        Block::Paragraph(vec![
+            Inline::Strong(vec![Inline::Text(label)]),
+            Inline::Markdown(format!(" {content}")),
+        ])
+    } else {
+        Block::Admonition {
+            kind,
+            title,
+            content: vec![Block::Markdown(content)],
         }
     }
 }
 
+/// Handler for the `info`, `tip`, `note`, `warning` and `panel` macros, which are rendered as
+/// admonitions (e.g. GitHub alerts).
 pub struct InfoMacroHandler {
-    start_pos: usize,
-    highlight_type: HighlightType,
-    title: Option<String>,
+    context: MacroContext,
 }
 
 impl InfoMacroHandler {
-    const QUOTE: &str = "> ";
-
-    pub fn new() -> Self {
-        Self {
-            start_pos: 0,
-            title: None,
-            highlight_type: HighlightType::Info,
-        }
+    pub fn with_context(context: MacroContext) -> Self {
+        Self { context }
     }
 }
 
 impl TagHandler for InfoMacroHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
-        self.start_pos = printer.data.len();
-
-        self.highlight_type = get_tag_attr(tag, "ac:name")
+        let kind = get_tag_attr(tag, "ac:name")
             .as_deref()
-            .and_then(|s| HighlightType::from_str(s).ok())
+            .and_then(admonition_kind)
             .expect("invalid name");
-        let children = tag.children.borrow();
-        self.title = children
-            .iter()
-            .filter_map(|child| {
-                if get_tag_name(child).is_some_and(|name| name == "ac:parameter") {
-                    get_tag_attr(child, "ac:name").map(|value| (value, child))
-                } else {
-                    None
-                }
-            })
-            .find_map(|(param_name, param)| {
-                if &param_name == "title" {
-                    let s = get_text_content(param);
-                    if s.is_empty() { None } else { Some(s) }
-                } else {
-                    None
-                }
-            });
+        let title = get_macro_parameter(tag, "title");
+        let content = find_child(tag, "ac:rich-text-body")
+            .map(|body| self.context.renderer.render_children(&body, printer))
+            .unwrap_or_default();
 
+        let admonition = admonition_block(kind, title, content, printer);
+        printer.insert_newline();
+        printer.insert_newline();
+        printer.append_str(&admonition.to_markdown(self.context.flavor));
+        printer.insert_newline();
         printer.insert_newline();
     }
 
-    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
-        let mut index = printer.data.len();
-        while index > self.start_pos {
-            if printer.data.as_bytes().get(index).copied() == Some(b'\n') {
-                printer.insert_str(index + 1, Self::QUOTE);
-            }
-            index -= 1;
-        }
-
-        let mut pos = self.start_pos + 1;
-        printer.insert_str(pos, Self::QUOTE);
-        pos += Self::QUOTE.len();
-        let gfm_alert = self.highlight_type.as_gfm_alert();
-        printer.insert_str(pos, gfm_alert);
-        pos += gfm_alert.len();
-        if let Some(title) = self.title.as_deref() {
-            printer.insert_str(
-                pos,
-                format!("\n{quote}**{title}**", quote = Self::QUOTE).as_str(),
-            );
-        }
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
 
-        printer.insert_newline();
-        printer.insert_newline();
+    fn skip_descendants(&self) -> bool {
+        true
     }
 }
 
@@ -152,9 +117,7 @@ mod test {
 > [!IMPORTANT]
 > **Some info**
 >
-> This is *important* information.
->
->"
+> This is *important* information."
         );
     }
 
@@ -174,9 +137,67 @@ mod test {
             "\
 > [!NOTE]
 >
-> This is *important* information.
+> This is *important* information."
+        );
+    }
+
+    #[test]
+    fn test_nested_content() {
+        markdown_assert_eq!(
+            r#"
+<ac:structured-macro ac:name="warning">
+  <ac:rich-text-body>
+    <p>Run:</p>
+    <pre>make
+make install</pre>
+    <ul><li>first</li><li>second</li></ul>
+    <ac:structured-macro ac:name="tip">
+      <ac:rich-text-body><p>Use <code>-j</code>.</p></ac:rich-text-body>
+    </ac:structured-macro>
+  </ac:rich-text-body>
+</ac:structured-macro>
+<p>After</p>
+"#,
+            "\
+> [!WARNING]
+>
+> Run:
+>
+> ```
+> make
+> make install
+> ```
+>
+> * first
+> * second
+>
+> > [!TIP]
+> >
+> > Use `-j`.
+
+After"
+        );
+    }
+
+    #[test]
+    fn test_panel() {
+        markdown_assert_eq!(
+            r#"
+<ac:structured-macro ac:name="panel">
+  <ac:parameter ac:name="title">Checklist</ac:parameter>
+  <ac:parameter ac:name="bgColor">#eae6ff</ac:parameter>
+  <ac:rich-text-body><p>Text</p></ac:rich-text-body>
+</ac:structured-macro>
+<table><tbody><tr><td><ac:structured-macro ac:name="panel"><ac:rich-text-body><p>In a cell</p></ac:rich-text-body></ac:structured-macro></td></tr></tbody></table>
+"#,
+            "\
+> [!NOTE]
+> **Checklist**
 >
->"
+> Text
+
+|**Note** In a cell|
+|------------------|"
         );
     }
 }
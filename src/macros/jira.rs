@@ -13,61 +13,185 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::util::{JiraServerMap, get_tag_name, get_text_content};
-use html2md::{Handle, StructuredPrinter, TagHandler, common::get_tag_attr};
+use super::MacroContext;
+use crate::document::Inline;
+use crate::jira::JiraIssue;
+use crate::util::{
+    JiraServer, get_text_content, macro_parameters, markdown_table, split_list_parameter,
+};
+use html2md::{Handle, StructuredPrinter, TagHandler};
 
 #[derive(Debug, Clone)]
 pub struct JiraMacroHandler {
-    servers: JiraServerMap,
+    context: MacroContext,
 }
 
 impl JiraMacroHandler {
-    pub fn with_servers(servers: JiraServerMap) -> Self {
-        Self { servers }
+    const DEFAULT_COLUMNS: &str =
+        "type,key,summary,assignee,reporter,priority,status,resolution,created,updated,due";
+    const DEFAULT_MAXIMUM_ISSUES: usize = 20;
+
+    pub fn with_context(context: MacroContext) -> Self {
+        Self { context }
+    }
+
+    fn render_issue(&self, server: Option<&JiraServer>, key: &str, show_summary: bool) -> String {
+        let link = issue_link(server, key);
+        let issue = self
+            .context
+            .jira_issue_provider
+            .as_deref()
+            .filter(|_| show_summary)
+            .and_then(|provider| provider.issue(key));
+        let Some(issue) = issue else {
+            return link.to_markdown();
+        };
+
+        let mut content = vec![link];
+        if let Some(summary) = issue.summary() {
+            content.push(Inline::Text(format!(": {summary}")));
+        }
+        if let Some(status) = issue.status() {
+            content.push(Inline::Text(format!(" ({status})")));
+        }
+        content.iter().map(Inline::to_markdown).collect()
+    }
+
+    fn render_issue_table(
+        server: Option<&JiraServer>,
+        issues: &[JiraIssue],
+        columns: &[String],
+        maximum_issues: usize,
+    ) -> String {
+        let header: Vec<_> = columns.iter().map(|column| capitalize(column)).collect();
+        let rows: Vec<Vec<String>> = issues
+            .iter()
+            .take(maximum_issues)
+            .map(|issue| {
+                columns
+                    .iter()
+                    .map(|column| {
+                        if column.eq_ignore_ascii_case("key") {
+                            issue_link(server, issue.key()).to_markdown()
+                        } else {
+                            Inline::Text(issue.field(column).unwrap_or_default().to_string())
+                                .to_markdown()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        markdown_table(&header, &rows)
+    }
+}
+
+/// Returns a link to the issue, or just the key if the server is unknown.
+fn issue_link(server: Option<&JiraServer>, key: &str) -> Inline {
+    let text = Inline::Text(key.to_string());
+    match server {
+        Some(server) => Inline::Link {
+            content: vec![text],
+            url: server.issue_url(key),
+        },
+        None => text,
     }
 }
 
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
 impl TagHandler for JiraMacroHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
         let mut key = None;
         let mut jql = None;
-        let mut server = None;
-
-        let children = tag.children.borrow();
-        let params = children.iter().filter_map(|child| {
-            if get_tag_name(child).is_some_and(|name| name == "ac:parameter") {
-                get_tag_attr(child, "ac:name").map(|value| (value, child))
-            } else {
-                None
-            }
-        });
+        let mut server_id = None;
+        let mut server_name = None;
+        let mut columns = None;
+        let mut count = false;
+        let mut maximum_issues = Self::DEFAULT_MAXIMUM_ISSUES;
+        let mut show_summary = true;
 
-        for (param_name, param) in params {
+        // Later parameters override earlier ones, so the default parameter must come first.
+        for (param_name, param) in macro_parameters(tag).iter().rev() {
             match param_name.as_str() {
                 "key" => key = Some(get_text_content(param)),
                 "jqlQuery" => jql = Some(get_text_content(param)),
-                "serverId" => server = self.servers.by_id(get_text_content(param)),
+                "serverId" => server_id = Some(get_text_content(param)),
+                "server" => server_name = Some(get_text_content(param)),
+                "columns" => columns = Some(split_list_parameter(&get_text_content(param))),
+                "count" => count = get_text_content(param) == "true",
+                "maximumIssues" => {
+                    maximum_issues = get_text_content(param)
+                        .parse()
+                        .unwrap_or(Self::DEFAULT_MAXIMUM_ISSUES)
+                }
+                "showSummary" => show_summary = get_text_content(param) != "false",
                 _ => (),
             }
         }
 
-        let Some(server) = server else {
-            return;
-        };
+        let server = self
+            .context
+            .jira_server_map
+            .resolve(server_id.as_deref(), server_name.as_deref());
 
-        let link = jql
-            .map(|query| {
-                let url = server.jql_url(&query);
-                format!("[``{query}``]({url})")
-            })
-            .or_else(|| {
-                key.map(|k| {
-                    let url = server.issue_url(&k);
-                    format!("[{k}]({url})")
-                })
-            });
-        if let Some(link) = link {
-            printer.append_str(&link);
+        if let Some(query) = jql {
+            let issues = self
+                .context
+                .jira_issue_provider
+                .as_deref()
+                .and_then(|provider| provider.search(&query));
+            match issues {
+                Some(issues) if count => {
+                    let text = match issues.len() {
+                        1 => "1 issue".to_string(),
+                        n => format!("{n} issues"),
+                    };
+                    match server {
+                        Some(server) => printer
+                            .append_str(&format!("[{text}]({url})", url = server.jql_url(&query))),
+                        None => printer.append_str(&text),
+                    }
+                }
+                Some(issues) if !issues.is_empty() => {
+                    let columns =
+                        columns.unwrap_or_else(|| split_list_parameter(Self::DEFAULT_COLUMNS));
+                    printer.insert_newline();
+                    printer.insert_newline();
+                    printer.append_str(&Self::render_issue_table(
+                        server,
+                        &issues,
+                        &columns,
+                        maximum_issues,
+                    ));
+                    printer.insert_newline();
+                }
+                Some(_) => {
+                    let text = Inline::Text("No issues found".to_string());
+                    let text = match server {
+                        Some(server) => Inline::Link {
+                            content: vec![text],
+                            url: server.jql_url(&query),
+                        },
+                        None => text,
+                    };
+                    printer.append_str(&text.to_markdown());
+                }
+                None => match server {
+                    Some(server) => printer.append_str(&format!(
+                        "[``{query}``]({url})",
+                        url = server.jql_url(&query)
+                    )),
+                    None => printer.append_str(&format!("``{query}``")),
+                },
+            }
+        } else if let Some(key) = key {
+            printer.append_str(&self.render_issue(server, &key, show_summary));
         }
     }
 
@@ -80,8 +204,9 @@ impl TagHandler for JiraMacroHandler {
 
 #[cfg(test)]
 mod test {
-    use crate::{JiraServer, ParseOptions, parse_confluence};
+    use crate::{JiraServer, JsonJiraIssueProvider, ParseOptions, parse_confluence};
     use std::str::FromStr;
+    use std::sync::Arc;
 
     macro_rules! markdown_assert_eq {
         ($html:expr, $markdown:expr) => {
@@ -92,8 +217,28 @@ mod test {
             let md = parse_confluence($html, &options);
             assert_eq!(md, $markdown);
         };
+        ($html:expr, $markdown:expr, with_issues) => {
+            let provider = JsonJiraIssueProvider::from_reader(ISSUES_JSON.as_bytes()).unwrap();
+            let options = ParseOptions::default()
+                .with_jira_server(
+                    "144880e9-a1111-333f-9412-ed999a9999fa".to_string(),
+                    JiraServer::from_str("http://jira.atlassian.com").unwrap(),
+                )
+                .with_jira_issue_provider(Arc::new(provider));
+            let md = parse_confluence($html, &options);
+            assert_eq!(md, $markdown);
+        };
     }
 
+    const ISSUES_JSON: &str = r#"{
+  "issues": [
+    {"key": "CONF-1234", "fields": {"summary": "Fix the docs", "status": {"name": "In Progress"},
+     "assignee": {"displayName": "Jane Doe"}}},
+    {"key": "CONF-1235", "fields": {"summary": "Write more docs", "status": {"name": "Open"}}}
+  ],
+  "queries": {"project = CONF": ["CONF-1234", "CONF-1235"], "key = CONF-1234": ["CONF-1234"]}
+}"#;
+
     #[test]
     fn test_jql_query() {
         markdown_assert_eq!(
@@ -121,4 +266,159 @@ mod test {
             "[CONF-1234](http://jira.atlassian.com/browse/CONF-1234)"
         );
     }
+
+    #[test]
+    fn test_legacy_macro_with_default_parameter() {
+        markdown_assert_eq!(
+            r#"<p>See <ac:macro ac:name="jira"><ac:default-parameter>CONF-1234</ac:default-parameter><ac:parameter ac:name="serverId">144880e9-a1111-333f-9412-ed999a9999fa</ac:parameter></ac:macro> and <ac:structured-macro ac:name="jira"><ac:parameter ac:name="">CONF-1235</ac:parameter><ac:parameter ac:name="serverId">144880e9-a1111-333f-9412-ed999a9999fa</ac:parameter></ac:structured-macro></p>"#,
+            "See [CONF-1234](http://jira.atlassian.com/browse/CONF-1234) and [CONF-1235](http://jira.atlassian.com/browse/CONF-1235)"
+        );
+    }
+
+    #[test]
+    fn test_issue_key_with_summary() {
+        markdown_assert_eq!(
+            r#"
+  <ac:structured-macro ac:name="jira">
+  <ac:parameter ac:name="serverId">144880e9-a1111-333f-9412-ed999a9999fa</ac:parameter>
+  <ac:parameter ac:name="key">CONF-1234</ac:parameter>
+  </ac:structured-macro>"#,
+            "[CONF-1234](http://jira.atlassian.com/browse/CONF-1234): Fix the docs (In Progress)",
+            with_issues
+        );
+    }
+
+    #[test]
+    fn test_jql_query_table() {
+        markdown_assert_eq!(
+            r#"
+  <ac:structured-macro ac:name="jira">
+  <ac:parameter ac:name="columns">key,summary,assignee,status</ac:parameter>
+  <ac:parameter ac:name="maximumIssues">1</ac:parameter>
+  <ac:parameter ac:name="serverId">144880e9-a1111-333f-9412-ed999a9999fa</ac:parameter>
+  <ac:parameter ac:name="jqlQuery">project = CONF</ac:parameter>
+  </ac:structured-macro>"#,
+            "\
+|                          Key                          |  Summary   |Assignee|  Status   |
+|-------------------------------------------------------|------------|--------|-----------|
+|[CONF-1234](http://jira.atlassian.com/browse/CONF-1234)|Fix the docs|Jane Doe|In Progress|",
+            with_issues
+        );
+    }
+
+    #[test]
+    fn test_provider_data_is_escaped() {
+        let provider = JsonJiraIssueProvider::from_reader(
+            r#"{
+  "issues": [{"key": "CONF-1", "fields": {"summary": "Fix [docs](x) *now*", "status": {"name": "<script>"}}}],
+  "queries": {"project = CONF": ["CONF-1"], "project = NONE": []}
+}"#
+            .as_bytes(),
+        )
+        .unwrap();
+        let options = ParseOptions::default()
+            .with_default_jira_server(JiraServer::from_str("http://jira.atlassian.com").unwrap())
+            .with_jira_issue_provider(Arc::new(provider));
+        let macro_with = |parameter, value| {
+            format!(
+                r#"<ac:structured-macro ac:name="jira"><ac:parameter ac:name="columns">summary,status</ac:parameter><ac:parameter ac:name="{parameter}">{value}</ac:parameter></ac:structured-macro>"#
+            )
+        };
+
+        assert_eq!(
+            parse_confluence(macro_with("key", "CONF-1"), &options),
+            "[CONF-1](http://jira.atlassian.com/browse/CONF-1): Fix \\[docs\\](x) \\*now\\* (\\<script>)"
+        );
+        assert_eq!(
+            parse_confluence(macro_with("jqlQuery", "project = CONF"), &options),
+            "\
+|        Summary        | Status  |
+|-----------------------|---------|
+|Fix \\[docs\\](x) \\*now\\*|\\<script>|"
+        );
+        assert_eq!(
+            parse_confluence(macro_with("jqlQuery", "project = NONE"), &options),
+            "[No issues found](http://jira.atlassian.com/issues/?jql=project%20%3D%20NONE)"
+        );
+    }
+
+    #[test]
+    fn test_jql_query_count() {
+        markdown_assert_eq!(
+            r#"
+  <ac:structured-macro ac:name="jira">
+  <ac:parameter ac:name="count">true</ac:parameter>
+  <ac:parameter ac:name="serverId">144880e9-a1111-333f-9412-ed999a9999fa</ac:parameter>
+  <ac:parameter ac:name="jqlQuery">project = CONF</ac:parameter>
+  </ac:structured-macro>"#,
+            "[2 issues](http://jira.atlassian.com/issues/?jql=project%20%3D%20CONF)",
+            with_issues
+        );
+    }
+
+    #[test]
+    fn test_jql_query_count_single_issue() {
+        markdown_assert_eq!(
+            r#"
+  <ac:structured-macro ac:name="jira">
+  <ac:parameter ac:name="count">true</ac:parameter>
+  <ac:parameter ac:name="serverId">144880e9-a1111-333f-9412-ed999a9999fa</ac:parameter>
+  <ac:parameter ac:name="jqlQuery">key = CONF-1234</ac:parameter>
+  </ac:structured-macro>"#,
+            "[1 issue](http://jira.atlassian.com/issues/?jql=key%20%3D%20CONF-1234)",
+            with_issues
+        );
+    }
+
+    #[test]
+    fn test_server_by_name() {
+        let options = ParseOptions::default()
+            .with_named_jira_server(
+                "144880e9-a1111-333f-9412-ed999a9999fa".to_string(),
+                "Atlassian JIRA".to_string(),
+                JiraServer::from_str("http://jira.atlassian.com").unwrap(),
+            )
+            .with_default_jira_server(JiraServer::from_str("http://jira.example.com").unwrap());
+        let md = parse_confluence(
+            r#"
+  <ac:structured-macro ac:name="jira">
+  <ac:parameter ac:name="server">Atlassian JIRA</ac:parameter>
+  <ac:parameter ac:name="serverId">00000000-0000-0000-0000-000000000000</ac:parameter>
+  <ac:parameter ac:name="key">CONF-1234</ac:parameter>
+  </ac:structured-macro>"#,
+            &options,
+        );
+        assert_eq!(
+            md,
+            "[CONF-1234](http://jira.atlassian.com/browse/CONF-1234)"
+        );
+    }
+
+    #[test]
+    fn test_default_server() {
+        let options = ParseOptions::default()
+            .with_default_jira_server(JiraServer::from_str("http://jira.example.com").unwrap());
+        let md = parse_confluence(
+            r#"
+  <ac:structured-macro ac:name="jira">
+  <ac:parameter ac:name="serverId">00000000-0000-0000-0000-000000000000</ac:parameter>
+  <ac:parameter ac:name="key">CONF-1234</ac:parameter>
+  </ac:structured-macro>"#,
+            &options,
+        );
+        assert_eq!(md, "[CONF-1234](http://jira.example.com/browse/CONF-1234)");
+    }
+
+    #[test]
+    fn test_unknown_server() {
+        let md = parse_confluence(
+            r#"
+  <ac:structured-macro ac:name="jira">
+  <ac:parameter ac:name="serverId">00000000-0000-0000-0000-000000000000</ac:parameter>
+  <ac:parameter ac:name="key">CONF-1234</ac:parameter>
+  </ac:structured-macro>"#,
+            &ParseOptions::default(),
+        );
+        assert_eq!(md, "CONF-1234");
+    }
 }
@@ -0,0 +1,81 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::util::{get_macro_parameter, get_plain_text_body};
+use html2md::{Handle, StructuredPrinter, TagHandler};
+
+/// Handler for the `mathblock`, `mathinline` and `latex` macros, which are rendered as `$$…$$`
+/// and `$…$` respectively.
+pub struct MathMacroHandler {
+    inline: bool,
+}
+
+impl MathMacroHandler {
+    pub fn block() -> Self {
+        Self { inline: false }
+    }
+
+    pub fn inline() -> Self {
+        Self { inline: true }
+    }
+}
+
+impl TagHandler for MathMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        // The inline macro stores the formula in the `body` parameter instead of the body.
+        let Some(formula) = get_plain_text_body(tag).or_else(|| get_macro_parameter(tag, "body"))
+        else {
+            return;
+        };
+
+        if self.inline {
+            printer.append_str(&format!("${}$", formula.trim()));
+        } else {
+            printer.insert_newline();
+            printer.insert_newline();
+            printer.append_str(&format!("$$\n{formula}\n$$"));
+            printer.insert_newline();
+            printer.insert_newline();
+        }
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_mathblock() {
+        crate::markdown_assert_eq!(
+            r#"<p>Euler:</p>
+<ac:structured-macro ac:name="mathblock">
+  <ac:plain-text-body><![CDATA[e^{i\pi} + 1 = 0]]></ac:plain-text-body>
+</ac:structured-macro>"#,
+            "Euler:\n\n$$\ne^{i\\pi} + 1 = 0\n$$"
+        );
+    }
+
+    #[test]
+    fn test_mathinline() {
+        crate::markdown_assert_eq!(
+            r#"<p>The area is <ac:structured-macro ac:name="mathinline"><ac:parameter ac:name="body">\pi r^2</ac:parameter></ac:structured-macro>.</p>"#,
+            "The area is $\\pi r^2$."
+        );
+    }
+}
@@ -13,24 +13,135 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+mod anchor;
+mod attachments;
+mod code;
+mod contentbylabel;
+mod details;
+mod detailssummary;
+mod diagram;
+mod excerpt;
 mod expand;
-mod info;
+mod gallery;
+pub(crate) mod info;
 mod jira;
-mod status;
+mod math;
+mod multimedia;
+mod passthrough;
+mod section;
+pub mod status;
+mod viewfile;
 
-use crate::util::{JiraServerMap, get_tag_name};
+use crate::attachment::AttachmentResolver;
+use crate::converter::ConversionContext;
+use crate::document::ContentRenderer;
+use crate::metadata::SharedPageMetadata;
+use crate::util::{ConfluencePageId, get_tag_name};
 use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory, common::get_tag_attr};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// State shared between all macro handlers of a single conversion. Cloning it only clones
+/// reference-counted pointers.
+#[derive(Debug, Clone)]
+pub struct MacroContext {
+    shared: Arc<ConversionContext>,
+    pub(crate) attachments: Rc<AttachmentResolver>,
+    pub(crate) page_id: Option<ConfluencePageId>,
+    pub(crate) metadata: SharedPageMetadata,
+    /// The `ac:ref` ids of the inline comments known to the comment provider.
+    pub(crate) known_comments: Rc<HashSet<String>>,
+    pub(crate) renderer: ContentRenderer,
+}
+
+impl MacroContext {
+    pub(crate) fn new(
+        shared: Arc<ConversionContext>,
+        attachments: AttachmentResolver,
+        page_id: Option<ConfluencePageId>,
+        metadata: SharedPageMetadata,
+        known_comments: HashSet<String>,
+        renderer: ContentRenderer,
+    ) -> Self {
+        Self {
+            shared,
+            attachments: Rc::new(attachments),
+            page_id,
+            metadata,
+            known_comments: Rc::new(known_comments),
+            renderer,
+        }
+    }
+}
+
+/// Handle to the [`MacroContext`] of the document that is currently converted.
+///
+/// The tag handler factories are reused for all documents of a converter, so they only hold this
+/// handle and get the context of the current document when they instantiate a handler.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DocumentContext(Rc<RefCell<Option<MacroContext>>>);
+
+impl DocumentContext {
+    /// Sets the context until the returned guard is dropped.
+    pub fn enter(&self, context: MacroContext) -> DocumentGuard<'_> {
+        *self.0.borrow_mut() = Some(context);
+        DocumentGuard(self)
+    }
+
+    /// Returns true while a document is converted.
+    pub fn is_active(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+
+    /// Returns the context of the current document.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no document is converted.
+    pub fn get(&self) -> MacroContext {
+        self.0
+            .borrow()
+            .clone()
+            .expect("handler instantiated outside of a conversion")
+    }
+}
+
+/// Clears the [`DocumentContext`] when the conversion of a document is finished.
+pub(crate) struct DocumentGuard<'a>(&'a DocumentContext);
+
+impl Drop for DocumentGuard<'_> {
+    fn drop(&mut self) {
+        self.0.0.borrow_mut().take();
+    }
+}
+
+/// Gives access to the settings shared by all conversions.
+impl Deref for MacroContext {
+    type Target = ConversionContext;
+
+    fn deref(&self) -> &Self::Target {
+        &self.shared
+    }
+}
+
+/// Returns true if the tag is a macro, either in the current (`ac:structured-macro`) or in the
+/// legacy (`ac:macro`) format.
+pub(crate) fn is_macro(tag: &Handle) -> bool {
+    get_tag_name(tag).is_some_and(|name| name == "ac:structured-macro" || name == "ac:macro")
+}
 
-#[derive(Default)]
 pub struct StructuredMacroHandler {
     macro_specific_handler: Option<Box<dyn TagHandler>>,
-    jira_server_map: JiraServerMap,
+    context: MacroContext,
 }
 
 impl StructuredMacroHandler {
-    pub fn with_jira_server_map(jira_server_map: JiraServerMap) -> Self {
+    pub fn with_context(context: MacroContext) -> Self {
         Self {
-            jira_server_map,
+            context,
             macro_specific_handler: Default::default(),
         }
     }
@@ -38,18 +149,80 @@ impl StructuredMacroHandler {
 
 impl TagHandler for StructuredMacroHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
-        debug_assert_eq!(get_tag_name(tag).unwrap(), "ac:structured-macro");
+        debug_assert!(is_macro(tag));
 
         self.macro_specific_handler = match get_tag_attr(tag, "ac:name").as_deref() {
-            Some("info") => Some(Box::new(info::InfoMacroHandler::new())),
-            Some("tip") => Some(Box::new(info::InfoMacroHandler::new())),
-            Some("note") => Some(Box::new(info::InfoMacroHandler::new())),
-            Some("warning") => Some(Box::new(info::InfoMacroHandler::new())),
-            Some("jira") => Some(Box::new(jira::JiraMacroHandler::with_servers(
-                self.jira_server_map.clone(),
+            Some("info" | "tip" | "note" | "warning" | "panel") => Some(Box::new(
+                info::InfoMacroHandler::with_context(self.context.clone()),
+            )),
+            Some("jira") => Some(Box::new(jira::JiraMacroHandler::with_context(
+                self.context.clone(),
+            ))),
+            Some("code") => Some(Box::new(code::CodeMacroHandler::new(self.context.flavor))),
+            Some("noformat") => Some(Box::new(code::CodeMacroHandler::noformat(
+                self.context.flavor,
+            ))),
+            Some("excerpt") => Some(Box::new(excerpt::ExcerptMacroHandler::new())),
+            Some("anchor") => Some(Box::new(anchor::AnchorMacroHandler::new(
+                self.context.flavor,
+            ))),
+            Some("html") => Some(Box::new(passthrough::PassthroughMacroHandler::html(
+                self.context.raw_html_policy,
+            ))),
+            Some("markdown") => Some(Box::new(passthrough::PassthroughMacroHandler::markdown())),
+            Some("expand") => Some(Box::new(expand::ExpandMacroHandler::with_context(
+                self.context.clone(),
+            ))),
+            Some("status") => Some(Box::new(status::StatusMacroHandler::with_style(
+                self.context.status_style,
+            ))),
+            Some("details") => Some(Box::new(details::DetailsMacroHandler::with_context(
+                self.context.clone(),
+            ))),
+            Some("attachments") => Some(Box::new(
+                attachments::AttachmentsMacroHandler::with_context(self.context.clone()),
+            )),
+            Some("section") => Some(Box::new(section::SectionMacroHandler::with_context(
+                self.context.clone(),
+            ))),
+            Some("column") => Some(Box::new(section::ColumnMacroHandler::with_context(
+                self.context.clone(),
+            ))),
+            Some("mathblock" | "latex") => Some(Box::new(math::MathMacroHandler::block())),
+            Some("mathinline") => Some(Box::new(math::MathMacroHandler::inline())),
+            Some("plantuml") => Some(Box::new(diagram::DiagramSourceMacroHandler::new(
+                self.context.flavor,
+                "plantuml",
+            ))),
+            Some("mermaid-macro" | "mermaid" | "mermaid-cloud") => Some(Box::new(
+                diagram::DiagramSourceMacroHandler::new(self.context.flavor, "mermaid"),
+            )),
+            Some("drawio") => Some(Box::new(diagram::AttachmentDiagramMacroHandler::new(
+                self.context.clone(),
+                diagram::DiagramEditor::DrawIo,
+            ))),
+            Some("gliffy") => Some(Box::new(diagram::AttachmentDiagramMacroHandler::new(
+                self.context.clone(),
+                diagram::DiagramEditor::Gliffy,
+            ))),
+            Some("gallery") => Some(Box::new(gallery::GalleryMacroHandler::with_context(
+                self.context.clone(),
+            ))),
+            Some("view-file" | "viewpdf" | "viewdoc" | "viewppt" | "viewxls") => Some(Box::new(
+                viewfile::ViewFileMacroHandler::with_context(self.context.clone()),
+            )),
+            Some("multimedia") => Some(Box::new(multimedia::MultimediaMacroHandler::with_context(
+                self.context.clone(),
+            ))),
+            Some("widget") => Some(Box::new(multimedia::WidgetMacroHandler::with_context(
+                self.context.clone(),
             ))),
-            Some("expand") => Some(Box::new(expand::ExpandMacroHandler::new())),
-            Some("status") => Some(Box::new(status::StatusMacroHandler::new())),
+            Some("detailssummary") => Some(Box::new(
+                detailssummary::DetailsSummaryMacroHandler::with_context(self.context.clone()),
+            )),
+            Some("contentbylabel") => Some(Box::new(
+                contentbylabel::ContentByLabelMacroHandler::with_context(self.context.clone()),
+            )),
             _ => None,
         };
 
@@ -72,19 +245,17 @@ impl TagHandler for StructuredMacroHandler {
 }
 
 pub struct StructuredMacroHandlerFactory {
-    jira_server_map: JiraServerMap,
+    document: DocumentContext,
 }
 
 impl StructuredMacroHandlerFactory {
-    pub fn with_jira_server_map(jira_server_map: JiraServerMap) -> Self {
-        Self { jira_server_map }
+    pub(crate) fn new(document: DocumentContext) -> Self {
+        Self { document }
     }
 }
 
 impl TagHandlerFactory for StructuredMacroHandlerFactory {
     fn instantiate(&self) -> Box<dyn TagHandler> {
-        Box::new(StructuredMacroHandler::with_jira_server_map(
-            self.jira_server_map.clone(),
-        ))
+        Box::new(StructuredMacroHandler::with_context(self.document.get()))
     }
 }
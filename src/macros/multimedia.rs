@@ -0,0 +1,279 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::MacroContext;
+use super::viewfile::attachment_parameter;
+use crate::document::{Inline, encode_url};
+use crate::util::{find_child, get_macro_parameter, get_macro_parameter_element, html_escape};
+use html2md::{Handle, StructuredPrinter, TagHandler, common::get_tag_attr};
+
+/// Formats the optional `width` and `height` macro parameters as HTML attributes.
+fn size_attributes(tag: &Handle) -> String {
+    ["width", "height"]
+        .iter()
+        .filter_map(|name| {
+            get_macro_parameter(tag, name)
+                .map(|value| format!(r#" {name}="{value}""#, value = html_escape(&value)))
+        })
+        .collect()
+}
+
+/// Handler for the `multimedia` macro, which embeds a video or audio attachment.
+pub struct MultimediaMacroHandler {
+    context: MacroContext,
+}
+
+impl MultimediaMacroHandler {
+    pub fn with_context(context: MacroContext) -> Self {
+        Self { context }
+    }
+}
+
+impl TagHandler for MultimediaMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let Some(filename) = attachment_parameter(tag) else {
+            return;
+        };
+        let text = Inline::Text(format!("🎬 {filename}"));
+        let Some(url) = self.context.attachments.url(&filename) else {
+            printer.append_str(&text.to_markdown());
+            return;
+        };
+
+        if self.context.flavor.allows_embeds() {
+            let autoplay = if get_macro_parameter(tag, "autostart").is_some_and(|v| v == "true") {
+                " autoplay"
+            } else {
+                ""
+            };
+            printer.insert_newline();
+            printer.insert_newline();
+            printer.append_str(&format!(
+                r#"<video src="{src}" controls{size}{autoplay}></video>"#,
+                src = html_escape(&url),
+                size = size_attributes(tag),
+            ));
+            printer.insert_newline();
+            printer.insert_newline();
+        } else {
+            let link = Inline::Link {
+                content: vec![text],
+                url,
+            };
+            printer.append_str(&link.to_markdown());
+        }
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum VideoService {
+    YouTube(String),
+    Vimeo(String),
+}
+
+impl VideoService {
+    fn from_url(url: &str) -> Option<Self> {
+        let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let without_www = without_scheme
+            .strip_prefix("www.")
+            .or_else(|| without_scheme.strip_prefix("m."))
+            .unwrap_or(without_scheme);
+        let (host, path) = without_www.split_once('/')?;
+        let video_id = |s: &str| {
+            let id: String = s
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                .collect();
+            (!id.is_empty()).then_some(id)
+        };
+
+        match host {
+            "youtube.com" => {
+                if let Some(id) = path
+                    .strip_prefix("embed/")
+                    .or_else(|| path.strip_prefix("shorts/"))
+                {
+                    return video_id(id).map(Self::YouTube);
+                }
+                let (_, query) = path.split_once('?')?;
+                query
+                    .split('&')
+                    .find_map(|param| param.strip_prefix("v="))
+                    .and_then(video_id)
+                    .map(Self::YouTube)
+            }
+            "youtu.be" => video_id(path).map(Self::YouTube),
+            "vimeo.com" => video_id(path)
+                .filter(|id| id.chars().all(|c| c.is_ascii_digit()))
+                .map(Self::Vimeo),
+            "player.vimeo.com" => path
+                .strip_prefix("video/")
+                .and_then(video_id)
+                .map(Self::Vimeo),
+            _ => None,
+        }
+    }
+
+    fn embed_url(&self) -> String {
+        match self {
+            Self::YouTube(id) => format!("https://www.youtube.com/embed/{id}"),
+            Self::Vimeo(id) => format!("https://player.vimeo.com/video/{id}"),
+        }
+    }
+
+    fn thumbnail_url(&self) -> Option<String> {
+        match self {
+            Self::YouTube(id) => Some(format!("https://img.youtube.com/vi/{id}/hqdefault.jpg")),
+            Self::Vimeo(_) => None,
+        }
+    }
+}
+
+/// Handler for the `widget` (Widget Connector) macro, which embeds external media like YouTube or
+/// Vimeo videos.
+pub struct WidgetMacroHandler {
+    context: MacroContext,
+}
+
+impl WidgetMacroHandler {
+    pub fn with_context(context: MacroContext) -> Self {
+        Self { context }
+    }
+}
+
+impl TagHandler for WidgetMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let Some(url) = get_macro_parameter_element(tag, "url")
+            .and_then(|param| find_child(&param, "ri:url"))
+            .and_then(|url| get_tag_attr(&url, "ri:value"))
+            .or_else(|| get_macro_parameter(tag, "url"))
+        else {
+            return;
+        };
+
+        let service = VideoService::from_url(&url);
+        match service {
+            Some(service) if self.context.flavor.allows_embeds() => {
+                printer.insert_newline();
+                printer.insert_newline();
+                printer.append_str(&format!(
+                    r#"<iframe src="{src}"{size} frameborder="0" allowfullscreen></iframe>"#,
+                    src = html_escape(&service.embed_url()),
+                    size = size_attributes(tag),
+                ));
+                printer.insert_newline();
+                printer.insert_newline();
+            }
+            Some(service) if service.thumbnail_url().is_some() => {
+                let thumbnail = service.thumbnail_url().unwrap_or_default();
+                printer.append_str(&format!(
+                    "[![Video]({thumbnail})]({url})",
+                    thumbnail = encode_url(&thumbnail),
+                    url = encode_url(&url)
+                ));
+            }
+            _ => {
+                let link = Inline::Link {
+                    content: vec![Inline::Text(url.clone())],
+                    url,
+                };
+                printer.append_str(&link.to_markdown());
+            }
+        }
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::VideoService;
+    use crate::{
+        Attachment, ConfluencePageId, InMemoryAttachmentProvider, MarkdownFlavor, ParseOptions,
+        parse_confluence,
+    };
+    use std::sync::Arc;
+
+    const YOUTUBE_WIDGET: &str = r#"
+<ac:structured-macro ac:name="widget">
+  <ac:parameter ac:name="url"><ri:url ri:value="https://www.youtube.com/watch?v=dQw4w9WgXcQ&amp;t=42" /></ac:parameter>
+  <ac:parameter ac:name="width">640</ac:parameter>
+</ac:structured-macro>"#;
+
+    #[test]
+    fn test_video_service() {
+        assert_eq!(
+            VideoService::from_url("https://youtu.be/dQw4w9WgXcQ?t=1"),
+            Some(VideoService::YouTube("dQw4w9WgXcQ".to_string()))
+        );
+        assert_eq!(
+            VideoService::from_url("https://vimeo.com/76979871"),
+            Some(VideoService::Vimeo("76979871".to_string()))
+        );
+        assert_eq!(VideoService::from_url("https://example.com/video"), None);
+    }
+
+    #[test]
+    fn test_widget() {
+        let md = parse_confluence(YOUTUBE_WIDGET, &ParseOptions::default());
+        assert_eq!(
+            md,
+            "[![Video](https://img.youtube.com/vi/dQw4w9WgXcQ/hqdefault.jpg)](https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42)"
+        );
+
+        let options = ParseOptions::default().with_flavor(MarkdownFlavor::CommonMark);
+        assert_eq!(
+            parse_confluence(YOUTUBE_WIDGET, &options),
+            r#"<iframe src="https://www.youtube.com/embed/dQw4w9WgXcQ" width="640" frameborder="0" allowfullscreen></iframe>"#
+        );
+    }
+
+    #[test]
+    fn test_multimedia() {
+        let page_id = ConfluencePageId::from(1);
+        let provider = InMemoryAttachmentProvider::default().with_attachment(
+            page_id.clone(),
+            Attachment::new("demo.mp4").with_local_path("assets/demo.mp4"),
+        );
+        let html = r#"
+<ac:structured-macro ac:name="multimedia">
+  <ac:parameter ac:name="name"><ri:attachment ri:filename="demo.mp4" /></ac:parameter>
+  <ac:parameter ac:name="autostart">true</ac:parameter>
+</ac:structured-macro>"#;
+        let options = ParseOptions::default()
+            .with_default_page_id(page_id)
+            .with_attachment_provider(Arc::new(provider));
+        assert_eq!(
+            parse_confluence(html, &options),
+            "[🎬 demo.mp4](assets/demo.mp4)"
+        );
+
+        let options = options.with_flavor(MarkdownFlavor::Pandoc);
+        assert_eq!(
+            parse_confluence(html, &options),
+            r#"<video src="assets/demo.mp4" controls autoplay></video>"#
+        );
+    }
+}
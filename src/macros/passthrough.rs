@@ -0,0 +1,311 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::flavor::RawHtmlPolicy;
+use crate::util::{get_plain_text_body, html_escape};
+use html2md::{Handle, NodeData, StructuredPrinter, TagHandler};
+use html5ever::tendril::TendrilSink;
+use html5ever::{ParseOpts, QualName, local_name, ns, parse_fragment};
+use markup5ever_rcdom::RcDom;
+
+/// Elements that are kept by the sanitizer. All other elements are replaced by their content.
+const ALLOWED_ELEMENTS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "details",
+    "dfn",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "samp",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "tr",
+    "u",
+    "ul",
+    "var",
+];
+
+/// Elements that are removed together with their content.
+const REMOVED_ELEMENTS: &[&str] = &[
+    "applet", "embed", "frame", "frameset", "iframe", "math", "noembed", "noframes", "noscript",
+    "object", "script", "style", "svg", "template", "title",
+];
+
+/// Attributes that are kept on allowed elements.
+const ALLOWED_ATTRIBUTES: &[&str] = &[
+    "align", "alt", "border", "cite", "class", "colspan", "datetime", "dir", "height", "href",
+    "lang", "open", "rowspan", "scope", "span", "src", "start", "title", "valign", "width",
+];
+
+/// Attributes containing URLs, which are replaced by `#` unless they use a safe scheme.
+const URL_ATTRIBUTES: &[&str] = &["cite", "href", "src"];
+
+const SAFE_URL_SCHEMES: &[&str] = &["http", "https", "mailto", "tel", "ftp"];
+
+const VOID_ELEMENTS: &[&str] = &["br", "col", "hr", "img"];
+
+/// Returns true if the URL is relative or uses a safe scheme.
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters in the scheme, e.g. `java\tscript:`.
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(index) if url[index..].starts_with(':') => SAFE_URL_SCHEMES
+            .iter()
+            .any(|scheme| url[..index].eq_ignore_ascii_case(scheme)),
+        _ => true,
+    }
+}
+
+fn sanitize_node(node: &Handle, output: &mut String) {
+    match node.data {
+        NodeData::Text { ref contents } => {
+            let text = contents.borrow();
+            output.push_str(
+                &text
+                    .replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;"),
+            );
+        }
+        NodeData::Element {
+            ref name,
+            ref attrs,
+            ..
+        } => {
+            let tag_name = name.local.as_ref();
+            if REMOVED_ELEMENTS.contains(&tag_name) {
+                return;
+            }
+            let allowed = name.ns == ns!(html) && ALLOWED_ELEMENTS.contains(&tag_name);
+            if allowed {
+                output.push('<');
+                output.push_str(tag_name);
+                for attribute in attrs.borrow().iter() {
+                    let attribute_name = attribute.name.local.as_ref();
+                    if !ALLOWED_ATTRIBUTES.contains(&attribute_name) {
+                        continue;
+                    }
+                    let value = if URL_ATTRIBUTES.contains(&attribute_name)
+                        && !is_safe_url(&attribute.value)
+                    {
+                        "#"
+                    } else {
+                        &attribute.value
+                    };
+                    output.push_str(&format!(
+                        r#" {attribute_name}="{value}""#,
+                        value = html_escape(value)
+                    ));
+                }
+                output.push('>');
+                if VOID_ELEMENTS.contains(&tag_name) {
+                    return;
+                }
+            }
+            for child in node.children.borrow().iter() {
+                sanitize_node(child, output);
+            }
+            if allowed {
+                output.push_str(&format!("</{tag_name}>"));
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Parses the HTML like a browser would and keeps only allowlisted elements and attributes and
+/// URLs with a safe scheme.
+fn sanitize_html(html: &str) -> String {
+    let dom = parse_fragment(
+        RcDom::default(),
+        ParseOpts::default(),
+        QualName::new(None, ns!(html), local_name!("body")),
+        Vec::new(),
+        false,
+    )
+    .one(html);
+    let mut output = String::new();
+    // The fragment is parsed into an `html` element below the document.
+    for root in dom.document.children.borrow().iter() {
+        for child in root.children.borrow().iter() {
+            sanitize_node(child, &mut output);
+        }
+    }
+    output
+}
+
+/// Handler for the `html` and `markdown` macros, whose plain text body is passed through
+/// verbatim (apart from the HTML policy).
+pub struct PassthroughMacroHandler {
+    html_policy: Option<RawHtmlPolicy>,
+}
+
+impl PassthroughMacroHandler {
+    pub fn html(policy: RawHtmlPolicy) -> Self {
+        Self {
+            html_policy: Some(policy),
+        }
+    }
+
+    pub fn markdown() -> Self {
+        Self { html_policy: None }
+    }
+}
+
+impl TagHandler for PassthroughMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let Some(body) = get_plain_text_body(tag) else {
+            return;
+        };
+
+        let body = match self.html_policy {
+            None | Some(RawHtmlPolicy::Keep) => body,
+            Some(RawHtmlPolicy::Sanitize) => sanitize_html(&body),
+            Some(RawHtmlPolicy::Strip) => html2md::parse_html(&sanitize_html(&body)),
+        };
+        printer.insert_newline();
+        printer.insert_newline();
+        printer.append_str(body.trim());
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::sanitize_html;
+    use crate::{MarkdownFlavor, ParseOptions, RawHtmlPolicy, parse_confluence};
+
+    const HTML: &str = r#"<ac:structured-macro ac:name="html"><ac:plain-text-body><![CDATA[<div class="banner" onclick="track()">
+  <a href="javascript:alert(1)">Hello</a> <b>world</b>
+</div>
+<script>alert("x")</script>]]></ac:plain-text-body></ac:structured-macro>"#;
+
+    #[test]
+    fn test_html_policies() {
+        assert_eq!(
+            parse_confluence(HTML, &ParseOptions::default()),
+            "<div class=\"banner\">\n  <a href=\"#\">Hello</a> <b>world</b>\n</div>"
+        );
+        assert_eq!(
+            parse_confluence(
+                HTML,
+                &ParseOptions::default().with_raw_html_policy(RawHtmlPolicy::Keep)
+            ),
+            "<div class=\"banner\" onclick=\"track()\">\n  <a href=\"javascript:alert(1)\">Hello</a> <b>world</b>\n</div>\n<script>alert(\"x\")</script>"
+        );
+        assert_eq!(
+            parse_confluence(
+                HTML,
+                &ParseOptions::default().with_flavor(MarkdownFlavor::Plain)
+            ),
+            "[Hello](#) **world**"
+        );
+    }
+
+    #[test]
+    fn test_sanitizer_bypasses() {
+        for (html, sanitized) in [
+            ("<img/onerror=alert(1) src=x>", r#"<img src="x">"#),
+            ("<svg/onload=alert(1)><circle/></svg>ok", "ok"),
+            (
+                "<scr<script></script>ipt>alert(1)</script>",
+                "ipt&gt;alert(1)",
+            ),
+            (
+                r#"<a href="&#106;avascript:alert(1)">a</a>"#,
+                r##"<a href="#">a</a>"##,
+            ),
+            (
+                "<a href=\"java\tscript:alert(1)\">a</a>",
+                r##"<a href="#">a</a>"##,
+            ),
+            (
+                r#"<a href="data:text/html,x">a</a><img src="VBScript:x">"#,
+                r##"<a href="#">a</a><img src="#">"##,
+            ),
+            (
+                r#"<a href="https://example.com/?a=1&amp;b=2" style="x" id="y">ok</a>"#,
+                r#"<a href="https://example.com/?a=1&amp;b=2">ok</a>"#,
+            ),
+            ("<form><input value=x>text</form>", "text"),
+        ] {
+            assert_eq!(sanitize_html(html), sanitized, "{html}");
+        }
+    }
+
+    #[test]
+    fn test_markdown() {
+        crate::markdown_assert_eq!(
+            r#"<ac:structured-macro ac:name="markdown"><ac:plain-text-body><![CDATA[# Title
+
+* one
+* two]]></ac:plain-text-body></ac:structured-macro>"#,
+            "# Title\n\n* one\n* two"
+        );
+    }
+}
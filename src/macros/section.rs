@@ -0,0 +1,155 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::MacroContext;
+use super::is_macro;
+use crate::util::{child_elements, find_child, get_macro_parameter, get_parent};
+use html2md::{Handle, StructuredPrinter, TagHandler, common::get_tag_attr};
+use std::rc::Rc;
+
+/// Returns the `column` macros in the body of a `section` macro.
+fn section_columns(body: &Handle) -> Vec<Handle> {
+    child_elements(body, |child| {
+        is_macro(child) && get_tag_attr(child, "ac:name").is_some_and(|name| name == "column")
+    })
+}
+
+/// Handler for the `section` macro. Like layout sections, a section with a single column is
+/// rendered as if there was no section.
+pub struct SectionMacroHandler {
+    context: MacroContext,
+    opened: bool,
+}
+
+impl SectionMacroHandler {
+    pub fn with_context(context: MacroContext) -> Self {
+        Self {
+            context,
+            opened: false,
+        }
+    }
+}
+
+impl TagHandler for SectionMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        self.opened = find_child(tag, "ac:rich-text-body")
+            .is_some_and(|body| section_columns(&body).len() > 1);
+        if self.opened {
+            self.context
+                .layout
+                .open_section(printer, &self.context.metadata);
+        }
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if self.opened {
+            self.context.layout.close_section(printer);
+        }
+    }
+}
+
+/// Handler for the `column` macro inside of a `section` macro.
+pub struct ColumnMacroHandler {
+    context: MacroContext,
+    opened: bool,
+}
+
+impl ColumnMacroHandler {
+    pub fn with_context(context: MacroContext) -> Self {
+        Self {
+            context,
+            opened: false,
+        }
+    }
+}
+
+impl TagHandler for ColumnMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let columns = get_parent(tag)
+            .map(|body| section_columns(&body))
+            .unwrap_or_default();
+        self.opened = columns.len() > 1;
+        if self.opened {
+            let index = columns
+                .iter()
+                .position(|column| Rc::ptr_eq(column, tag))
+                .unwrap_or_default();
+            let width = get_macro_parameter(tag, "width");
+            self.context
+                .layout
+                .open_cell(printer, &self.context.metadata, index, width.as_deref());
+        }
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if self.opened {
+            self.context.layout.close_cell(printer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{LayoutStyle, ParseOptions, parse_confluence, parse_confluence_page};
+
+    const SECTION: &str = r#"
+<ac:structured-macro ac:name="section">
+  <ac:rich-text-body>
+    <ac:structured-macro ac:name="column">
+      <ac:parameter ac:name="width">30%</ac:parameter>
+      <ac:rich-text-body><p>Navigation</p></ac:rich-text-body>
+    </ac:structured-macro>
+    <ac:structured-macro ac:name="column">
+      <ac:rich-text-body><p>Content</p></ac:rich-text-body>
+    </ac:structured-macro>
+  </ac:rich-text-body>
+</ac:structured-macro>"#;
+
+    #[test]
+    fn test_section_linear() {
+        assert_eq!(
+            parse_confluence(SECTION, &ParseOptions::default()),
+            "Navigation\n\n---\n\nContent"
+        );
+        let page = parse_confluence_page(SECTION, &ParseOptions::default());
+        assert_eq!(
+            page.metadata().column_widths(),
+            [vec![Some("30%".to_string()), None]]
+        );
+    }
+
+    #[test]
+    fn test_section_grid() {
+        let options = ParseOptions::default().with_layout_style(LayoutStyle::Grid);
+        assert_eq!(
+            parse_confluence(SECTION, &options),
+            r#"<div style="display: flex; gap: 1em">
+
+<div style="flex: 0 0 30%">
+
+Navigation
+
+</div>
+
+<div style="flex: 1">
+
+Content
+
+</div>
+
+</div>"#
+        );
+    }
+}
@@ -13,61 +13,281 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::util::{get_tag_name, get_text_content};
-use html2md::{Handle, StructuredPrinter, TagHandler, common::get_tag_attr};
+use crate::document::Inline;
+use crate::flavor::MarkdownFlavor;
+use crate::util::{get_macro_parameter, html_escape};
+use html2md::{Handle, StructuredPrinter, TagHandler};
+use std::str::FromStr;
 
-pub struct StatusMacroHandler;
+/// How status lozenges are rendered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StatusStyle {
+    /// The title prefixed with a coloured circle emoji, e.g. `🟢 On track`.
+    Emoji,
+    /// An HTML `<span>` with inline colour styles.
+    HtmlBadge,
+    /// A shields.io badge image.
+    ShieldsBadge,
+    /// The title in bold.
+    Bold,
+    /// The title as inline code.
+    Code,
+    /// Just the title.
+    #[default]
+    Text,
+}
+
+impl StatusStyle {
+    pub fn default_for(flavor: MarkdownFlavor) -> Self {
+        match flavor {
+            MarkdownFlavor::Github | MarkdownFlavor::CommonMark => Self::Text,
+            MarkdownFlavor::Pandoc => Self::HtmlBadge,
+            MarkdownFlavor::Plain => Self::Bold,
+        }
+    }
+}
+
+impl FromStr for StatusStyle {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "emoji" => Ok(Self::Emoji),
+            "html" => Ok(Self::HtmlBadge),
+            "shields" => Ok(Self::ShieldsBadge),
+            "bold" => Ok(Self::Bold),
+            "code" => Ok(Self::Code),
+            "text" => Ok(Self::Text),
+            _ => Err("invalid status style"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StatusColour {
+    #[default]
+    Grey,
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl StatusColour {
+    fn as_emoji(&self) -> &'static str {
+        match self {
+            Self::Grey => "⚪",
+            Self::Red => "🔴",
+            Self::Yellow => "🟡",
+            Self::Green => "🟢",
+            Self::Blue => "🔵",
+            Self::Purple => "🟣",
+        }
+    }
+
+    /// Foreground and background colours, taken from the Confluence lozenge styles.
+    fn as_html_colours(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Grey => ("#42526e", "#dfe1e6"),
+            Self::Red => ("#bf2600", "#ffebe6"),
+            Self::Yellow => ("#172b4d", "#fff0b3"),
+            Self::Green => ("#006644", "#e3fcef"),
+            Self::Blue => ("#0747a6", "#deebff"),
+            Self::Purple => ("#403294", "#eae6ff"),
+        }
+    }
+
+    fn as_shields_colour(&self) -> &'static str {
+        match self {
+            Self::Grey => "lightgrey",
+            Self::Red => "red",
+            Self::Yellow => "yellow",
+            Self::Green => "green",
+            Self::Blue => "blue",
+            Self::Purple => "blueviolet",
+        }
+    }
+}
+
+impl FromStr for StatusColour {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "grey" | "gray" | "neutral" => Ok(Self::Grey),
+            "red" => Ok(Self::Red),
+            "yellow" => Ok(Self::Yellow),
+            "green" => Ok(Self::Green),
+            "blue" => Ok(Self::Blue),
+            "purple" => Ok(Self::Purple),
+            _ => Err("invalid colour"),
+        }
+    }
+}
+
+/// Escape a text for use in a shields.io badge path segment.
+fn shields_escape(s: &str) -> String {
+    let s = s.replace('-', "--").replace('_', "__").replace(' ', "_");
+    urlencoding::encode(&s).into_owned()
+}
+
+pub fn render_status(
+    title: &str,
+    colour: StatusColour,
+    subtle: bool,
+    style: StatusStyle,
+) -> String {
+    let text = Inline::Text(title.to_string());
+    match style {
+        StatusStyle::Emoji => format!(
+            "{emoji} {title}",
+            emoji = colour.as_emoji(),
+            title = text.to_markdown()
+        ),
+        StatusStyle::HtmlBadge => {
+            let (foreground, background) = colour.as_html_colours();
+            let style = if subtle {
+                format!(
+                    "color: {foreground}; border: 1px solid {foreground}; border-radius: 3px; padding: 0 4px;"
+                )
+            } else {
+                format!(
+                    "color: {foreground}; background-color: {background}; border-radius: 3px; padding: 0 4px;"
+                )
+            };
+            format!(
+                r#"<span style="{style}">{title}</span>"#,
+                title = html_escape(&title.to_uppercase())
+            )
+        }
+        StatusStyle::ShieldsBadge => format!(
+            "![{title}](https://img.shields.io/badge/{label}-{colour})",
+            title = text.to_markdown(),
+            label = shields_escape(title),
+            colour = colour.as_shields_colour(),
+        ),
+        StatusStyle::Bold => Inline::Strong(vec![text]).to_markdown(),
+        StatusStyle::Code => Inline::Code(title.to_string()).to_markdown(),
+        StatusStyle::Text => text.to_markdown(),
+    }
+}
+
+pub struct StatusMacroHandler {
+    style: StatusStyle,
+}
 
 impl StatusMacroHandler {
-    pub fn new() -> Self {
-        Self {}
+    pub fn with_style(style: StatusStyle) -> Self {
+        Self { style }
     }
 }
 
 impl TagHandler for StatusMacroHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
-        let children = tag.children.borrow();
-        let title = children
-            .iter()
-            .filter_map(|child| {
-                if get_tag_name(child).is_some_and(|name| name == "ac:parameter") {
-                    get_tag_attr(child, "ac:name").map(|value| (value, child))
-                } else {
-                    None
-                }
-            })
-            .find_map(|(param_name, param)| {
-                if &param_name == "title" {
-                    let s = get_text_content(param);
-                    if s.is_empty() { None } else { Some(s) }
-                } else {
-                    None
-                }
-            });
-
-        if let Some(title) = title {
-            printer.append_str(&title);
-        }
+        let Some(title) = get_macro_parameter(tag, "title") else {
+            return;
+        };
+
+        let colour = get_macro_parameter(tag, "colour")
+            .and_then(|colour| StatusColour::from_str(&colour).ok())
+            .unwrap_or_default();
+        let subtle = get_macro_parameter(tag, "subtle").is_some_and(|value| value == "true");
+
+        printer.append_str(&render_status(&title, colour, subtle, self.style));
     }
 
     fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::markdown_assert_eq;
+    use crate::{MarkdownFlavor, ParseOptions, StatusStyle, parse_confluence};
 
-    #[test]
-    fn test() {
-        markdown_assert_eq!(
-            r#"
+    const STATUS: &str = r#"
  <ac:structured-macro ac:name="status">
     <ac:parameter ac:name="colour">Green</ac:parameter>
     <ac:parameter ac:name="title">On track</ac:parameter>
     <ac:parameter ac:name="subtle">true</ac:parameter>
 </ac:structured-macro>
+"#;
+
+    #[test]
+    fn test() {
+        markdown_assert_eq!(STATUS, "On track");
+    }
+
+    #[test]
+    fn test_styles() {
+        let render =
+            |style| parse_confluence(STATUS, &ParseOptions::default().with_status_style(style));
+        assert_eq!(render(StatusStyle::Emoji), "🟢 On track");
+        assert_eq!(render(StatusStyle::Text), "On track");
+        assert_eq!(render(StatusStyle::Bold), "**On track**");
+        assert_eq!(render(StatusStyle::Code), "`On track`");
+        assert_eq!(
+            render(StatusStyle::ShieldsBadge),
+            "![On track](https://img.shields.io/badge/On_track-green)"
+        );
+        assert_eq!(
+            render(StatusStyle::HtmlBadge),
+            r#"<span style="color: #006644; border: 1px solid #006644; border-radius: 3px; padding: 0 4px;">ON TRACK</span>"#
+        );
+    }
+
+    #[test]
+    fn test_escaping() {
+        let status = r#"<ac:structured-macro ac:name="status"><ac:parameter ac:name="title">&lt;b&gt;a_b`]&lt;/b&gt;</ac:parameter></ac:structured-macro>"#;
+        let render =
+            |style| parse_confluence(status, &ParseOptions::default().with_status_style(style));
+        assert_eq!(render(StatusStyle::Bold), r"**\<b>a\_b\`\]\</b>**");
+        assert_eq!(render(StatusStyle::Code), "``<b>a_b`]</b>``");
+        assert_eq!(
+            render(StatusStyle::ShieldsBadge),
+            r"![\<b>a\_b\`\]\</b>](https://img.shields.io/badge/%3Cb%3Ea__b%60%5D%3C%2Fb%3E-lightgrey)"
+        );
+        assert_eq!(
+            render(StatusStyle::HtmlBadge),
+            r#"<span style="color: #42526e; background-color: #dfe1e6; border-radius: 3px; padding: 0 4px;">&lt;B&gt;A_B`]&lt;/B&gt;</span>"#
+        );
+    }
+
+    #[test]
+    fn test_flavor_default() {
+        let md = parse_confluence(
+            STATUS,
+            &ParseOptions::default().with_flavor(MarkdownFlavor::Plain),
+        );
+        assert_eq!(md, "**On track**");
+    }
+
+    #[test]
+    fn test_legacy_macro_with_default_parameter() {
+        markdown_assert_eq!(
+            r#"<ac:macro ac:name="status"><ac:default-parameter>Done</ac:default-parameter><ac:parameter ac:name="colour">Green</ac:parameter></ac:macro> <ac:structured-macro ac:name="status"><ac:parameter ac:name="title">Open</ac:parameter></ac:structured-macro>"#,
+            "Done Open"
+        );
+    }
+
+    #[test]
+    fn test_adf_status() {
+        let md = parse_confluence(
+            r#"
+<ac:adf-extension>
+  <ac:adf-node type="status">
+    <ac:adf-attribute key="text">Blocked</ac:adf-attribute>
+    <ac:adf-attribute key="color">red</ac:adf-attribute>
+  </ac:adf-node>
+  <ac:adf-fallback><span>Blocked</span></ac:adf-fallback>
+</ac:adf-extension>
 "#,
-            "On track"
+            &ParseOptions::default().with_status_style(StatusStyle::Emoji),
         );
+        assert_eq!(md, "🔴 Blocked");
     }
 }
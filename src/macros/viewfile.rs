@@ -0,0 +1,136 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::MacroContext;
+use crate::attachment::Attachment;
+use crate::document::Inline;
+use crate::util::{find_child, get_macro_parameter, get_macro_parameter_element};
+use html2md::{Handle, StructuredPrinter, TagHandler, common::get_tag_attr};
+
+/// Returns the filename of the attachment referenced by the `name` parameter.
+pub fn attachment_parameter(tag: &Handle) -> Option<String> {
+    get_macro_parameter_element(tag, "name")
+        .and_then(|param| find_child(&param, "ri:attachment"))
+        .and_then(|attachment| get_tag_attr(&attachment, "ri:filename"))
+        .or_else(|| get_macro_parameter(tag, "name"))
+}
+
+fn file_icon(attachment: &Attachment) -> &'static str {
+    match attachment.extension().as_deref() {
+        Some("pdf") => "📕",
+        Some("doc" | "docx" | "odt" | "rtf" | "txt") => "📝",
+        Some("ppt" | "pptx" | "odp") => "📊",
+        Some("xls" | "xlsx" | "ods" | "csv") => "📈",
+        Some("zip" | "tar" | "gz" | "7z") => "📦",
+        _ if attachment.is_image() => "🖼️",
+        _ => "📎",
+    }
+}
+
+/// Handler for the `view-file` macro and the legacy `viewpdf`, `viewdoc`, `viewppt` and `viewxls`
+/// macros, which are rendered as download links.
+pub struct ViewFileMacroHandler {
+    context: MacroContext,
+}
+
+impl ViewFileMacroHandler {
+    pub fn with_context(context: MacroContext) -> Self {
+        Self { context }
+    }
+}
+
+impl TagHandler for ViewFileMacroHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let Some(filename) = attachment_parameter(tag) else {
+            return;
+        };
+
+        let attachment = Attachment::new(&filename);
+        let icon = file_icon(&attachment);
+        let name = Inline::Text(filename.clone());
+        let name = match self.context.attachments.url(&filename) {
+            Some(url) => Inline::Link {
+                content: vec![name],
+                url,
+            },
+            None => name,
+        };
+        printer.append_str(&format!("{icon} {}", name.to_markdown()));
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Attachment, ConfluencePageId, ConfluenceServer, InMemoryAttachmentProvider, ParseOptions,
+        parse_confluence,
+    };
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_view_file() {
+        let options = ParseOptions::default()
+            .with_confluence_server(ConfluenceServer::from_str("https://example.com").unwrap())
+            .with_default_page_id(ConfluencePageId::from(1337));
+        let md = parse_confluence(
+            r#"
+<ac:structured-macro ac:name="view-file">
+  <ac:parameter ac:name="name"><ri:attachment ri:filename="Quarterly Report.pdf" /></ac:parameter>
+  <ac:parameter ac:name="height">250</ac:parameter>
+</ac:structured-macro>"#,
+            &options,
+        );
+        assert_eq!(
+            md,
+            "📕 [Quarterly Report.pdf](https://example.com/download/attachments/1337/Quarterly%20Report.pdf)"
+        );
+    }
+
+    #[test]
+    fn test_viewxls_without_server() {
+        let md = parse_confluence(
+            r#"
+<ac:structured-macro ac:name="viewxls">
+  <ac:parameter ac:name="name"><ri:attachment ri:filename="budget.xlsx" /></ac:parameter>
+</ac:structured-macro>"#,
+            &ParseOptions::default(),
+        );
+        assert_eq!(md, "📈 budget.xlsx");
+    }
+
+    #[test]
+    fn test_local_path_is_escaped() {
+        let page_id = ConfluencePageId::from(1);
+        let provider = InMemoryAttachmentProvider::default().with_attachment(
+            page_id.clone(),
+            Attachment::new("Q1_[final].pdf").with_local_path("assets/Q1 (final).pdf"),
+        );
+        let options = ParseOptions::default()
+            .with_default_page_id(page_id)
+            .with_attachment_provider(Arc::new(provider));
+        let md = parse_confluence(
+            r#"<ac:structured-macro ac:name="view-file"><ac:parameter ac:name="name"><ri:attachment ri:filename="Q1_[final].pdf" /></ac:parameter></ac:structured-macro>"#,
+            &options,
+        );
+        assert_eq!(md, "📕 [Q1\\_\\[final\\].pdf](assets/Q1%20%28final%29.pdf)");
+    }
+}
@@ -13,17 +13,169 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use html2md_confluence::{ParseOptions, parse_confluence};
-use std::io::{self, Read};
+use html2md_confluence::{
+    BatchConverter, BatchJob, BatchProgress, Converter, MANIFEST_FILE_NAME, Manifest, ParseMode,
+    ParseOptions,
+};
+use std::ffi::OsString;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-fn main() -> io::Result<()> {
-    let mut buffer = String::new();
-    let mut stdin = io::stdin();
+const USAGE: &str = "\
+Usage: html2md-confluence [--lenient] [-j JOBS] [-o OUTPUT_DIR [--incremental]] [FILE...]
 
-    stdin.read_to_string(&mut buffer)?;
+Converts Confluence storage format to Markdown. Without files, the document is read from stdin
+and written to stdout. Otherwise, all files are converted in parallel and each one is written
+next to its input (or into OUTPUT_DIR) with the extension `.md`.
 
-    let options = ParseOptions::default();
-    print!("{}", parse_confluence(&buffer, &options));
+Options:
+  -j, --jobs JOBS             Number of worker threads (defaults to the number of CPUs)
+  -o, --output-dir DIR        Directory for the converted files
+  -i, --incremental           Only convert files that changed since the last run and remove the
+                              output of files that are no longer given, using a manifest in the
+                              output directory
+  -l, --lenient               Repair malformed input instead of rejecting it, and report each
+                              repair
+  -h, --help                  Print this help";
 
-    Ok(())
+#[derive(Debug, Default)]
+struct Args {
+    jobs: Option<NonZeroUsize>,
+    output_dir: Option<PathBuf>,
+    incremental: bool,
+    lenient: bool,
+    inputs: Vec<PathBuf>,
+}
+
+impl Args {
+    fn parse<I: Iterator<Item = OsString>>(mut args: I) -> Result<Option<Self>, String> {
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.to_str() {
+                Some("-h" | "--help") => return Ok(None),
+                Some("-j" | "--jobs") => {
+                    let jobs = args.next().ok_or("missing value for --jobs")?;
+                    let jobs = jobs
+                        .to_str()
+                        .and_then(|jobs| jobs.parse().ok())
+                        .ok_or("--jobs must be a positive number")?;
+                    parsed.jobs = Some(jobs);
+                }
+                Some("-o" | "--output-dir") => {
+                    let dir = args.next().ok_or("missing value for --output-dir")?;
+                    parsed.output_dir = Some(dir.into());
+                }
+                Some("-i" | "--incremental") => parsed.incremental = true,
+                Some("-l" | "--lenient") => parsed.lenient = true,
+                Some(option) if option.starts_with('-') => {
+                    return Err(format!("unknown option {option}"));
+                }
+                _ => parsed.inputs.push(arg.into()),
+            }
+        }
+        if parsed.incremental && parsed.output_dir.is_none() {
+            return Err("--incremental requires --output-dir".to_string());
+        }
+        Ok(Some(parsed))
+    }
+}
+
+/// Returns the path of the Markdown file for the input file.
+fn output_path(input: &Path, output_dir: Option<&Path>) -> PathBuf {
+    let mut name = input.file_stem().unwrap_or_default().to_os_string();
+    name.push(".md");
+    match output_dir {
+        Some(dir) => dir.join(name),
+        None => input.with_file_name(name),
+    }
+}
+
+fn print_progress(progress: BatchProgress<'_>) {
+    let document = progress.document;
+    let prefix = format!("[{}/{}]", progress.completed, progress.total);
+    match document.error() {
+        None => eprintln!(
+            "{prefix} {} -> {} ({:.2?})",
+            document.input().display(),
+            document.output().display(),
+            document.elapsed()
+        ),
+        Some(error) => eprintln!("{prefix} {} failed: {error}", document.input().display()),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args_os().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("error: {error}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let parse_mode = if args.lenient {
+        ParseMode::Lenient
+    } else {
+        ParseMode::Strict
+    };
+    let converter = Converter::new(ParseOptions::default().with_parse_mode(parse_mode));
+    if args.inputs.is_empty() {
+        return match converter.convert_stream(io::stdin().lock(), io::stdout().lock()) {
+            Ok(metadata) => {
+                for diagnostic in metadata.diagnostics() {
+                    eprintln!("warning: {diagnostic}");
+                }
+                ExitCode::SUCCESS
+            }
+            Err(error) => {
+                eprintln!("error: {error}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let jobs: Vec<_> = args
+        .inputs
+        .iter()
+        .map(|input| BatchJob::new(input, output_path(input, args.output_dir.as_deref())))
+        .collect();
+    let mut batch = BatchConverter::new(converter);
+    if let Some(workers) = args.jobs {
+        batch = batch.with_workers(workers);
+    }
+    let report = match args.output_dir.as_deref().filter(|_| args.incremental) {
+        Some(output_dir) => {
+            let manifest_path = output_dir.join(MANIFEST_FILE_NAME);
+            let mut manifest = match Manifest::load(&manifest_path) {
+                Ok(manifest) => manifest,
+                Err(error) => {
+                    eprintln!("error: failed to load {}: {error}", manifest_path.display());
+                    return ExitCode::FAILURE;
+                }
+            };
+            let (report, changes) = batch.convert_incremental(&jobs, &mut manifest, print_progress);
+            let saved =
+                std::fs::create_dir_all(output_dir).and_then(|()| manifest.save(&manifest_path));
+            if let Err(error) = saved {
+                eprintln!("error: failed to save {}: {error}", manifest_path.display());
+                return ExitCode::FAILURE;
+            }
+            eprint!("\n{}", changes.summary());
+            report
+        }
+        None => batch.convert(&jobs, print_progress),
+    };
+    eprint!("\n{}", report.summary());
+
+    if report.failures().next().is_some() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
 }
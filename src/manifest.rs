@@ -0,0 +1,415 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::ParseOptions;
+use crate::util::ConfluencePageId;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Name of the manifest file that is written next to the converted output.
+pub const MANIFEST_FILE_NAME: &str = ".html2md-confluence-manifest.json";
+
+/// Version of the converter, which invalidates all entries of a manifest when it changes.
+const CONVERTER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn hash_bytes(bytes: &[u8]) -> String {
+    blake3::hash(bytes).to_hex().to_string()
+}
+
+/// Returns the hash of the debug representation of the value, which is used as fingerprint of
+/// provider data. The value must not contain unordered collections.
+pub(crate) fn debug_hash<T: fmt::Debug>(value: &T) -> String {
+    hash_bytes(format!("{value:?}").as_bytes())
+}
+
+/// Returns the hash of the file content.
+pub(crate) fn file_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Returns the fingerprint of a provider that is set. Providers without fingerprint get a unique
+/// value, so that the manifest never matches and all pages are converted again.
+fn provider_fingerprint(fingerprint: Option<Option<String>>) -> Option<String> {
+    fingerprint.map(|fingerprint| {
+        fingerprint.unwrap_or_else(|| {
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default();
+            format!("unknown-{}", now.as_nanos())
+        })
+    })
+}
+
+/// Returns the hash of all options that affect the output, including the fingerprints of the
+/// providers' data.
+pub(crate) fn options_hash(options: &ParseOptions) -> String {
+    let emoticon_mappings: BTreeMap<_, _> = options.emoticon_mappings.iter().collect();
+    let description = format!(
+        "{jira_servers} {jira_key_pattern:?} {confluence_server:?} {default_space_key:?} \
+         {default_page_id:?} {flavor:?} {status_style:?} {emoticon_style:?} {layout_style:?} \
+         {date_format:?} {raw_html_policy:?} {placeholders_as_comments} \
+         {inline_comment_references} {footer_comment_placement:?} {emoticon_mappings:?} \
+         {parse_mode:?} {providers:?}",
+        jira_servers = options.jira_server_map.canonical_description(),
+        jira_key_pattern = options
+            .jira_key_pattern
+            .as_ref()
+            .map(|pattern| pattern.as_str()),
+        confluence_server = options.confluence_server,
+        default_space_key = options.default_space_key,
+        default_page_id = options.default_page_id,
+        flavor = options.flavor,
+        status_style = options.status_style,
+        emoticon_style = options.emoticon_style,
+        layout_style = options.layout_style,
+        date_format = options.date_format,
+        raw_html_policy = options.raw_html_policy,
+        placeholders_as_comments = options.placeholders_as_comments,
+        inline_comment_references = options.inline_comment_references,
+        footer_comment_placement = options.footer_comment_placement,
+        parse_mode = options.parse_mode,
+        providers = [
+            provider_fingerprint(
+                options
+                    .jira_issue_provider
+                    .as_ref()
+                    .map(|provider| provider.fingerprint())
+            ),
+            provider_fingerprint(
+                options
+                    .attachment_provider
+                    .as_ref()
+                    .map(|provider| provider.fingerprint())
+            ),
+            options.page_index.as_ref().map(|index| index.fingerprint()),
+            provider_fingerprint(
+                options
+                    .comment_provider
+                    .as_ref()
+                    .map(|provider| provider.fingerprint())
+            ),
+            provider_fingerprint(
+                options
+                    .link_title_provider
+                    .as_ref()
+                    .map(|provider| provider.fingerprint())
+            ),
+        ],
+    );
+    hash_bytes(description.as_bytes())
+}
+
+/// Returns the hashes of the attachments of the page by filename.
+///
+/// Only the attachment metadata known to the attachment provider is hashed, because that is what
+/// the converted page refers to.
+pub(crate) fn attachment_hashes(
+    options: &ParseOptions,
+    page_id: Option<&ConfluencePageId>,
+) -> BTreeMap<String, String> {
+    options
+        .attachment_provider
+        .as_deref()
+        .zip(page_id)
+        .map(|(provider, page_id)| provider.attachments(page_id))
+        .unwrap_or_default()
+        .iter()
+        .map(|attachment| {
+            let hash = hash_bytes(format!("{attachment:?}").as_bytes());
+            (attachment.filename().to_string(), hash)
+        })
+        .collect()
+}
+
+/// The state of a converted page at the time of its last conversion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    source_hash: String,
+    output: PathBuf,
+    attachment_hashes: BTreeMap<String, String>,
+}
+
+impl ManifestEntry {
+    pub(crate) fn new(
+        source_hash: String,
+        output: PathBuf,
+        attachment_hashes: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            source_hash,
+            output,
+            attachment_hashes,
+        }
+    }
+
+    /// Hash of the storage format source of the page.
+    pub fn source_hash(&self) -> &str {
+        &self.source_hash
+    }
+
+    /// Path of the converted Markdown file.
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    /// Hashes of the page's attachments by filename.
+    pub fn attachment_hashes(&self) -> &BTreeMap<String, String> {
+        &self.attachment_hashes
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "source_hash": self.source_hash,
+            "output": self.output.to_string_lossy(),
+            "attachments": self.attachment_hashes,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let attachment_hashes = match value.get("attachments") {
+            Some(Value::Object(attachments)) => attachments
+                .iter()
+                .map(|(filename, hash)| Some((filename.clone(), hash.as_str()?.to_string())))
+                .collect::<Option<_>>()?,
+            None => BTreeMap::new(),
+            Some(_) => return None,
+        };
+        Some(Self {
+            source_hash: value.get("source_hash")?.as_str()?.to_string(),
+            output: PathBuf::from(value.get("output")?.as_str()?),
+            attachment_hashes,
+        })
+    }
+}
+
+/// Records which pages were converted from which source with which options, so that re-runs
+/// only need to convert the pages that changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    converter_version: String,
+    options_hash: String,
+    entries: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    /// Loads the manifest from the file, or returns an empty manifest if it does not exist yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+        let value: Value = serde_json::from_reader(BufReader::new(file))?;
+        Self::from_json(&value)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid manifest"))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, &self.to_json())?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+
+    pub fn get<S: AsRef<str>>(&self, key: S) -> Option<&ManifestEntry> {
+        self.entries.get(key.as_ref())
+    }
+
+    /// Entries by page id (or input path for pages without id).
+    pub fn entries(&self) -> &BTreeMap<String, ManifestEntry> {
+        &self.entries
+    }
+
+    /// Returns true if the manifest was written by this version of the converter with the same
+    /// options, so that its entries are still valid.
+    pub(crate) fn is_compatible(&self, options_hash: &str) -> bool {
+        self.converter_version == CONVERTER_VERSION && self.options_hash == options_hash
+    }
+
+    /// Returns true if the page was already converted from the same source and its output still
+    /// exists.
+    pub(crate) fn is_current(&self, key: &str, entry: &ManifestEntry) -> bool {
+        self.entries.get(key) == Some(entry) && entry.output.exists()
+    }
+
+    pub(crate) fn set_options_hash(&mut self, options_hash: String) {
+        self.converter_version = CONVERTER_VERSION.to_string();
+        self.options_hash = options_hash;
+    }
+
+    pub(crate) fn insert(&mut self, key: String, entry: ManifestEntry) -> Option<ManifestEntry> {
+        self.entries.insert(key, entry)
+    }
+
+    pub(crate) fn remove(&mut self, key: &str) -> Option<ManifestEntry> {
+        self.entries.remove(key)
+    }
+
+    fn to_json(&self) -> Value {
+        let entries: Map<String, Value> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.to_json()))
+            .collect();
+        json!({
+            "converter_version": self.converter_version,
+            "options_hash": self.options_hash,
+            "pages": entries,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let entries = value
+            .get("pages")?
+            .as_object()?
+            .iter()
+            .map(|(key, entry)| Some((key.clone(), ManifestEntry::from_json(entry)?)))
+            .collect::<Option<_>>()?;
+        Some(Self {
+            converter_version: value.get("converter_version")?.as_str()?.to_string(),
+            options_hash: value.get("options_hash")?.as_str()?.to_string(),
+            entries,
+        })
+    }
+}
+
+/// Removes the output of a page that is no longer part of the conversion.
+pub(crate) fn remove_output(output: &Path) -> io::Result<()> {
+    match fs::remove_file(output) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// What changed in an incremental conversion compared to the previous run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestChanges {
+    pub(crate) added: Vec<String>,
+    pub(crate) modified: Vec<String>,
+    pub(crate) unchanged: Vec<String>,
+    pub(crate) removed: Vec<String>,
+}
+
+impl ManifestChanges {
+    /// Pages that were converted for the first time.
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    /// Pages that were converted again because their source, attachments or the options changed.
+    pub fn modified(&self) -> &[String] {
+        &self.modified
+    }
+
+    /// Pages that were skipped because nothing changed.
+    pub fn unchanged(&self) -> &[String] {
+        &self.unchanged
+    }
+
+    /// Pages whose output was removed because they are no longer part of the conversion.
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{added} added, {modified} modified, {unchanged} unchanged, {removed} removed\n",
+            added = self.added.len(),
+            modified = self.modified.len(),
+            unchanged = self.unchanged.len(),
+            removed = self.removed.len(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Manifest, ManifestEntry, options_hash};
+    use crate::{
+        ConfluencePageId, InMemoryLinkTitleProvider, IndexedPage, JiraServer, MarkdownFlavor,
+        PageIndex, ParseOptions,
+    };
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_options_hash() {
+        let options = |flavor| {
+            (0..10)
+                .fold(ParseOptions::default(), |options, index| {
+                    options
+                        .with_emoticon_mapping(format!("emoticon-{index}"), "x")
+                        .with_jira_server(index.to_string(), JiraServer::from("https://jira"))
+                })
+                .with_flavor(flavor)
+        };
+        assert_eq!(
+            options_hash(&options(MarkdownFlavor::Github)),
+            options_hash(&options(MarkdownFlavor::Github))
+        );
+        assert_ne!(
+            options_hash(&options(MarkdownFlavor::Github)),
+            options_hash(&options(MarkdownFlavor::Plain))
+        );
+    }
+
+    #[test]
+    fn test_options_hash_provider_data() {
+        let options = |title| {
+            ParseOptions::default().with_link_title_provider(Arc::new(
+                InMemoryLinkTitleProvider::default().with_title("https://example.com", title),
+            ))
+        };
+        assert_eq!(options_hash(&options("Old")), options_hash(&options("Old")));
+        assert_ne!(options_hash(&options("Old")), options_hash(&options("New")));
+
+        let index = |title| {
+            ParseOptions::default().with_page_index(PageIndex::from_iter([IndexedPage::new(
+                ConfluencePageId::from(123),
+                "DOC",
+                title,
+            )]))
+        };
+        assert_eq!(options_hash(&index("Old")), options_hash(&index("Old")));
+        assert_ne!(options_hash(&index("Old")), options_hash(&index("New")));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join("html2md-confluence-manifest-test.json");
+        let mut manifest = Manifest::default();
+        manifest.set_options_hash(options_hash(&ParseOptions::default()));
+        manifest.insert(
+            "123".to_string(),
+            ManifestEntry::new(
+                "abc".to_string(),
+                PathBuf::from("out/page.md"),
+                BTreeMap::from([("image.png".to_string(), "def".to_string())]),
+            ),
+        );
+        manifest.save(&path).unwrap();
+        assert_eq!(Manifest::load(&path).unwrap(), manifest);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(Manifest::load(&path).unwrap(), Manifest::default());
+    }
+}
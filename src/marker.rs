@@ -0,0 +1,205 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::macros::DocumentContext;
+use crate::metadata::SharedPageMetadata;
+use crate::util::get_text_content;
+use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory, common::get_tag_attr};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Returns the text as HTML comment. Consecutive hyphens are separated by a space, because
+/// comments must not contain `--`, and the padding keeps a trailing `-` away from the `-->`.
+fn html_comment(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '-' && escaped.ends_with('-') {
+            escaped.push(' ');
+        }
+        escaped.push(c);
+    }
+    format!("<!-- {escaped} -->")
+}
+
+/// Handler for `ac:placeholder` elements, i.e. the instructional text of page templates.
+pub struct PlaceholderHandler {
+    as_comment: bool,
+}
+
+impl TagHandler for PlaceholderHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        if !self.as_comment {
+            return;
+        }
+
+        let text = get_text_content(tag);
+        let text = text.trim();
+        if !text.is_empty() {
+            printer.append_str(&html_comment(text));
+        }
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+pub struct PlaceholderHandlerFactory {
+    as_comment: bool,
+}
+
+impl PlaceholderHandlerFactory {
+    /// Creates a factory for handlers that either drop placeholders or keep them as HTML comments.
+    pub fn new(as_comment: bool) -> Self {
+        Self { as_comment }
+    }
+}
+
+impl TagHandlerFactory for PlaceholderHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(PlaceholderHandler {
+            as_comment: self.as_comment,
+        })
+    }
+}
+
+/// Handler for `ac:inline-comment-marker` elements. The marked text is rendered as usual,
+/// followed by a footnote reference like `[^comment-1]` if references are enabled or if the
+/// comment is known.
+pub struct InlineCommentMarkerHandler {
+    metadata: SharedPageMetadata,
+    references: bool,
+    known_comments: Rc<HashSet<String>>,
+    number: Option<usize>,
+}
+
+impl TagHandler for InlineCommentMarkerHandler {
+    fn handle(&mut self, tag: &Handle, _printer: &mut StructuredPrinter) {
+        self.number = get_tag_attr(tag, "ac:ref")
+            .filter(|comment_ref| self.references || self.known_comments.contains(comment_ref))
+            .map(|comment_ref| {
+                self.metadata
+                    .borrow_mut()
+                    .inline_comment_number(&comment_ref)
+            });
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if let Some(number) = self.number {
+            printer.append_str(&format!("[^comment-{number}]"));
+        }
+    }
+}
+
+pub struct InlineCommentMarkerHandlerFactory {
+    document: DocumentContext,
+    references: bool,
+}
+
+impl InlineCommentMarkerHandlerFactory {
+    pub(crate) fn new(document: DocumentContext, references: bool) -> Self {
+        Self {
+            document,
+            references,
+        }
+    }
+}
+
+impl TagHandlerFactory for InlineCommentMarkerHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        let context = self.document.get();
+        Box::new(InlineCommentMarkerHandler {
+            metadata: context.metadata,
+            references: self.references,
+            known_comments: context.known_comments,
+            number: None,
+        })
+    }
+}
+
+/// Handler for wrapper elements like `ac:adf-mark` that are replaced by their content.
+pub struct UnwrapHandler;
+
+impl TagHandler for UnwrapHandler {
+    fn handle(&mut self, _tag: &Handle, _printer: &mut StructuredPrinter) {}
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+}
+
+pub struct UnwrapHandlerFactory;
+
+impl TagHandlerFactory for UnwrapHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(UnwrapHandler)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::html_comment;
+    use crate::{ParseOptions, parse_confluence, parse_confluence_page};
+
+    const TEMPLATE: &str =
+        r#"<p>Owner: <ac:placeholder>Mention the owner -- e.g. @name</ac:placeholder></p>"#;
+
+    #[test]
+    fn test_placeholder_is_dropped() {
+        crate::markdown_assert_eq!(TEMPLATE, "Owner:");
+    }
+
+    #[test]
+    fn test_placeholder_as_comment() {
+        let options = ParseOptions::default().with_placeholders_as_comments(true);
+        assert_eq!(
+            parse_confluence(TEMPLATE, &options),
+            "Owner: <!-- Mention the owner - - e.g. @name -->"
+        );
+    }
+
+    #[test]
+    fn test_html_comment() {
+        for (text, comment) in [
+            ("a---b", "<!-- a- - -b -->"),
+            ("<!-- x --!>", "<!-- <!- - x - -!> -->"),
+            ("trailing-", "<!-- trailing- -->"),
+        ] {
+            let comment_text = comment.strip_prefix("<!--").unwrap().strip_suffix("-->");
+            assert!(!comment_text.unwrap().contains("--"));
+            assert_eq!(html_comment(text), comment);
+        }
+    }
+
+    #[test]
+    fn test_inline_comment_marker() {
+        let html = r#"<p><ac:inline-comment-marker ac:ref="b1">Marked</ac:inline-comment-marker> and <ac:adf-mark key="border"><ac:inline-comment-marker ac:ref="a2">also</ac:inline-comment-marker></ac:adf-mark> <ac:inline-comment-marker ac:ref="b1">again</ac:inline-comment-marker>.</p>"#;
+        assert_eq!(
+            parse_confluence(html, &ParseOptions::default()),
+            "Marked and also again."
+        );
+
+        let options = ParseOptions::default().with_inline_comment_references(true);
+        let page = parse_confluence_page(html, &options);
+        assert_eq!(
+            page.markdown(),
+            "Marked[^comment-1] and also[^comment-2] again[^comment-1].
+
+[^comment-1]: *Inline comment not available*
+[^comment-2]: *Inline comment not available*"
+        );
+        assert_eq!(page.metadata().inline_comment_refs(), ["b1", "a2"]);
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::comment::{self, RenderedComment};
+use crate::diagnostic::Diagnostic;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Key/value pairs extracted from a single Page Properties (`details`) macro.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PageProperties {
+    id: Option<String>,
+    entries: Vec<(String, String)>,
+}
+
+impl PageProperties {
+    pub fn new(id: Option<String>) -> Self {
+        Self {
+            id,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+
+    pub fn get<S: AsRef<str>>(&self, key: S) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key.as_ref())
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn insert(&mut self, key: String, value: String) {
+        self.entries.push((key, value));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Structured metadata collected while converting a page.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PageMetadata {
+    properties: Vec<PageProperties>,
+    inline_comment_refs: Vec<String>,
+    column_widths: Vec<Vec<Option<String>>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl PageMetadata {
+    pub fn properties(&self) -> &[PageProperties] {
+        &self.properties
+    }
+
+    pub fn add_properties(&mut self, properties: PageProperties) {
+        self.properties.push(properties);
+    }
+
+    /// Returns the `ac:ref` ids of the inline comments that are referenced by footnotes, in the
+    /// order of their footnote numbers.
+    pub fn inline_comment_refs(&self) -> &[String] {
+        &self.inline_comment_refs
+    }
+
+    /// Returns the 1-based footnote number of the inline comment, registering it if necessary.
+    pub(crate) fn inline_comment_number(&mut self, comment_ref: &str) -> usize {
+        let index = match self
+            .inline_comment_refs
+            .iter()
+            .position(|known| known == comment_ref)
+        {
+            Some(index) => index,
+            None => {
+                self.inline_comment_refs.push(comment_ref.to_string());
+                self.inline_comment_refs.len() - 1
+            }
+        };
+        index + 1
+    }
+
+    /// Returns the column widths of each multi-column section (page layouts and `section`
+    /// macros) in document order, with `None` for columns without a width.
+    pub fn column_widths(&self) -> &[Vec<Option<String>>] {
+        &self.column_widths
+    }
+
+    /// Starts a new multi-column section for [`PageMetadata::add_column_width`].
+    pub(crate) fn add_section(&mut self) {
+        self.column_widths.push(Vec::new());
+    }
+
+    /// Records the width of the next column of the current section.
+    pub(crate) fn add_column_width(&mut self, width: Option<&str>) {
+        if self.column_widths.is_empty() {
+            self.add_section();
+        }
+        if let Some(section) = self.column_widths.last_mut() {
+            section.push(width.map(str::to_string));
+        }
+    }
+
+    /// Returns the problems in the source that were repaired in lenient mode.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub(crate) fn add_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics.extend(diagnostics);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.properties.iter().all(PageProperties::is_empty)
+    }
+
+    /// Render the metadata as a YAML front matter block (including the `---` delimiters).
+    ///
+    /// Properties from `details` macros without an `id` are emitted at the top level, properties
+    /// from macros with an `id` are nested below that id. Macros with the same id are merged, and
+    /// keys that occur more than once get a list of all their values.
+    pub fn to_front_matter(&self) -> String {
+        let mut top_level = PropertyGroup::default();
+        let mut by_id: Vec<(&str, PropertyGroup<'_>)> = Vec::new();
+        for properties in &self.properties {
+            let group = match properties.id() {
+                None => &mut top_level,
+                Some(id) => match by_id.iter().position(|(known, _)| *known == id) {
+                    Some(index) => &mut by_id[index].1,
+                    None => {
+                        by_id.push((id, PropertyGroup::default()));
+                        &mut by_id.last_mut().unwrap().1
+                    }
+                },
+            };
+            group.extend(properties.entries());
+        }
+
+        let mut front_matter = String::from("---\n");
+        top_level.write(&mut front_matter, "");
+        for (id, group) in by_id {
+            if group.0.is_empty() {
+                continue;
+            }
+            front_matter.push_str(&format!("{}:\n", yaml_quote(id)));
+            group.write(&mut front_matter, "  ");
+        }
+        front_matter.push_str("---\n");
+        front_matter
+    }
+}
+
+/// The values of properties by key, in the order of their first occurrence.
+#[derive(Default)]
+struct PropertyGroup<'a>(Vec<(&'a str, Vec<&'a str>)>);
+
+impl<'a> PropertyGroup<'a> {
+    fn extend(&mut self, entries: &'a [(String, String)]) {
+        for (key, value) in entries {
+            match self.0.iter_mut().find(|(known, _)| known == key) {
+                Some((_, values)) => values.push(value),
+                None => self.0.push((key, vec![value])),
+            }
+        }
+    }
+
+    /// Writes the properties as YAML mapping, with a sequence for keys with several values.
+    fn write(&self, front_matter: &mut String, indent: &str) {
+        for (key, values) in &self.0 {
+            let key = yaml_quote(key);
+            match values.as_slice() {
+                [value] => {
+                    front_matter.push_str(&format!("{indent}{key}: {}\n", yaml_quote(value)));
+                }
+                values => {
+                    front_matter.push_str(&format!("{indent}{key}:\n"));
+                    for value in values {
+                        front_matter.push_str(&format!("{indent}  - {}\n", yaml_quote(value)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) type SharedPageMetadata = Rc<RefCell<PageMetadata>>;
+
+/// Quotes the string as double-quoted YAML scalar, escaping line breaks and other control
+/// characters.
+fn yaml_quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Result of converting a Confluence page.
+#[derive(Debug, Clone)]
+pub struct ConfluencePage {
+    markdown: String,
+    metadata: PageMetadata,
+    footer_comments: Vec<RenderedComment>,
+}
+
+impl ConfluencePage {
+    pub(crate) fn new(markdown: String, metadata: PageMetadata) -> Self {
+        Self {
+            markdown,
+            metadata,
+            footer_comments: Vec::new(),
+        }
+    }
+
+    pub(crate) fn with_footer_comments(mut self, footer_comments: Vec<RenderedComment>) -> Self {
+        self.footer_comments = footer_comments;
+        self
+    }
+
+    pub fn markdown(&self) -> &str {
+        &self.markdown
+    }
+
+    pub fn metadata(&self) -> &PageMetadata {
+        &self.metadata
+    }
+
+    /// Returns the markdown prefixed with a YAML front matter block, unless the metadata is empty.
+    pub fn to_markdown_with_front_matter(&self) -> String {
+        if self.metadata.is_empty() {
+            return self.markdown.clone();
+        }
+
+        format!("{}\n{}", self.metadata.to_front_matter(), self.markdown)
+    }
+
+    /// Returns the footer comments of the page, if a comment provider was configured.
+    pub fn footer_comments(&self) -> &[RenderedComment] {
+        &self.footer_comments
+    }
+
+    /// Renders the footer comments as standalone Markdown document, e.g. for a sidecar file.
+    pub fn footer_comments_markdown(&self) -> String {
+        comment::comments_section(&self.footer_comments, "#")
+    }
+
+    /// Renders the footer comments as JSON array, e.g. for a sidecar file.
+    pub fn footer_comments_json(&self) -> String {
+        comment::comments_json(&self.footer_comments)
+    }
+}
+
+impl From<ConfluencePage> for String {
+    fn from(page: ConfluencePage) -> Self {
+        page.markdown
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PageMetadata, PageProperties};
+
+    fn properties(id: Option<&str>, entries: &[(&str, &str)]) -> PageProperties {
+        let mut properties = PageProperties::new(id.map(str::to_string));
+        for (key, value) in entries {
+            properties.insert(key.to_string(), value.to_string());
+        }
+        properties
+    }
+
+    #[test]
+    fn test_repeated_keys_are_merged() {
+        let mut metadata = PageMetadata::default();
+        metadata.add_properties(properties(None, &[("Owner", "A"), ("Status", "Open")]));
+        metadata.add_properties(properties(Some("project"), &[("Owner", "B")]));
+        metadata.add_properties(properties(None, &[("Owner", "C")]));
+        metadata.add_properties(properties(Some("project"), &[("Owner", "D")]));
+        assert_eq!(
+            metadata.to_front_matter(),
+            "\
+---
+\"Owner\":
+  - \"A\"
+  - \"C\"
+\"Status\": \"Open\"
+\"project\":
+  \"Owner\":
+    - \"B\"
+    - \"D\"
+---
+"
+        );
+    }
+
+    #[test]
+    fn test_control_characters_are_escaped() {
+        let mut metadata = PageMetadata::default();
+        metadata.add_properties(properties(None, &[("Owner", "B\nline2\t\"x\"\u{7}")]));
+        assert_eq!(
+            metadata.to_front_matter(),
+            "---\n\"Owner\": \"B\\nline2\\t\\\"x\\\"\\u0007\"\n---\n"
+        );
+    }
+}
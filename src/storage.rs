@@ -0,0 +1,765 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::diagnostic::{Diagnostic, ParseError, ParseMode, Position};
+use crate::util::{JiraServer, get_tag_name};
+use html2md::{Handle, NodeData};
+use markup5ever::data::NAMED_ENTITIES;
+use markup5ever::tendril::StrTendril;
+use markup5ever::{Attribute, LocalName, QualName, ns};
+use markup5ever_rcdom::Node;
+use quick_xml::events::{BytesEnd, BytesStart, Event};
+use quick_xml::reader::Reader;
+use regex::Regex;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::io::{self, BufRead, Read};
+use std::rc::Rc;
+
+/// Elements whose text content must not be auto-linked.
+const NO_AUTOLINK_ELEMENTS: &[&str] = &[
+    "a",
+    "code",
+    "pre",
+    "ac:link",
+    "ac:parameter",
+    "ac:plain-text-body",
+    "ac:plain-text-link-body",
+];
+
+/// HTML elements that never have content, which lenient mode accepts without end tag.
+const HTML_VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Decodes character references, including the HTML named ones like `&nbsp;` that Confluence
+/// uses, but which are not defined in XML.
+///
+/// Ampersands that do not start a known reference are kept as they are and returned along with
+/// their byte offsets in the text.
+fn decode_entities(text: &str) -> (Cow<'_, str>, Vec<(usize, String)>) {
+    if !text.contains('&') {
+        return (Cow::Borrowed(text), Vec::new());
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut unknown = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest
+            .find(';')
+            .filter(|end| *end < 40)
+            .map(|end| &rest[..=end]);
+        let characters = reference.and_then(|reference| {
+            let name = &reference[1..];
+            if let Some(number) = name.strip_prefix('#') {
+                let number = number.trim_end_matches(';');
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => number.parse().ok(),
+                };
+                code.and_then(char::from_u32).map(|c| [Some(c), None])
+            } else {
+                NAMED_ENTITIES
+                    .get(name)
+                    .map(|(first, second)| [char::from_u32(*first), char::from_u32(*second)])
+            }
+        });
+        match (reference, characters) {
+            (Some(reference), Some(characters)) => {
+                decoded.extend(characters.into_iter().flatten().filter(|c| *c != '\0'));
+                rest = &rest[reference.len()..];
+            }
+            _ => {
+                let offset = text.len() - rest.len();
+                let description = match reference {
+                    Some(reference) if !reference[1..].contains(char::is_whitespace) => {
+                        format!("unknown entity `{reference}`")
+                    }
+                    _ => "unescaped `&`".to_string(),
+                };
+                unknown.push((offset, description));
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    (Cow::Owned(decoded), unknown)
+}
+
+fn element(name: &str, attributes: Vec<(String, String)>) -> Handle {
+    Node::new(NodeData::Element {
+        name: QualName::new(None, ns!(html), LocalName::from(name)),
+        attrs: RefCell::new(
+            attributes
+                .into_iter()
+                .map(|(name, value)| Attribute {
+                    name: QualName::new(None, ns!(), LocalName::from(name)),
+                    value: StrTendril::from(value),
+                })
+                .collect(),
+        ),
+        template_contents: RefCell::new(None),
+        mathml_annotation_xml_integration_point: false,
+    })
+}
+
+fn append(parent: &Handle, child: Handle) {
+    child.parent.set(Some(Rc::downgrade(parent)));
+    parent.children.borrow_mut().push(child);
+}
+
+/// Appends the text, merging it with a preceding text node like an HTML parser does.
+fn append_text(parent: &Handle, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(last) = parent.children.borrow().last()
+        && let NodeData::Text { ref contents } = last.data
+    {
+        contents.borrow_mut().push_slice(text);
+        return;
+    }
+    let text = if !parent.children.borrow().is_empty() {
+        text
+    } else if matches!(parent.data, NodeData::Document) {
+        // HTML ignores whitespace before the start of the document body.
+        text.trim_start_matches(|c: char| c.is_ascii_whitespace())
+    } else if get_tag_name(parent).is_some_and(|name| name == "pre") {
+        // HTML ignores a newline directly after the start tag of `pre`.
+        text.strip_prefix('\n').unwrap_or(text)
+    } else {
+        text
+    };
+    if text.is_empty() {
+        return;
+    }
+    append(
+        parent,
+        Node::new(NodeData::Text {
+            contents: RefCell::new(StrTendril::from_slice(text)),
+        }),
+    );
+}
+
+/// Appends the text, replacing all matches of the Jira key pattern with links to the issue.
+fn append_autolinked_text(parent: &Handle, text: &str, pattern: &Regex, server: &JiraServer) {
+    let mut last_end = 0;
+    for key in pattern.find_iter(text) {
+        append_text(parent, &text[last_end..key.start()]);
+        let link = element(
+            "a",
+            vec![("href".to_string(), server.issue_url(key.as_str()))],
+        );
+        append_text(&link, key.as_str());
+        append(parent, link);
+        last_end = key.end();
+    }
+    append_text(parent, &text[last_end..]);
+}
+
+/// Input that records where its lines start, to report byte offsets as line and column.
+///
+/// The bytes of the current event are kept, so that they can be read again after markup that
+/// could not be parsed.
+struct LineTracker<R> {
+    inner: R,
+    offset: u64,
+    line_starts: Vec<u64>,
+    /// The bytes consumed since the start of the current event.
+    consumed: Vec<u8>,
+    /// The offset at which the current XML reader started reading.
+    reader_start: u64,
+    /// Bytes that are read again before the rest of the input.
+    replay: Vec<u8>,
+    replay_position: usize,
+}
+
+impl<R: BufRead> LineTracker<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            offset: 0,
+            line_starts: vec![0],
+            consumed: Vec::new(),
+            reader_start: 0,
+            replay: Vec::new(),
+            replay_position: 0,
+        }
+    }
+
+    /// Marks the start of the next event at the given position of the XML reader, which may
+    /// already have consumed the first bytes of the event (e.g. the `<` after text).
+    fn start_event(&mut self, reader_offset: u64) -> u64 {
+        let offset = self.reader_start + reader_offset;
+        let event_length = usize::try_from(self.offset - offset).unwrap_or(usize::MAX);
+        let previous_length = self.consumed.len().saturating_sub(event_length);
+        self.consumed.drain(..previous_length);
+        offset
+    }
+
+    /// Returns the first `length` bytes of the current event, and reads the rest of them again
+    /// with a new XML reader.
+    fn rewind(&mut self, length: usize) -> Vec<u8> {
+        let length = length.min(self.consumed.len());
+        let mut replay = self.consumed.split_off(length);
+        self.offset -= replay.len() as u64;
+        self.reader_start = self.offset;
+        replay.extend_from_slice(&self.replay[self.replay_position..]);
+        self.replay = replay;
+        self.replay_position = 0;
+        let offset = self.offset;
+        self.line_starts.retain(|start| *start <= offset);
+        std::mem::take(&mut self.consumed)
+    }
+
+    fn position(&self, offset: u64) -> Position {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let column = offset - self.line_starts[line - 1] + 1;
+        Position {
+            line,
+            column: usize::try_from(column).unwrap_or(usize::MAX),
+        }
+    }
+}
+
+impl<R: BufRead> Read for LineTracker<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let length = available.len().min(buf.len());
+        buf[..length].copy_from_slice(&available[..length]);
+        self.consume(length);
+        Ok(length)
+    }
+}
+
+impl<R: BufRead> BufRead for LineTracker<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.replay_position < self.replay.len() {
+            return Ok(&self.replay[self.replay_position..]);
+        }
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        let replaying = self.replay_position < self.replay.len();
+        // The buffer was already filled by the caller, so this does not read any input.
+        let buffer = if replaying {
+            &self.replay[self.replay_position..]
+        } else {
+            self.inner.fill_buf().unwrap_or_default()
+        };
+        let consumed = &buffer[..amount.min(buffer.len())];
+        let offset = self.offset;
+        self.line_starts.extend(
+            consumed
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == b'\n')
+                .map(|(index, _)| offset + index as u64 + 1),
+        );
+        self.consumed.extend_from_slice(consumed);
+        self.offset += amount as u64;
+        if replaying {
+            self.replay_position += amount;
+        } else {
+            self.inner.consume(amount);
+        }
+    }
+}
+
+/// Error while reading the storage format.
+#[derive(Debug)]
+pub(crate) enum StorageError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl From<StorageError> for io::Error {
+    fn from(error: StorageError) -> Self {
+        match error {
+            StorageError::Io(error) => error,
+            StorageError::Parse(error) => io::Error::new(io::ErrorKind::InvalidData, error),
+        }
+    }
+}
+
+/// The document tree of a storage format document.
+pub(crate) struct StorageDocument {
+    pub(crate) tree: Handle,
+    /// Problems in the source that were repaired in lenient mode.
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+struct OpenElement {
+    name: String,
+    node: Handle,
+    position: Position,
+}
+
+/// Builds the document tree from the events of the XML reader.
+struct TreeBuilder<'a> {
+    mode: ParseMode,
+    jira_key_autolink: Option<(&'a Regex, &'a JiraServer)>,
+    document: Handle,
+    open_elements: Vec<OpenElement>,
+    no_autolink_depth: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn new(mode: ParseMode, jira_key_autolink: Option<(&'a Regex, &'a JiraServer)>) -> Self {
+        Self {
+            mode,
+            jira_key_autolink,
+            document: Node::new(NodeData::Document),
+            open_elements: Vec::new(),
+            no_autolink_depth: 0,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Records the problem in lenient mode, or returns it as error in strict mode.
+    fn recover<S: Into<String>>(
+        &mut self,
+        position: Position,
+        message: S,
+    ) -> Result<(), ParseError> {
+        match self.mode {
+            ParseMode::Strict => Err(ParseError::new(position, message)),
+            ParseMode::Lenient => {
+                self.diagnostics.push(Diagnostic::new(position, message));
+                Ok(())
+            }
+        }
+    }
+
+    /// Decodes the bytes as UTF-8. Invalid sequences are an error in strict mode and are replaced
+    /// by U+FFFD in lenient mode (their positions are relative to the start of the bytes).
+    fn utf8<'t>(
+        &mut self,
+        bytes: &'t [u8],
+        position: impl Fn(usize) -> Position,
+    ) -> Result<Cow<'t, str>, ParseError> {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Ok(Cow::Borrowed(text)),
+            Err(error) => {
+                self.recover(
+                    position(error.valid_up_to()),
+                    "invalid UTF-8 was replaced by U+FFFD",
+                )?;
+                Ok(String::from_utf8_lossy(bytes))
+            }
+        }
+    }
+
+    fn parent(&self) -> Handle {
+        self.open_elements
+            .last()
+            .map_or_else(|| Rc::clone(&self.document), |open| Rc::clone(&open.node))
+    }
+
+    /// Decodes the text, keeping unknown entities as text (their positions are relative to the
+    /// start of the text).
+    ///
+    /// Bare `&`s are common in hand-written content (e.g. `AT&T`), so they are reported as
+    /// diagnostics even in strict mode.
+    fn decode<'t>(&mut self, text: &'t str, position: impl Fn(usize) -> Position) -> Cow<'t, str> {
+        let (decoded, unknown) = decode_entities(text);
+        for (offset, description) in unknown {
+            self.diagnostics.push(Diagnostic::new(
+                position(offset),
+                format!("{description} was kept as text"),
+            ));
+        }
+        decoded
+    }
+
+    fn element(
+        &mut self,
+        start: &BytesStart<'_>,
+        position: Position,
+    ) -> Result<(String, Handle), ParseError> {
+        // Like an HTML parser, tag and attribute names are matched case-insensitively.
+        // Namespace prefixes like `ac:` are part of the name and never need to be declared.
+        let name = self
+            .utf8(start.name().as_ref(), |_| position)?
+            .to_ascii_lowercase();
+        let attributes = match start.attributes().collect::<Result<Vec<_>, _>>() {
+            Ok(attributes) => attributes,
+            Err(error) => {
+                self.recover(
+                    position,
+                    format!("malformed attributes of `<{name}>` were repaired: {error}"),
+                )?;
+                start
+                    .html_attributes()
+                    .with_checks(false)
+                    .filter_map(Result::ok)
+                    .collect()
+            }
+        };
+        let mut decoded_attributes = Vec::with_capacity(attributes.len());
+        for attribute in attributes {
+            let key = self
+                .utf8(attribute.key.as_ref(), |_| position)?
+                .to_ascii_lowercase();
+            let value = self.utf8(&attribute.value, |_| position)?;
+            let value = self.decode(&value, |_| position).into_owned();
+            decoded_attributes.push((key, value));
+        }
+        let node = element(&name, decoded_attributes);
+        Ok((name, node))
+    }
+
+    fn start(&mut self, start: &BytesStart<'_>, position: Position) -> Result<(), ParseError> {
+        let (name, node) = self.element(start, position)?;
+        append(&self.parent(), Rc::clone(&node));
+        // HTML void elements like `<br>` are often not closed.
+        if self.mode == ParseMode::Lenient && HTML_VOID_ELEMENTS.contains(&name.as_str()) {
+            return Ok(());
+        }
+        if NO_AUTOLINK_ELEMENTS.contains(&name.as_str()) {
+            self.no_autolink_depth += 1;
+        }
+        self.open_elements.push(OpenElement {
+            name,
+            node,
+            position,
+        });
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<OpenElement> {
+        let open = self.open_elements.pop()?;
+        if NO_AUTOLINK_ELEMENTS.contains(&open.name.as_str()) {
+            self.no_autolink_depth = self.no_autolink_depth.saturating_sub(1);
+        }
+        Some(open)
+    }
+
+    fn end(&mut self, end: &BytesEnd<'_>, position: Position) -> Result<(), ParseError> {
+        let name = self
+            .utf8(end.name().as_ref(), |_| position)?
+            .to_ascii_lowercase();
+        let Some(index) = self
+            .open_elements
+            .iter()
+            .rposition(|open| open.name == name)
+        else {
+            if self.mode == ParseMode::Lenient && HTML_VOID_ELEMENTS.contains(&name.as_str()) {
+                return Ok(());
+            }
+            let message = match self.open_elements.last() {
+                Some(open) => format!(
+                    "expected `</{expected}>`, but `</{name}>` was found",
+                    expected = open.name
+                ),
+                None => format!("unexpected `</{name}>`"),
+            };
+            return self.recover(position, format!("{message}; it was ignored"));
+        };
+
+        while self.open_elements.len() > index + 1 {
+            let open = self.open_elements.last().expect("element is open");
+            match self.mode {
+                ParseMode::Strict => {
+                    return Err(ParseError::new(
+                        position,
+                        format!(
+                            "expected `</{expected}>`, but `</{name}>` was found",
+                            expected = open.name
+                        ),
+                    ));
+                }
+                ParseMode::Lenient => {
+                    let message = format!(
+                        "unclosed `<{unclosed}>` was closed by `</{name}>` at {position}",
+                        unclosed = open.name
+                    );
+                    let opened = open.position;
+                    self.diagnostics.push(Diagnostic::new(opened, message));
+                    self.pop();
+                }
+            }
+        }
+        self.pop();
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str, position: impl Fn(usize) -> Position) -> Result<(), ParseError> {
+        let text = self.decode(text, position);
+        let parent = self.parent();
+        match self.jira_key_autolink {
+            Some((pattern, server)) if self.no_autolink_depth == 0 => {
+                append_autolinked_text(&parent, &text, pattern, server);
+            }
+            _ => append_text(&parent, &text),
+        }
+        Ok(())
+    }
+
+    /// Keeps markup that could not be parsed as text.
+    fn markup_as_text(
+        &mut self,
+        text: &[u8],
+        position: Position,
+        problem: &str,
+    ) -> Result<(), ParseError> {
+        self.recover(position, format!("{problem}; it was kept as text"))?;
+        self.text(&String::from_utf8_lossy(text), |_| position)
+    }
+
+    fn finish(mut self) -> Result<StorageDocument, ParseError> {
+        while let Some(open) = self.open_elements.last() {
+            let message = format!("unclosed `<{}>` at end of document", open.name);
+            let opened = open.position;
+            self.recover(opened, message)?;
+            self.pop();
+        }
+        Ok(StorageDocument {
+            tree: self.document,
+            diagnostics: self.diagnostics,
+        })
+    }
+}
+
+/// Returns whether the name of a start tag is a valid XML name, i.e. does not start with e.g. a
+/// space or digit.
+fn is_tag_name(name: &[u8]) -> bool {
+    name.first()
+        .is_some_and(|first| first.is_ascii_alphabetic() || matches!(first, b'_' | b':' | 0x80..))
+}
+
+fn xml_reader<R: BufRead>(input: LineTracker<R>) -> Reader<LineTracker<R>> {
+    let mut reader = Reader::from_reader(input);
+    // End tags are matched by the tree builder, which can recover from mismatches. A new reader
+    // is created after markup that could not be parsed, so it does not know the open elements.
+    reader.config_mut().check_end_names = false;
+    reader.config_mut().allow_unmatched_ends = true;
+    reader
+}
+
+/// Builds the document tree directly from the events of the XML reader, so that the storage
+/// format does not need to be serialized and parsed again by an HTML parser.
+///
+/// If a Jira key pattern and server are given, bare Jira issue keys in the text are replaced by
+/// links to the issue.
+pub(crate) fn parse_storage_format<R: BufRead>(
+    input: R,
+    mode: ParseMode,
+    jira_key_autolink: Option<(&Regex, &JiraServer)>,
+) -> Result<StorageDocument, StorageError> {
+    let mut reader = xml_reader(LineTracker::new(input));
+    let mut builder = TreeBuilder::new(mode, jira_key_autolink);
+    let mut buf = Vec::with_capacity(2048);
+
+    loop {
+        let reader_offset = reader.buffer_position();
+        let offset = reader.get_mut().start_event(reader_offset);
+        let event = match reader.read_event_into(&mut buf) {
+            // A `<` that is followed by e.g. a space or digit starts a tag without valid name.
+            Ok(Event::Start(start) | Event::Empty(start))
+                if !is_tag_name(start.name().as_ref()) =>
+            {
+                let position = reader.get_ref().position(offset);
+                let text = reader.get_mut().rewind(1);
+                reader = xml_reader(reader.into_inner());
+                builder
+                    .markup_as_text(&text, position, "`<` does not start a tag")
+                    .map_err(StorageError::Parse)?;
+                buf.clear();
+                continue;
+            }
+            Ok(event) => event,
+            Err(quick_xml::Error::Io(error)) => {
+                return Err(StorageError::Io(io::Error::new(error.kind(), error)));
+            }
+            Err(error) => {
+                // The markup is kept as text up to where the error occurred, and the rest of the
+                // event is parsed again.
+                let length = reader.error_position().saturating_sub(reader_offset) + 1;
+                let tracker = reader.get_mut();
+                let text = tracker.rewind(usize::try_from(length).unwrap_or(usize::MAX));
+                let position = tracker.position(tracker.offset.saturating_sub(1).max(offset));
+                // The reader cannot continue after an error.
+                reader = xml_reader(reader.into_inner());
+                if text.is_empty() {
+                    let message = format!("{error}; the rest of the document was ignored");
+                    builder
+                        .recover(position, message)
+                        .map_err(StorageError::Parse)?;
+                    break;
+                }
+                builder
+                    .markup_as_text(&text, position, &error.to_string())
+                    .map_err(StorageError::Parse)?;
+                buf.clear();
+                continue;
+            }
+        };
+        let tracker = reader.get_ref();
+        let position = tracker.position(offset);
+        let result = match event {
+            Event::Start(start) => builder.start(&start, position),
+            Event::Empty(empty) => builder.element(&empty, position).map(|(_, node)| {
+                append(&builder.parent(), node);
+            }),
+            Event::End(end) => builder.end(&end, position),
+            Event::Text(text) => {
+                let position = |index| tracker.position(offset + index as u64);
+                builder
+                    .utf8(&text, position)
+                    .and_then(|text| builder.text(&text, position))
+            }
+            Event::CData(text) => {
+                // The content starts after `<![CDATA[`.
+                let position = |index| tracker.position(offset + 9 + index as u64);
+                builder.utf8(&text, position).map(|text| {
+                    append_text(&builder.parent(), &text);
+                })
+            }
+            Event::Eof => break,
+            Event::Comment(_) | Event::Decl(_) | Event::PI(_) | Event::DocType(_) => Ok(()),
+        };
+        result.map_err(StorageError::Parse)?;
+        buf.clear();
+    }
+
+    builder.finish().map_err(StorageError::Parse)
+}
+
+#[cfg(test)]
+mod test {
+    use super::decode_entities;
+    use crate::{Converter, ParseMode, ParseOptions, Position};
+
+    #[test]
+    fn test_decode_entities() {
+        let (decoded, unknown) = decode_entities("a&nbsp;&amp;&#160;&#x2014;&rarr; &unknown; & b");
+        assert_eq!(decoded, "a\u{a0}&\u{a0}\u{2014}\u{2192} &unknown; & b");
+        assert_eq!(
+            unknown,
+            [
+                (33, "unknown entity `&unknown;`".to_string()),
+                (43, "unescaped `&`".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_html_whitespace_rules() {
+        crate::markdown_assert_eq!(
+            "\n  <pre>\n  indented</pre><p>a<br/>b</p>",
+            "```\n  indented\n```\n\na  \nb"
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_errors() {
+        let converter = Converter::new(ParseOptions::default());
+        let error = converter
+            .try_convert_page("<p>\n  <b>bold</p>")
+            .unwrap_err();
+        assert_eq!(
+            error.position(),
+            Position {
+                line: 2,
+                column: 10
+            }
+        );
+        assert_eq!(error.message(), "expected `</b>`, but `</p>` was found");
+
+        let page = converter.try_convert_page("<p>a &unknown; b</p>").unwrap();
+        assert_eq!(page.markdown(), "a &unknown; b");
+        assert_eq!(
+            page.metadata().diagnostics()[0].to_string(),
+            "1:6: unknown entity `&unknown;` was kept as text"
+        );
+    }
+
+    #[test]
+    fn test_bare_ampersand() {
+        assert_eq!(
+            crate::parse_confluence("<p>AT&T and R&D</p>", &ParseOptions::default()),
+            "AT&T and R&D"
+        );
+    }
+
+    #[test]
+    fn test_lenient_mode_recovery() {
+        let converter = Converter::new(ParseOptions::default().with_parse_mode(ParseMode::Lenient));
+        let page = converter.convert_page(
+            "<p>Link&nbsp;<b>bold &amp; more</p>\n<p>a<br>b &unknown;</p></ac:rich-text-body>\n<ul><li>open",
+        );
+        assert_eq!(
+            page.markdown(),
+            "Link\u{a0}**bold & more**\n\na  \nb &unknown;\n\n* open"
+        );
+        let diagnostics: Vec<_> = page
+            .metadata()
+            .diagnostics()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                "1:14: unclosed `<b>` was closed by `</p>` at 1:32",
+                "2:11: unknown entity `&unknown;` was kept as text",
+                "2:24: unexpected `</ac:rich-text-body>`; it was ignored",
+                "3:5: unclosed `<li>` at end of document",
+                "3:1: unclosed `<ul>` at end of document",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unparseable_markup_is_kept_as_text() {
+        let converter = Converter::new(ParseOptions::default().with_parse_mode(ParseMode::Lenient));
+        let page =
+            converter.convert_page("<p>a < b and c</p><p>x <!-- y</p>\n<p>after &unknown;</p>");
+        assert_eq!(
+            page.markdown(),
+            "a \\< b and c\n\nx \\<!-- y\n\nafter &unknown;"
+        );
+        let diagnostics: Vec<_> = page
+            .metadata()
+            .diagnostics()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                "1:6: `<` does not start a tag; it was kept as text",
+                "1:24: syntax error: comment not closed: `-->` not found before end of input; it was kept as text",
+                "2:10: unknown entity `&unknown;` was kept as text",
+            ]
+        );
+
+        let error = Converter::new(ParseOptions::default())
+            .try_convert("<p>a < b</p>")
+            .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "`<` does not start a tag; it was kept as text"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (c) 2025 Jan Holthuis <jan.holthuis@rub.de>
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See
+// the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this program. If
+// not, see <https://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::document::{Block, ContentRenderer};
+use crate::flavor::MarkdownFlavor;
+use crate::util::{child_elements, find_child, get_tag_name, get_text_content};
+use html2md::{Handle, StructuredPrinter, TagHandler, TagHandlerFactory};
+
+/// Handler for `ac:task-list` and `ac:task` elements, which are rendered as task list items.
+/// Due dates in the task body are handled by the `time` handler.
+pub struct TaskHandler {
+    renderer: ContentRenderer,
+    flavor: MarkdownFlavor,
+}
+
+impl TaskHandler {
+    fn task(&self, task: &Handle, printer: &StructuredPrinter) -> (bool, Vec<Block>) {
+        let complete = find_child(task, "ac:task-status")
+            .is_some_and(|status| get_text_content(&status).trim() == "complete");
+        let content = find_child(task, "ac:task-body")
+            .map(|body| self.renderer.render_children(&body, printer))
+            .unwrap_or_default();
+        (complete, vec![Block::Markdown(content)])
+    }
+}
+
+impl TagHandler for TaskHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let tasks = if get_tag_name(tag).is_some_and(|name| name == "ac:task") {
+            vec![tag.clone()]
+        } else {
+            child_elements(tag, |child| {
+                get_tag_name(child).is_some_and(|name| name == "ac:task")
+            })
+        };
+        let task_list = Block::TaskList {
+            items: tasks.iter().map(|task| self.task(task, printer)).collect(),
+        };
+
+        printer.insert_newline();
+        printer.insert_newline();
+        printer.append_str(&task_list.to_markdown(self.flavor));
+        printer.insert_newline();
+        printer.insert_newline();
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+pub struct TaskHandlerFactory {
+    renderer: ContentRenderer,
+    flavor: MarkdownFlavor,
+}
+
+impl TaskHandlerFactory {
+    pub(crate) fn new(renderer: ContentRenderer, flavor: MarkdownFlavor) -> Self {
+        Self { renderer, flavor }
+    }
+}
+
+impl TagHandlerFactory for TaskHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(TaskHandler {
+            renderer: self.renderer.clone(),
+            flavor: self.flavor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DateFormat, MarkdownFlavor, ParseOptions, parse_confluence};
+
+    #[test]
+    fn test_task_list_with_due_date() {
+        let options = ParseOptions::default().with_date_format(DateFormat::Locale);
+        let md = parse_confluence(
+            r#"<ac:task-list>
+<ac:task><ac:task-id>1</ac:task-id><ac:task-status>complete</ac:task-status><ac:task-body>Write draft</ac:task-body></ac:task>
+<ac:task><ac:task-id>2</ac:task-id><ac:task-status>incomplete</ac:task-status><ac:task-body>Publish by <time datetime="2025-03-01" /></ac:task-body></ac:task>
+</ac:task-list>
+<p>Done.</p>"#,
+            &options,
+        );
+        assert_eq!(
+            md,
+            "- [x] Write draft\n- [ ] Publish by Mar 1, 2025\n\nDone."
+        );
+    }
+
+    #[test]
+    fn test_nested_task_list() {
+        crate::markdown_assert_eq!(
+            r#"<ac:task-list><ac:task><ac:task-status>incomplete</ac:task-status><ac:task-body><p>Release</p>
+<ac:task-list><ac:task><ac:task-status>complete</ac:task-status><ac:task-body>Tag</ac:task-body></ac:task>
+<ac:task><ac:task-status>incomplete</ac:task-status><ac:task-body>Publish</ac:task-body></ac:task></ac:task-list>
+</ac:task-body></ac:task></ac:task-list>"#,
+            "- [ ] Release\n\n  - [x] Tag\n  - [ ] Publish"
+        );
+    }
+
+    #[test]
+    fn test_plain_flavor() {
+        let options = ParseOptions::default().with_flavor(MarkdownFlavor::Plain);
+        let md = parse_confluence(
+            r#"<ac:task-list>
+<ac:task><ac:task-status>complete</ac:task-status><ac:task-body>Tag</ac:task-body></ac:task>
+<ac:task><ac:task-status>incomplete</ac:task-status><ac:task-body>Publish</ac:task-body></ac:task>
+</ac:task-list>"#,
+            &options,
+        );
+        assert_eq!(md, "- ✔ Tag\n- ☐ Publish");
+    }
+}
@@ -13,14 +13,16 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use html2md::{Handle, NodeData};
+use crate::document::Block;
+use crate::flavor::MarkdownFlavor;
+use html2md::{Handle, NodeData, common::get_tag_attr};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ConfluencePageId(usize);
 
 impl From<usize> for ConfluencePageId {
@@ -51,7 +53,7 @@ impl TryFrom<&str> for ConfluencePageId {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConfluenceServer {
     base_url: String,
 }
@@ -106,7 +108,7 @@ impl FromStr for ConfluenceServer {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct JiraServer {
     base_url: String,
 }
@@ -141,23 +143,66 @@ impl From<&str> for JiraServer {
     }
 }
 
-#[derive(Debug, Default, Clone)]
-pub struct JiraServerMap(HashMap<String, JiraServer>);
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct JiraServerMap {
+    servers: HashMap<String, JiraServer>,
+    names: HashMap<String, String>,
+    default_server: Option<JiraServer>,
+}
 
 impl JiraServerMap {
     pub fn insert(&mut self, server_id: String, server: JiraServer) -> Option<JiraServer> {
-        self.0.insert(server_id, server)
+        self.servers.insert(server_id, server)
+    }
+
+    /// Registers the display name (the `server` macro parameter) of the server with the given id.
+    pub fn insert_name(&mut self, server_name: String, server_id: String) {
+        self.names.insert(server_name.to_lowercase(), server_id);
+    }
+
+    pub fn set_default(&mut self, server: JiraServer) {
+        self.default_server = Some(server);
     }
 
     pub fn by_id<S: AsRef<str>>(&self, server_id: S) -> Option<&JiraServer> {
-        self.0.get(server_id.as_ref())
+        self.servers.get(server_id.as_ref())
+    }
+
+    pub fn by_name<S: AsRef<str>>(&self, server_name: S) -> Option<&JiraServer> {
+        self.names
+            .get(&server_name.as_ref().to_lowercase())
+            .and_then(|server_id| self.by_id(server_id))
+    }
+
+    pub fn default_server(&self) -> Option<&JiraServer> {
+        self.default_server.as_ref()
+    }
+
+    /// Returns a description of the servers that does not depend on the iteration order of the
+    /// maps, e.g. for hashing.
+    pub(crate) fn canonical_description(&self) -> String {
+        let servers: BTreeMap<_, _> = self.servers.iter().collect();
+        let names: BTreeMap<_, _> = self.names.iter().collect();
+        format!("{servers:?} {names:?} {:?}", self.default_server)
+    }
+
+    /// Resolve a server by id, falling back to the display name and then to the default server.
+    pub fn resolve(
+        &self,
+        server_id: Option<&str>,
+        server_name: Option<&str>,
+    ) -> Option<&JiraServer> {
+        server_id
+            .and_then(|server_id| self.by_id(server_id))
+            .or_else(|| server_name.and_then(|server_name| self.by_name(server_name)))
+            .or(self.default_server.as_ref())
     }
 }
 
 impl From<&[(&str, &str)]> for JiraServerMap {
     fn from(servers: &[(&str, &str)]) -> Self {
-        Self(
-            servers
+        Self {
+            servers: servers
                 .iter()
                 .map(|(server_id, base_url)| {
                     (
@@ -166,7 +211,8 @@ impl From<&[(&str, &str)]> for JiraServerMap {
                     )
                 })
                 .collect(),
-        )
+            ..Default::default()
+        }
     }
 }
 
@@ -177,6 +223,149 @@ pub fn get_tag_name(tag: &Handle) -> Option<String> {
     }
 }
 
+/// Returns the documented default parameter of the macro, i.e. the one that legacy `ac:macro`
+/// elements store in `ac:default-parameter` and structured macros in a parameter without name.
+fn default_parameter_name(macro_name: &str) -> Option<&'static str> {
+    match macro_name {
+        "code" => Some("language"),
+        "jira" => Some("key"),
+        "status" => Some("title"),
+        "info" | "tip" | "note" | "warning" | "expand" => Some("title"),
+        "column" => Some("width"),
+        "contentbylabel" => Some("labels"),
+        "view-file" | "viewpdf" | "viewdoc" | "viewppt" | "viewxls" | "gliffy" => Some("name"),
+        "drawio" => Some("diagramName"),
+        "widget" => Some("url"),
+        _ => None,
+    }
+}
+
+/// Returns the parameter elements of the macro by name. The default parameter is returned under
+/// the name of the macro's documented default parameter (or the empty name if there is none).
+pub fn macro_parameters(tag: &Handle) -> Vec<(String, Handle)> {
+    let default_name = get_tag_attr(tag, "ac:name")
+        .and_then(|name| default_parameter_name(&name))
+        .unwrap_or_default();
+    let mut parameters: Vec<_> = child_elements(tag, |_| true)
+        .into_iter()
+        .filter_map(|child| {
+            let name = match get_tag_name(&child).as_deref() {
+                Some("ac:parameter") => get_tag_attr(&child, "ac:name")
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| default_name.to_string()),
+                Some("ac:default-parameter") => default_name.to_string(),
+                _ => return None,
+            };
+            Some((name, child))
+        })
+        .collect();
+    // Explicitly named parameters take precedence over the default parameter.
+    parameters.sort_by_key(|(_, parameter)| {
+        get_tag_attr(parameter, "ac:name").is_none_or(|name| name.is_empty())
+    });
+    parameters
+}
+
+/// Returns the macro parameter element with the given name, e.g. to access resource identifiers
+/// like `ri:attachment` in its content.
+pub fn get_macro_parameter_element(tag: &Handle, name: &str) -> Option<Handle> {
+    macro_parameters(tag)
+        .into_iter()
+        .find(|(parameter_name, _)| parameter_name == name)
+        .map(|(_, parameter)| parameter)
+}
+
+/// Returns the text content of the macro parameter with the given name, if present and non-empty.
+pub fn get_macro_parameter(tag: &Handle, name: &str) -> Option<String> {
+    get_macro_parameter_element(tag, name)
+        .map(|parameter| get_text_content(&parameter))
+        .filter(|value| !value.is_empty())
+}
+
+/// Returns the first child element with the given tag name.
+pub fn find_child(tag: &Handle, name: &str) -> Option<Handle> {
+    tag.children
+        .borrow()
+        .iter()
+        .find(|child| get_tag_name(child).is_some_and(|tag_name| tag_name == name))
+        .cloned()
+}
+
+/// Returns the text of the tag and its descendants without collapsing whitespace.
+pub fn get_raw_text_content(tag: &Handle) -> String {
+    let mut text = match tag.data {
+        NodeData::Text { ref contents } => contents.borrow().to_string(),
+        _ => String::new(),
+    };
+    for child in tag.children.borrow().iter() {
+        text.push_str(&get_raw_text_content(child));
+    }
+    text
+}
+
+/// Returns the content of the macro's `ac:plain-text-body` (e.g. code or diagram source), without
+/// leading and trailing blank lines.
+pub fn get_plain_text_body(tag: &Handle) -> Option<String> {
+    find_child(tag, "ac:plain-text-body")
+        .map(|body| {
+            get_raw_text_content(&body)
+                .trim_start_matches(['\n', '\r'])
+                .trim_end()
+                .to_string()
+        })
+        .filter(|body| !body.is_empty())
+}
+
+/// Returns the parent node of the tag, if any.
+pub fn get_parent(tag: &Handle) -> Option<Handle> {
+    let parent = tag.parent.take();
+    tag.parent.set(parent.clone());
+    parent.and_then(|parent| parent.upgrade())
+}
+
+/// Returns the child elements of the tag that match the predicate.
+pub fn child_elements<P: Fn(&Handle) -> bool>(tag: &Handle, predicate: P) -> Vec<Handle> {
+    tag.children
+        .borrow()
+        .iter()
+        .filter(|child| get_tag_name(child).is_some() && predicate(child))
+        .cloned()
+        .collect()
+}
+
+/// Escapes the characters that have a special meaning in HTML text and attribute values.
+pub fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Splits a comma-separated macro parameter value into its trimmed, non-empty items.
+pub fn split_list_parameter(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Renders a Markdown table with the given header and rows.
+pub fn markdown_table<S: AsRef<str>, T: AsRef<str>>(header: &[S], rows: &[Vec<T>]) -> String {
+    let table = Block::Table {
+        header: header
+            .iter()
+            .map(|cell| cell.as_ref().to_string())
+            .collect(),
+        rows: rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.as_ref().to_string()).collect())
+            .collect(),
+    };
+    format!("{}\n", table.to_markdown(MarkdownFlavor::default()))
+}
+
 lazy_static! {
     static ref EXCESSIVE_WHITESPACE_PATTERN: Regex = Regex::new("\\s{2,}").unwrap();   // for HTML on-the-fly cleanup
 }